@@ -3,46 +3,567 @@ use std::io::Write;
 use std::{
     cell::RefCell,
     io::{stdin, stdout},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener},
+    path::PathBuf,
     rc::Rc,
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
 };
 
-use azulrs::model::player::Player;
-use azulrs::model::view::GameView;
+use azulrs::model::bag::Bag;
+use azulrs::model::clock::TimeControl;
+use azulrs::model::local::{local_players, replay, LocalGame};
+use azulrs::model::rules::Rules;
 use azulrs::model::Game;
-use azulrs::visor::backend::TermionBackend;
-use azulrs::visor::{Engine, UserInput};
-use termion::input::TermRead;
-use termion::raw::IntoRawMode;
+use azulrs::net::client::{self, Connection, NetworkEvent, RemoteGame};
+use azulrs::net::correspondence::{CorrespondenceTurn, SeenGames, TurnFile};
+use azulrs::net::discovery::{
+    broadcast_address, Announcer, Discovered, Discovery, FindGames, FindGamesChoice,
+    ANNOUNCE_INTERVAL, DISCOVERY_PORT,
+};
+use azulrs::net::http::{HttpApi, DEFAULT_HTTP_ADDRESS};
+use azulrs::net::peer::{Mesh, PeerEvent, PeerGame};
+use azulrs::net::protocol::TableId;
+use azulrs::net::server::Server;
+use azulrs::net::telnet::TelnetGateway;
+use azulrs::net::view::FindGamesView;
+use azulrs::visor::backend::{TermionBackend, UNBOUNDED};
+use azulrs::visor::events::{Event, EventLoop, Flow};
+use azulrs::visor::input::{Recorder, Recording, TerminalInput};
+use azulrs::visor::keymap::Keymap;
+use azulrs::visor::theme::{utf8_locale, Theme};
+use azulrs::visor::{Component, Engine, UserInput};
+use termion::{input::MouseTerminal, raw::IntoRawMode};
 
-fn main() {
-    let players = [
-        Player::default_with_name("Alice".into()),
-        Player::default_with_name("Bob".into()),
-    ];
-    let game = Rc::new(RefCell::new(Game::for_players(players)));
-    let game_view = GameView { game: game.clone() };
-    let backend = TermionBackend::new(Box::new(stdout()));
-    let mut engine = Engine::new(backend, Box::new(game_view) as Box<_>);
-
-    let stdin = stdin();
-    let mut stdout = stdout().into_raw_mode().unwrap();
+const DEFAULT_ADDRESS: &str = "0.0.0.0:4242";
+const DEFAULT_SERVER_NAME: &str = "azulrs";
+/// How often the clocks are checked and redrawn
+const CLOCK_TICK: Duration = Duration::from_millis(200);
+const RECONNECT_ATTEMPTS: usize = 10;
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Where the server itself reaches the lobby: on this machine when it listens everywhere
+fn lobby_address(local: SocketAddr) -> SocketAddr {
+    match local.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => (Ipv4Addr::LOCALHOST, local.port()).into(),
+        IpAddr::V6(ip) if ip.is_unspecified() => (Ipv6Addr::LOCALHOST, local.port()).into(),
+        _ => local,
+    }
+}
+
+fn serve(address: &str, telnet_address: Option<&str>) {
+    let mut server = Server::bind(address).unwrap();
+    if let Ok(dir) = std::env::var("AZULRS_JOURNAL") {
+        println!("Keeping the tables in {dir}");
+        server = server.with_journal(dir);
+    }
+    let local = server.local_addr().unwrap();
+    println!("Waiting for players on {local}");
+    let lobby = lobby_address(local);
+    if local.ip().is_loopback() {
+        println!("Not announcing the tables, only this machine can reach them");
+    } else {
+        let name = std::env::var("AZULRS_SERVER_NAME").unwrap_or(DEFAULT_SERVER_NAME.to_owned());
+        match Announcer::new(&name, lobby, broadcast_address()) {
+            Ok(announcer) => {
+                thread::spawn(move || announcer.run());
+            }
+            Err(e) => eprintln!("Could not announce the tables on the network: {e}"),
+        }
+    }
+    if let Some(telnet_address) = telnet_address {
+        let gateway = TelnetGateway::bind(telnet_address, lobby).unwrap();
+        println!(
+            "Telnet players can connect to {}",
+            gateway.local_addr().unwrap()
+        );
+        thread::spawn(move || gateway.run().unwrap());
+    }
+    server.run().unwrap();
+}
+
+fn serve_api(address: &str) {
+    let api = HttpApi::bind(address).unwrap();
+    println!(
+        "Serving the HTTP API on http://{}",
+        api.local_addr().unwrap()
+    );
+    api.run().unwrap();
+}
+
+enum ClientEvent {
+    Network(NetworkEvent),
+    Announced(Discovered),
+    Peer(PeerEvent),
+    /// Sent a while after losing the connection, to try and get it back
+    Reconnect,
+}
+
+impl From<NetworkEvent> for ClientEvent {
+    fn from(event: NetworkEvent) -> Self {
+        ClientEvent::Network(event)
+    }
+}
+
+impl From<Discovered> for ClientEvent {
+    fn from(discovered: Discovered) -> Self {
+        ClientEvent::Announced(discovered)
+    }
+}
+
+impl From<PeerEvent> for ClientEvent {
+    fn from(event: PeerEvent) -> Self {
+        ClientEvent::Peer(event)
+    }
+}
+
+// Only one thread reads the keys, for every screen we show
+fn key_events() -> EventLoop<ClientEvent> {
+    let events = EventLoop::new();
+    events.forward_keys(stdin());
+    events.forward_resizes();
+    events
+}
+
+fn show_tables(address: &str) {
+    for table in client::list_tables(address).unwrap() {
+        let state = if table.started { "playing" } else { "open" };
+        println!(
+            "Table {}: {}/{} players ({}), {} factories, {}",
+            table.id,
+            table.seats.len(),
+            table.players,
+            table.seats.join(", "),
+            table.rules.factories,
+            state
+        );
+    }
+}
+
+fn create(address: &str, players: usize, rules: Rules) -> TableId {
+    let table = client::create_table(address, players, rules, None).unwrap();
+    println!("Created table {table}");
+    table
+}
+
+fn connect(address: &str, name: &str, table: Option<TableId>) {
+    let connection = match table {
+        Some(table) => Connection::open(address, name, Some(table)),
+        None => Connection::join_any(address, name),
+    };
+    play_connection(connection.unwrap(), &mut key_events());
+}
+
+fn watch(address: &str, name: &str, table: Option<TableId>) {
+    play_connection(
+        Connection::spectate(address, name, table).unwrap(),
+        &mut key_events(),
+    );
+}
+
+fn find(name: &str) {
+    let mut events = key_events();
+    Discovery::bind((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT))
+        .unwrap()
+        .listen(events.sender());
+    let games = Rc::new(RefCell::new(FindGames::default()));
+    let mut engine = terminal_engine(Box::new(FindGamesView::new(games.clone())));
+
+    let mut raw = stdout().into_raw_mode().unwrap();
+    write!(raw, "{}", termion::cursor::Hide).unwrap();
+    // Stale servers go away even when nothing else happens
+    events.set_tick(Some(ANNOUNCE_INTERVAL));
+    let mut choice = FindGamesChoice::Quit;
+    engine.run(&mut events, |event| {
+        if let Some(input) = event.input(keymap()) {
+            if let Some(picked) = games.borrow_mut().handle_input(input) {
+                choice = picked;
+                return Flow::Quit;
+            }
+        } else if let Event::External(ClientEvent::Announced(discovered)) = event {
+            games.borrow_mut().update(discovered, Instant::now());
+        }
+        games.borrow_mut().forget_stale(Instant::now());
+        Flow::Continue
+    });
+    events.set_tick(None);
+    write!(raw, "{}", termion::cursor::Show).unwrap();
+    drop(raw);
+
+    if let FindGamesChoice::Join(address, table) = choice {
+        let connection = Connection::open(address, name, Some(table)).unwrap();
+        play_connection(connection, &mut events);
+    }
+}
+
+fn play_with_peers(listen: &str, name: &str, players: usize, peers: &[SocketAddr]) {
+    let listener = TcpListener::bind(listen).unwrap();
+    println!(
+        "Waiting for {} peers on {}",
+        players - 1,
+        listener.local_addr().unwrap()
+    );
+    let mut mesh = or_exit(
+        Mesh::connect(&listener, name, players, peers)
+            .map_err(|e| format!("Could not reach the peers: {e}")),
+    );
+    let seed = or_exit(
+        mesh.agree_on_seed(rand::random())
+            .map_err(|e| format!("Could not agree on a seed with the peers: {e}")),
+    );
+    let mut events = key_events();
+    match players {
+        2 => play_peer_game::<2>(mesh, seed, &mut events),
+        3 => play_peer_game::<3>(mesh, seed, &mut events),
+        4 => play_peer_game::<4>(mesh, seed, &mut events),
+        n => panic!("A game is for 2 to 4 players, not {n}"),
+    }
+}
+
+fn play_peer_game<const N: usize>(mesh: Mesh, seed: u64, events: &mut EventLoop<ClientEvent>) {
+    let mut peer = PeerGame::<N>::new(mesh, seed, events.sender()).unwrap();
+    let mut engine = terminal_engine(Box::new(peer.view()));
+
+    let mut stdout = MouseTerminal::from(stdout().into_raw_mode().unwrap());
     write!(stdout, "{}", termion::cursor::Hide).unwrap();
+    engine.run(events, |event| {
+        if let Some(input) = event.input(keymap()) {
+            if peer.handle_input(input) {
+                return Flow::Quit;
+            }
+        } else if let Event::External(ClientEvent::Peer(event)) = event {
+            peer.handle_peer_event(event);
+        }
+        Flow::Continue
+    });
+    write!(stdout, "{}", termion::cursor::Show).unwrap();
+}
 
-    engine.render();
-    for c in stdin.keys() {
-        stdout.flush().unwrap();
+fn play_connection(connection: Connection, events: &mut EventLoop<ClientEvent>) {
+    match connection.players() {
+        2 => play_remotely::<2>(connection, events),
+        3 => play_remotely::<3>(connection, events),
+        4 => play_remotely::<4>(connection, events),
+        n => or_exit(Err(format!(
+            "The server wants to play with {n} players, that is not supported"
+        ))),
+    }
+}
 
-        let input = match c.unwrap() {
-            termion::event::Key::Backspace | termion::event::Key::Esc => UserInput::Back,
-            termion::event::Key::Char(c) => UserInput::from_char(c),
-            _ => UserInput::Noop,
-        };
+fn play_remotely<const N: usize>(connection: Connection, events: &mut EventLoop<ClientEvent>) {
+    let sender = events.sender();
+    let timer = events.timer();
+    let mut remote = RemoteGame::<N>::new(connection, sender.clone()).unwrap();
+    let mut reconnect_attempts = 0;
+    let mut engine = terminal_engine(Box::new(remote.view()));
+
+    let mut stdout = MouseTerminal::from(stdout().into_raw_mode().unwrap());
+    write!(stdout, "{}", termion::cursor::Hide).unwrap();
+    engine.run(events, |event| {
+        match event {
+            Event::Key(key) => {
+                let input = if remote.is_typing() {
+                    UserInput::from_text_key(key)
+                } else {
+                    keymap().map(key)
+                };
+                if remote.handle_input(input) {
+                    return Flow::Quit;
+                }
+            }
+            Event::Input(input) => {
+                if remote.handle_input(input) {
+                    return Flow::Quit;
+                }
+            }
+            Event::External(ClientEvent::Network(event)) => {
+                let lost = matches!(event, NetworkEvent::Disconnected);
+                remote.handle_network_event(event);
+                // Spectators have no seat to take back
+                if lost && remote.status.borrow().seat.is_some() {
+                    reconnect_attempts = 0;
+                    timer.send_after(RECONNECT_DELAY, ClientEvent::Reconnect);
+                }
+            }
+            Event::External(ClientEvent::Reconnect) => {
+                // The server holds our seat for a while, so keep trying for a bit before giving
+                // up. The screen stays alive in between, the player can still quit.
+                reconnect_attempts += 1;
+                if remote.reconnect(sender.clone()).is_err()
+                    && reconnect_attempts < RECONNECT_ATTEMPTS
+                {
+                    timer.send_after(RECONNECT_DELAY, ClientEvent::Reconnect);
+                }
+            }
+            // Still listening from the "find games" screen
+            Event::External(ClientEvent::Announced(_) | ClientEvent::Peer(_))
+            | Event::Tick
+            | Event::Resize
+            | Event::Closed => {}
+        }
+        Flow::Continue
+    });
+    write!(stdout, "{}", termion::cursor::Show).unwrap();
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // Read the config before the terminal goes raw, so that mistakes show up properly
+    keymap();
+    theme();
+    match args.first().map(String::as_str) {
+        Some("serve") => serve(
+            args.get(1).map_or(DEFAULT_ADDRESS, String::as_str),
+            args.get(2).map(String::as_str),
+        ),
+        Some("tables") => show_tables(args.get(1).expect("Usage: azulrs tables <address>")),
+        Some("create") => {
+            let usage = "Usage: azulrs create <address> <players> [factories]";
+            let address = args.get(1).expect(usage);
+            let players = args.get(2).and_then(|p| p.parse().ok()).expect(usage);
+            let mut rules = Rules::default();
+            if let Some(factories) = args.get(3) {
+                rules.factories = factories.parse().expect(usage);
+            }
+            create(address, players, rules);
+        }
+        Some("connect") => {
+            let usage = "Usage: azulrs connect <address> <name> [table]";
+            let address = args.get(1).expect(usage);
+            let name = args.get(2).expect(usage);
+            let table = args.get(3).map(|t| t.parse().expect(usage));
+            connect(address, name, table);
+        }
+        Some("watch") => {
+            let usage = "Usage: azulrs watch <address> [name] [table]";
+            let address = args.get(1).expect(usage);
+            let name = args.get(2).map_or("Spectator", String::as_str);
+            let table = args.get(3).map(|t| t.parse().expect(usage));
+            watch(address, name, table);
+        }
+        Some("peer") => {
+            let usage = "Usage: azulrs peer <address> <name> <players> [peer address...]";
+            let address = args.get(1).expect(usage);
+            let name = args.get(2).expect(usage);
+            let players = args.get(3).and_then(|p| p.parse().ok()).expect(usage);
+            let peers: Vec<SocketAddr> =
+                args[4..].iter().map(|a| a.parse().expect(usage)).collect();
+            play_with_peers(address, name, players, &peers);
+        }
+        Some("api") => serve_api(args.get(1).map_or(DEFAULT_HTTP_ADDRESS, String::as_str)),
+        Some("mail") => {
+            let usage = "Usage: azulrs mail new <file> <player...> | azulrs mail <file> <name>";
+            match (args.get(1).map(String::as_str), args.get(2)) {
+                (Some("new"), Some(path)) => new_mail_game(path, args[3..].to_vec()),
+                (Some(path), Some(name)) => play_mail_turn(path, name),
+                _ => panic!("{usage}"),
+            }
+        }
+        Some("find") => find(args.get(1).expect("Usage: azulrs find <name>")),
+        Some("clock") => {
+            let usage = "Usage: azulrs clock <minutes>+<seconds> [forfeit|first-move|floor]";
+            let mut control: TimeControl = or_exit(args.get(1).expect(usage).parse());
+            if let Some(policy) = args.get(2) {
+                control.on_timeout = or_exit(policy.parse());
+            }
+            play_locally(Some(control), None);
+        }
+        Some("record") => play_locally(
+            None,
+            Some(args.get(1).expect("Usage: azulrs record <file>")),
+        ),
+        Some("keys") => {
+            for line in keymap().help() {
+                println!("{line}");
+            }
+        }
+        Some("replay") => replay_recording(args.get(1).expect("Usage: azulrs replay <file>")),
+        _ => play_locally(None, None),
+    }
+}
+
+/// Stops with a message meant for the player, rather than a panic
+fn or_exit<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|error| {
+        eprintln!("{error}");
+        std::process::exit(1)
+    })
+}
+
+/// An engine that draws on the terminal, in the theme of the player
+fn terminal_engine<'a>(root: Box<dyn Component + 'a>) -> Engine<'a, TermionBackend> {
+    Engine::new(TermionBackend::for_terminal(Box::new(stdout())), root).with_theme(theme().clone())
+}
 
-        let is_over = game.borrow_mut().handle(input);
-        if is_over {
-            break;
+/// The theme from `AZULRS_THEME`, a preset or a file, or from `~/.config/azulrs/theme.json`, in
+/// ASCII when the terminal cannot do better
+fn theme() -> &'static Theme {
+    static THEME: OnceLock<Theme> = OnceLock::new();
+    THEME.get_or_init(|| {
+        let theme = chosen_theme();
+        if ascii_only() {
+            theme.to_ascii()
+        } else {
+            theme
         }
-        engine.render();
+    })
+}
+
+/// The theme the player asked for
+fn chosen_theme() -> Theme {
+    if let Ok(theme) = std::env::var("AZULRS_THEME") {
+        if Theme::PRESETS.contains(&theme.as_str()) {
+            return or_exit(Theme::preset(&theme));
+        }
+        return or_exit(Theme::load(theme));
+    }
+    let home = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
+    let path = PathBuf::from(format!("{home}/.config/azulrs/theme.json"));
+    if path.exists() {
+        or_exit(Theme::load(path))
+    } else {
+        Theme::default()
     }
+}
+
+/// `AZULRS_ASCII=1` draws in ASCII only, `AZULRS_ASCII=0` never does, and otherwise it depends on
+/// the locale
+fn ascii_only() -> bool {
+    match std::env::var("AZULRS_ASCII").as_deref() {
+        Ok("1" | "true" | "yes") => true,
+        Ok("0" | "false" | "no") => false,
+        _ => !utf8_locale(|name| std::env::var(name).ok()),
+    }
+}
+
+/// The keys from `AZULRS_KEYS`, or from `~/.config/azulrs/keys.json` if there is one
+fn keymap() -> &'static Keymap {
+    static KEYMAP: OnceLock<Keymap> = OnceLock::new();
+    KEYMAP.get_or_init(|| {
+        if let Ok(path) = std::env::var("AZULRS_KEYS") {
+            return or_exit(Keymap::load(path));
+        }
+        let home = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
+        let path = PathBuf::from(format!("{home}/.config/azulrs/keys.json"));
+        if path.exists() {
+            or_exit(Keymap::load(path))
+        } else {
+            Keymap::default()
+        }
+    })
+}
+
+fn seen_games() -> SeenGames {
+    let dir = std::env::var("AZULRS_MAIL_DIR").unwrap_or_else(|_| {
+        let home = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
+        format!("{home}/.azulrs/mail")
+    });
+    SeenGames::open(dir).unwrap()
+}
+
+fn new_mail_game(path: &str, players: Vec<String>) {
+    let file = or_exit(TurnFile::new(
+        players,
+        Rules::default(),
+        rand::random(),
+        rand::random(),
+    ));
+    std::fs::write(path, file.to_json()).unwrap();
+    println!("Send {path} to {}, who plays first", file.name(0));
+}
+
+fn play_mail_turn(path: &str, name: &str) {
+    let contents = std::fs::read_to_string(path).unwrap();
+    let file = or_exit(TurnFile::from_json(&contents));
+    let seen = seen_games();
+    if let Some(last) = seen.get(file.game, name).unwrap() {
+        or_exit(file.check_follows(&last));
+    }
+    match file.players.len() {
+        2 => play_mail_game::<2>(path, name, file, &seen),
+        3 => play_mail_game::<3>(path, name, file, &seen),
+        4 => play_mail_game::<4>(path, name, file, &seen),
+        n => or_exit(Err(format!("A game is for 2 to 4 players, not {n}"))),
+    }
+}
+
+fn play_mail_game<const N: usize>(path: &str, name: &str, file: TurnFile, seen: &SeenGames) {
+    let mut turn = or_exit(CorrespondenceTurn::<N>::new(file, name));
+    // Even without a move, this file is the newest we saw
+    seen.save(name, &turn.file.seen()).unwrap();
+    let mut engine = terminal_engine(Box::new(turn.view()));
+
+    let mut stdout = MouseTerminal::from(stdout().into_raw_mode().unwrap());
+    write!(stdout, "{}", termion::cursor::Hide).unwrap();
+    engine.run(&mut key_events(), |event| match event.input(keymap()) {
+        Some(input) if turn.handle_input(input) => Flow::Quit,
+        _ => Flow::Continue,
+    });
     write!(stdout, "{}", termion::cursor::Show).unwrap();
+    drop(stdout);
+    if !turn.has_played() {
+        println!("\r\nNothing was played, {path} is unchanged");
+        return;
+    }
+    std::fs::write(path, turn.file.to_json()).unwrap();
+    seen.save(name, &turn.file.seen()).unwrap();
+    let game = turn.game.borrow();
+    if game.is_over() {
+        println!("\r\nThe game is over, send {path} to everybody");
+    } else {
+        let next = turn.file.name(game.get_current_player_id());
+        println!("\r\nYour move is in {path}, send it to {next}");
+    }
+}
+
+fn play_locally(control: Option<TimeControl>, record: Option<&str>) {
+    let seed = rand::random();
+    let game = Game::for_players_with_bag(local_players(), Bag::seeded(seed));
+    let mut local = LocalGame::new(game).with_keymap(keymap().clone());
+    let mut events = EventLoop::<ClientEvent>::new();
+    events.forward_resizes();
+    if let Some(control) = control {
+        local = local.with_clocks(control, Instant::now());
+        // The clocks keep ticking while nobody types
+        events.set_tick(Some(CLOCK_TICK));
+    }
+    let input = TerminalInput::new(stdin()).with_keymap(keymap().clone());
+    match record {
+        Some(path) => {
+            let file = std::fs::File::create(path).unwrap();
+            let size = termion::terminal_size().unwrap_or(UNBOUNDED);
+            events.forward_input(Recorder::new(input, file, seed, size).unwrap());
+        }
+        None => events.forward_input(input),
+    }
+    let mut engine = terminal_engine(Box::new(local.view()));
+
+    let mut stdout = MouseTerminal::from(stdout().into_raw_mode().unwrap());
+    write!(stdout, "{}", termion::cursor::Hide).unwrap();
+    engine.run(&mut events, |event| {
+        let done = match event.input(keymap()) {
+            Some(input) => local.handle_input(input, Instant::now()),
+            None => local.tick(Instant::now()),
+        };
+        if done {
+            Flow::Quit
+        } else {
+            Flow::Continue
+        }
+    });
+    write!(stdout, "{}", termion::cursor::Show).unwrap();
+    drop(stdout);
+    if let Some(seat) = local.forfeit() {
+        let name = local.game.borrow().get_players()[seat]
+            .get_name()
+            .to_owned();
+        println!("\r\n{name} ran out of time and loses the game");
+    }
+}
+
+fn replay_recording(path: &str) {
+    let contents = std::fs::read_to_string(path).unwrap();
+    let recording = or_exit(Recording::read(&contents));
+    println!("{}", replay(&recording));
 }
@@ -1,16 +1,40 @@
 use super::{Factory, Tile, TILE_PER_FACTORY};
-use rand::{prelude::SliceRandom, thread_rng};
+use rand::{prelude::SliceRandom, rngs::StdRng, SeedableRng};
 use std::cmp::min;
 
 #[derive(Debug)]
 pub struct Bag {
     tiles: Vec<Tile>,
     discards: Vec<Tile>,
+    // Every shuffle comes from here, so a seeded bag always deals the same tiles
+    rng: StdRng,
 }
 
 impl Bag {
-    pub fn new(tiles: Vec<Tile>, discards: Vec<Tile>) -> Self {
-        Self { tiles, discards }
+    /// A bag that draws `tiles` in order, the discards are reshuffled from `seed`.
+    pub fn new(tiles: Vec<Tile>, discards: Vec<Tile>, seed: u64) -> Self {
+        Self {
+            tiles,
+            discards,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// A full, shuffled bag. The same seed always gives the same game.
+    pub fn seeded(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut tiles = Vec::with_capacity(100);
+        tiles.append(&mut vec![Tile::Yellow; 20]);
+        tiles.append(&mut vec![Tile::Blue; 20]);
+        tiles.append(&mut vec![Tile::Red; 20]);
+        tiles.append(&mut vec![Tile::White; 20]);
+        tiles.append(&mut vec![Tile::Green; 20]);
+        tiles.shuffle(&mut rng);
+        Bag {
+            tiles,
+            discards: vec![],
+            rng,
+        }
     }
 
     fn draw(&mut self, how_many: usize) -> Vec<Tile> {
@@ -19,29 +43,32 @@ impl Bag {
         drawn
     }
 
+    /// Fills an empty factory. When the bag and the discards together have too few tiles left,
+    /// the factory stays empty and the tiles stay in the bag.
     pub fn fill_factory(&mut self, factory: &mut Factory) {
-        let mut new_tiles = match factory.0 {
-            Some(_) => panic!("You tried filling a non-empty factory"),
-            None => {
-                let mut some_tiles = self.draw(TILE_PER_FACTORY);
-                if some_tiles.len() == TILE_PER_FACTORY {
-                    some_tiles
-                } else {
-                    self.reshuffle();
-                    let mut rest = self.draw(TILE_PER_FACTORY - some_tiles.len());
-                    rest.append(&mut some_tiles);
-                    rest
-                }
+        if factory.0.is_some() {
+            panic!("You tried filling a non-empty factory");
+        }
+        let mut new_tiles = self.draw(TILE_PER_FACTORY);
+        if new_tiles.len() < TILE_PER_FACTORY {
+            self.reshuffle();
+            let mut rest = self.draw(TILE_PER_FACTORY - new_tiles.len());
+            rest.append(&mut new_tiles);
+            new_tiles = rest;
+        }
+        match <[Tile; TILE_PER_FACTORY]>::try_from(new_tiles) {
+            Ok(mut tiles) => {
+                tiles.sort();
+                factory.0 = Some(tiles);
             }
-        };
-        assert!(new_tiles.len() == TILE_PER_FACTORY);
-        new_tiles.sort();
-        factory.0 = Some(new_tiles.try_into().unwrap());
+            Err(left) => self.tiles.extend(left),
+        }
     }
 
     fn reshuffle(&mut self) {
-        self.tiles = self.discards.drain(..).collect();
-        self.tiles.shuffle(&mut thread_rng());
+        let mut discards: Vec<_> = self.discards.drain(..).collect();
+        discards.shuffle(&mut self.rng);
+        self.tiles.append(&mut discards);
     }
 
     // restrict visibility on this to some kind of pub(in mod::struct)?
@@ -51,17 +78,8 @@ impl Bag {
 }
 
 impl Default for Bag {
+    /// The only place where a bag picks its own seed
     fn default() -> Self {
-        let mut tiles = Vec::with_capacity(100);
-        tiles.append(&mut vec![Tile::Yellow; 20]);
-        tiles.append(&mut vec![Tile::Blue; 20]);
-        tiles.append(&mut vec![Tile::Red; 20]);
-        tiles.append(&mut vec![Tile::White; 20]);
-        tiles.append(&mut vec![Tile::Green; 20]);
-        tiles.shuffle(&mut thread_rng());
-        Bag {
-            tiles,
-            discards: vec![],
-        }
+        Self::seeded(rand::random())
     }
 }
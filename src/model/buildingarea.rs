@@ -1,4 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 use crate::visor::{
+    grid::Grid,
     layout::Layout,
     renderer::{self, RootedRenderer},
     view::{PanelBuilder, TextView},
@@ -11,13 +14,14 @@ use self::{
     wall::{FillResult, Wall, WallView},
 };
 
-use super::{player::Player, CommonArea, Factory, Tile};
+use super::{player::Player, zobrist::ZobristHash, CommonArea, Factory, Tile};
 
 pub mod floorline;
 pub mod patternline;
 pub mod wall;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Slot {
     Filled(Tile),
     Free(Tile),
@@ -25,6 +29,15 @@ pub enum Slot {
 
 type InProgress = [PatternLine; 5];
 
+/// Where the tiles picked by a player end up
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Destination {
+    PatternLine(usize),
+    FloorLine,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BuildingArea {
     in_progress: InProgress,
     wall: Wall,
@@ -58,11 +71,22 @@ impl From<IsGameOver> for bool {
 }
 
 impl BuildingArea {
-    pub(super) fn move_tiles_to_wall(&mut self) -> IsGameOver {
+    /// Moves a tile of every full pattern line to the wall, the other tiles of the line go to
+    /// `discards`
+    pub(super) fn move_tiles_to_wall(
+        &mut self,
+        player_id: usize,
+        zobrist: &mut ZobristHash,
+        discards: &mut Vec<Tile>,
+    ) -> IsGameOver {
         let mut is_game_over = IsGameOver::No;
         for (i, pl) in &mut self.in_progress.iter_mut().enumerate() {
             if pl.is_full() {
+                *zobrist ^= pl.zobrist(player_id, i);
                 let tile = pl.flush();
+                // Pattern line i holds i + 1 tiles
+                discards.extend(std::iter::repeat_n(tile, i));
+                *zobrist ^= self.wall.zobrist_for_slot(player_id, i, tile);
                 if let FillResult::PointsGainedAndGameOver(_) = self.wall.fill_slot(i, tile) {
                     is_game_over = IsGameOver::Yes;
                 }
@@ -71,8 +95,13 @@ impl BuildingArea {
         is_game_over
     }
 
-    pub fn flush_floorline(&mut self) {
+    /// Takes the points off for the floor line and empties it, returns the tiles that were on
+    /// it. The first player tile is not one of them, it goes back to the common area.
+    #[must_use]
+    pub fn flush_floorline(&mut self) -> Vec<Tile> {
+        let tiles = self.floorline.tiles();
         self.wall.reset_floorline(&mut self.floorline);
+        tiles
     }
 
     pub fn get_row(&self, row_number: usize) -> &PatternLine {
@@ -96,6 +125,26 @@ impl BuildingArea {
             .collect()
     }
 
+    /// All the places tiles of a color can go to, the floorline always accepts everything.
+    pub fn get_destinations_for(&self, tile: Tile) -> Vec<Destination> {
+        let mut destinations: Vec<_> = self
+            .get_rows_that_can_accept(tile)
+            .into_iter()
+            .map(Destination::PatternLine)
+            .collect();
+        destinations.push(Destination::FloorLine);
+        destinations
+    }
+
+    pub fn can_place(&self, what: Tile, destination: Destination) -> bool {
+        match destination {
+            Destination::PatternLine(row_number) => {
+                row_number < self.in_progress.len() && self.can_accept(what, row_number)
+            }
+            Destination::FloorLine => true,
+        }
+    }
+
     pub fn can_accept(&self, what: Tile, row_number: usize) -> bool {
         // Is the tile already filled on the right side?
         match self.wall.find_slot_for_tile(what, row_number) {
@@ -111,12 +160,43 @@ impl BuildingArea {
         &self.floorline
     }
 
+    pub fn get_wall(&self) -> &Wall {
+        &self.wall
+    }
+
+    // Returns how many tiles did not fit into the destination
+    fn accept_into(
+        &mut self,
+        destination: Destination,
+        tile: Tile,
+        how_many: usize,
+    ) -> Result<usize, String> {
+        if !self.can_place(tile, destination) {
+            return Err(format!("{:?} cannot accept tile {}", destination, tile));
+        }
+        match destination {
+            Destination::PatternLine(row_number) => {
+                self.in_progress[row_number].accept(tile, how_many)
+            }
+            Destination::FloorLine => Ok(how_many),
+        }
+    }
+
+    pub(crate) fn zobrist(&self, player_id: usize) -> u64 {
+        self.in_progress
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, pl)| acc ^ pl.zobrist(player_id, i))
+            ^ self.wall.zobrist(player_id)
+            ^ self.floorline.zobrist(player_id)
+    }
+
     // Returns the tiles that did fit nor into the patternline, nor into the floorline
     pub fn pick_factory(
         &mut self,
         factory: &mut Factory,
         common_area: &mut CommonArea,
-        row_number: usize,
+        destination: Destination,
         picked_tile: Tile,
     ) -> Result<Vec<Tile>, String> {
         let tiles = factory
@@ -130,10 +210,9 @@ impl BuildingArea {
                 &picked_tile
             ));
         }
-        let pattern_line = &mut self.in_progress[row_number];
         let (picked, non_picked): (Vec<Tile>, Vec<Tile>) =
             tiles.iter().partition(|&tile| tile == &picked_tile);
-        let remaining = pattern_line.accept(picked_tile, picked.len())?;
+        let remaining = self.accept_into(destination, picked_tile, picked.len())?;
         common_area.add(&non_picked);
         factory.0 = None;
         let remaining = self.floorline.add_tiles(&vec![picked_tile; remaining]);
@@ -143,13 +222,12 @@ impl BuildingArea {
     // Returns the tiles that did fit nor into the patternline, nor into the floorline
     pub fn pick_from_common_area(
         &mut self,
-        row_number: usize,
+        destination: Destination,
         picked_tile: Tile,
         how_many: usize,
         first_player: bool,
     ) -> Result<Vec<Tile>, String> {
-        let pattern_line = &mut self.in_progress[row_number];
-        let remaining = pattern_line.accept(picked_tile, how_many)?;
+        let remaining = self.accept_into(destination, picked_tile, how_many)?;
         let mut to_floor = vec![picked_tile; remaining];
         if first_player {
             to_floor.push(Tile::FirstPlayer);
@@ -162,29 +240,47 @@ impl BuildingArea {
 
 pub struct BuildingAreaView<'a> {
     buildingarea: &'a BuildingArea,
-    selected: Option<usize>,
+    selected: Option<Destination>,
+    /// Only the rows of the current player can be clicked
+    clickable: bool,
 }
 
 impl<'a> BuildingAreaView<'a> {
-    pub fn new(buildingarea: &'a BuildingArea, selected: Option<usize>) -> Self {
+    pub fn new(buildingarea: &'a BuildingArea, selected: Option<Destination>) -> Self {
         Self {
             buildingarea,
             selected,
+            clickable: false,
         }
     }
+
+    pub fn clickable(self, clickable: bool) -> Self {
+        Self { clickable, ..self }
+    }
 }
 
 impl<'a> Component for BuildingAreaView<'a> {
     fn render(&self, writer: &mut RootedRenderer) {
+        let selected_row = match self.selected {
+            Some(Destination::PatternLine(row_number)) => Some(row_number),
+            _ => None,
+        };
         let panel = Layout::vertical(vec![
             Box::new(Layout::horizontal(vec![
-                Box::new(InProgressView::new(
-                    self.selected,
-                    self.buildingarea.get_rows(),
-                )),
+                Box::new(InProgressView {
+                    selected: selected_row,
+                    in_progress: self.buildingarea.get_rows(),
+                    clickable: self.clickable,
+                }),
                 Box::new(WallView::new(&self.buildingarea.wall)),
             ])),
-            Box::new(FloorLineView::new(self.buildingarea.get_floorline())),
+            Box::new(
+                FloorLineView::new(
+                    self.buildingarea.get_floorline(),
+                    self.selected == Some(Destination::FloorLine),
+                )
+                .clickable(self.clickable),
+            ),
         ]);
         panel.render(writer);
     }
@@ -193,24 +289,19 @@ impl<'a> Component for BuildingAreaView<'a> {
 struct InProgressView<'a> {
     selected: Option<usize>,
     in_progress: &'a [PatternLine],
-}
-
-impl<'a> InProgressView<'a> {
-    fn new(selected: Option<usize>, in_progress: &'a [PatternLine]) -> Self {
-        Self {
-            selected,
-            in_progress,
-        }
-    }
+    clickable: bool,
 }
 
 impl<'a> Component for InProgressView<'a> {
     fn render(&self, writer: &mut renderer::RootedRenderer) {
         for (i, pl) in self.in_progress.iter().enumerate() {
             let is_selected = self.selected.map(|x| x == i).unwrap_or(false);
-            PatternLineView::new(pl, is_selected).render(writer);
-            let next = (i + 2) as u16;
-            writer.set_cursor_to((1, next).into());
+            let mut view = PatternLineView::new(pl, is_selected);
+            if self.clickable {
+                view = view.clickable(i);
+            }
+            // Every line in a layer of its own, so that it can be clicked on its own
+            writer.render_into_layer("pattern-line", (0, i as u16).into(), &view);
         }
     }
 }
@@ -227,16 +318,21 @@ impl<'a> ScoreView<'a> {
 
 impl<'a> Component for ScoreView<'a> {
     fn render(&self, writer: &mut RootedRenderer) {
-        let mut text = String::new();
-        for p in self.players {
-            let bg = p.get_buildingarea();
-            let wall = &bg.wall;
-            text.push_str(&format!("{}: {}\n", p.get_name(), wall.count_points()));
-        }
-        let textarea = TextView::from(text);
+        let rows = self
+            .players
+            .iter()
+            .map(|p| {
+                let points = p.get_buildingarea().wall.count_points();
+                vec![
+                    TextView::from(format!("{}:", p.get_name())).into(),
+                    TextView::from(points).into(),
+                ]
+            })
+            .collect();
+        let table = Grid::from_rows(rows).with_spacing(1, 0);
 
         let panel = PanelBuilder::default()
-            .component(Box::new(textarea) as Box<_>)
+            .component(Box::new(table) as Box<_>)
             .name("Score")
             .build()
             .unwrap();
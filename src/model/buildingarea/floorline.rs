@@ -1,10 +1,13 @@
-use std::borrow::Cow;
+use serde::{Deserialize, Serialize};
 
-use crate::visor::Component;
+use crate::{
+    model::{zobrist, Pick},
+    visor::{Component, UserInput},
+};
 
-use super::Tile;
+use super::{Destination, Tile};
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct FloorLine([Option<Tile>; 7]);
 
 impl FloorLine {
@@ -14,6 +17,16 @@ impl FloorLine {
         })
     }
 
+    /// The tiles on the line, without the first player tile
+    pub fn tiles(&self) -> Vec<Tile> {
+        self.0
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|tile| *tile != Tile::FirstPlayer)
+            .collect()
+    }
+
     pub fn reset(&mut self) {
         *self = Default::default();
     }
@@ -25,6 +38,15 @@ impl FloorLine {
         })
     }
 
+    pub(crate) fn zobrist(&self, player_id: usize) -> u64 {
+        let keys = &zobrist::keys().floor_line[player_id];
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.map(|tile| keys[i][zobrist::tile_index(tile)]))
+            .fold(0, |acc, key| acc ^ key)
+    }
+
     fn points_for_slot(slot_id: usize) -> u8 {
         match slot_id {
             0..=1 => 1,
@@ -54,27 +76,48 @@ impl FloorLine {
 
 pub struct FloorLineView<'a> {
     floorline: &'a FloorLine,
+    selected: bool,
+    clickable: bool,
 }
 
 impl<'a> FloorLineView<'a> {
-    pub fn new(floorline: &'a FloorLine) -> Self {
-        Self { floorline }
+    pub fn new(floorline: &'a FloorLine, selected: bool) -> Self {
+        Self {
+            floorline,
+            selected,
+            clickable: false,
+        }
+    }
+
+    pub fn clickable(self, clickable: bool) -> Self {
+        Self { clickable, ..self }
     }
 }
 
 impl<'a> Component for FloorLineView<'a> {
     fn render(&self, writer: &mut crate::visor::renderer::RootedRenderer) {
         for (i, _slot) in self.floorline.0.iter().enumerate() {
-            writer.write(&FloorLine::points_for_slot(i).to_string());
+            writer.write(FloorLine::points_for_slot(i).to_string());
         }
         writer.reset_cursor_to_root();
         writer.write("\n");
+        let theme = writer.theme();
         for slot in self.floorline.0.iter() {
-            let s = match slot {
-                Some(tile) => Cow::from(tile.to_string()),
-                None => Cow::from("☐"),
-            };
-            writer.write(&s);
+            match slot {
+                Some(tile) => writer.write(theme.tile(*tile).span()),
+                None => writer.write(theme.empty_slot.span()),
+            }
+        }
+        if self.selected {
+            writer.write(&theme.markers.floor);
+        }
+        if self.clickable {
+            // Clicking the floor line once it is selected puts the tiles there
+            writer.on_click(if self.selected {
+                UserInput::Confirm
+            } else {
+                UserInput::Pick(Pick::Destination(Destination::FloorLine))
+            });
         }
     }
 }
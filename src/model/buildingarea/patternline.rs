@@ -1,7 +1,10 @@
-use super::Tile;
-use crate::visor::{renderer::RootedRenderer, Component};
+use super::{Destination, Tile};
+use crate::model::{zobrist, Pick};
+use crate::visor::{renderer::RootedRenderer, Component, UserInput};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PatternLine {
     Free {
         length: usize,
@@ -102,17 +105,39 @@ impl PatternLine {
             PatternLine::Taken { length, .. } => length,
         }
     }
+
+    pub(crate) fn zobrist(&self, player_id: usize, row: usize) -> u64 {
+        match *self {
+            PatternLine::Free { .. } => 0,
+            PatternLine::Taken { tile, taken, .. } => {
+                zobrist::keys().pattern_line[player_id][row][zobrist::tile_index(tile)][taken]
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct PatternLineView<'a> {
     line: &'a PatternLine,
     selected: bool,
+    /// The row of the line, once it can be clicked
+    row: Option<usize>,
 }
 
 impl<'a> PatternLineView<'a> {
     pub fn new(line: &'a PatternLine, selected: bool) -> Self {
-        Self { line, selected }
+        Self {
+            line,
+            selected,
+            row: None,
+        }
+    }
+
+    pub fn clickable(self, row: usize) -> Self {
+        Self {
+            row: Some(row),
+            ..self
+        }
     }
 }
 
@@ -124,24 +149,40 @@ impl<'a> From<PatternLineView<'a>> for Box<dyn Component + 'a> {
 
 impl<'a> Component for PatternLineView<'a> {
     fn render(&self, writer: &mut RootedRenderer) {
-        let mut output = match *self.line {
-            PatternLine::Free { length } => {
-                format!("{: >5}", "☐".repeat(length))
-            }
+        let theme = writer.theme();
+        let (slots, mut prefix) = match *self.line {
+            // Free lines are aligned on 5 even when selected, taken lines are not
+            PatternLine::Free { length } => (
+                vec![theme.empty_slot; length],
+                " ".repeat(5_usize.saturating_sub(length)),
+            ),
             PatternLine::Taken {
                 tile,
                 length,
                 taken,
             } => {
-                let mut output = String::from(&"☐".repeat(length - taken));
-                output.push_str(&tile.to_string().repeat(taken));
-                output
+                let mut slots = vec![theme.empty_slot; length - taken];
+                slots.extend(vec![theme.tile(tile); taken]);
+                (slots, String::new())
             }
         };
         if self.selected {
-            output = format!("-> {}", output);
+            prefix = format!("{}{}", theme.markers.row, prefix);
+        }
+        let width = prefix.chars().count() + slots.len();
+        writer.write(" ".repeat(8_usize.saturating_sub(width)));
+        writer.write(prefix);
+        for slot in slots {
+            writer.write(slot.span());
+        }
+        if let Some(row) = self.row {
+            // Clicking the selected line again puts the tiles there
+            writer.on_click(if self.selected {
+                UserInput::Confirm
+            } else {
+                UserInput::Pick(Pick::Destination(Destination::PatternLine(row)))
+            });
         }
-        writer.write(&format!("{: >8}", output));
     }
 }
 
@@ -1,14 +1,16 @@
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::{
-    model::Tile,
-    visor::{renderer, Component},
+    model::{zobrist, Tile},
+    visor::{renderer, theme::Glyph, Component},
 };
 
 use super::{floorline::FloorLine, Slot};
 
 const AREA_LENGTH: usize = 5;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Wall {
     slots: [[Slot; AREA_LENGTH]; AREA_LENGTH],
     points: u8,
@@ -33,12 +35,7 @@ impl Wall {
 
     pub fn reset_floorline(&mut self, floorline: &mut FloorLine) {
         let minus = floorline.calculate_minus_points();
-        let total = if self.points <= minus {
-            0
-        } else {
-            self.points - minus
-        };
-        self.points = total;
+        self.points = self.points.saturating_sub(minus);
         floorline.reset();
     }
 
@@ -144,6 +141,37 @@ impl Wall {
         tiles.iter().any(|(_tile, count)| *count == 5)
     }
 
+    pub(crate) fn zobrist(&self, player_id: usize) -> u64 {
+        let keys = &zobrist::keys().wall[player_id];
+        let mut hash = 0;
+        for (row, slots) in self.slots.iter().enumerate() {
+            for (col, slot) in slots.iter().enumerate() {
+                if let Slot::Filled(_) = slot {
+                    hash ^= keys[row][col];
+                }
+            }
+        }
+        hash
+    }
+
+    pub(crate) fn zobrist_for_slot(&self, player_id: usize, row: usize, tile: Tile) -> u64 {
+        let col = self.find_col_for_tile(row, tile);
+        zobrist::keys().wall[player_id][row][col]
+    }
+
+    /// Whether every tile has its usual place, like on a new wall
+    pub(crate) fn has_usual_layout(&self) -> bool {
+        let usual = Self::default();
+        self.slots
+            .iter()
+            .flatten()
+            .zip(usual.slots.iter().flatten())
+            .all(|(slot, usual)| match (slot, usual) {
+                (Slot::Filled(tile) | Slot::Free(tile), Slot::Free(usual)) => tile == usual,
+                _ => false,
+            })
+    }
+
     pub fn count_points(&self) -> u8 {
         self.points
     }
@@ -186,12 +214,17 @@ impl<'a> WallView<'a> {
 
 impl<'a> Component for WallView<'a> {
     fn render(&self, writer: &mut renderer::RootedRenderer) {
+        let theme = writer.theme();
         for (i, row) in self.wall.slots.iter().enumerate() {
             for t in row.iter() {
-                match t {
-                    Slot::Filled(_tile) => writer.write("X"),
-                    Slot::Free(tile) => writer.write(&tile.to_string()),
-                }
+                let glyph = match t {
+                    Slot::Filled(tile) => {
+                        Glyph::new(theme.wall_filled, theme.tile(*tile).style.bold())
+                    }
+                    // Where the tile goes, faded
+                    Slot::Free(tile) => Glyph::new(theme.tile(*tile).glyph, theme.wall_hint),
+                };
+                writer.write(glyph.span());
             }
             writer.set_cursor_to((1, i as u16 + 2).into());
         }
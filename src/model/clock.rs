@@ -0,0 +1,167 @@
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use super::{buildingarea::Destination, zobrist::ZobristHash, Game, Move};
+
+/// What happens to a player whose time runs out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPolicy {
+    /// The player loses, and the game stops
+    Forfeit,
+    /// The first legal move is played for them
+    FirstLegalMove,
+    /// The first tiles they can take go to their floor line
+    ToFloor,
+}
+
+impl FromStr for TimeoutPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forfeit" => Ok(Self::Forfeit),
+            "first-move" => Ok(Self::FirstLegalMove),
+            "floor" => Ok(Self::ToFloor),
+            _ => Err(format!(
+                "Unknown timeout policy {}, expected forfeit, first-move or floor",
+                s
+            )),
+        }
+    }
+}
+
+/// A starting time per player, and an increment for every move they make
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    pub initial: Duration,
+    pub increment: Duration,
+    pub on_timeout: TimeoutPolicy,
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    /// Reads `minutes+seconds`, like chess players write it: `5+3` is five minutes each and
+    /// three more seconds per move
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid time control {}, expected minutes+seconds", s);
+        let (minutes, seconds) = s.split_once('+').unwrap_or((s, "0"));
+        let minutes: u64 = minutes.parse().map_err(|_| invalid())?;
+        let seconds: u64 = seconds.parse().map_err(|_| invalid())?;
+        if minutes == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            initial: Duration::from_secs(minutes * 60),
+            increment: Duration::from_secs(seconds),
+            on_timeout: TimeoutPolicy::Forfeit,
+        })
+    }
+}
+
+/// What the clocks did when a player ran out of time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeout {
+    Forfeit { seat: usize },
+    Played { seat: usize, mv: Move },
+}
+
+/// A chess clock for every player. Only the clock of the current player runs.
+pub struct Clocks {
+    control: TimeControl,
+    remaining: Vec<Duration>,
+    /// The player whose clock runs, and since when
+    running: Option<(usize, Instant)>,
+    /// The position when the clock last switched, to notice moves
+    position: ZobristHash,
+    flagged: Option<usize>,
+}
+
+impl Clocks {
+    /// Starts the clock of the first player
+    pub fn start<const N: usize>(control: TimeControl, game: &Game<N>, now: Instant) -> Self {
+        Self {
+            control,
+            remaining: vec![control.initial; N],
+            running: Some((game.get_current_player_id(), now)),
+            position: game.zobrist_hash(),
+            flagged: None,
+        }
+    }
+
+    pub fn remaining(&self, seat: usize, now: Instant) -> Duration {
+        let left = self.remaining[seat];
+        match self.running {
+            Some((running, since)) if running == seat => {
+                left.saturating_sub(now.duration_since(since))
+            }
+            _ => left,
+        }
+    }
+
+    /// The player who lost on time, if any
+    pub fn flagged(&self) -> Option<usize> {
+        self.flagged
+    }
+
+    /// Whatever the clocks show, `m:ss`
+    pub fn readings(&self, now: Instant) -> Vec<String> {
+        (0..self.remaining.len())
+            .map(|seat| {
+                if self.flagged == Some(seat) {
+                    return String::from("out of time");
+                }
+                let left = self.remaining(seat, now);
+                // Rounded up, so that 0:00 is only shown once time is up
+                let seconds = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+                format!("{}:{:02}", seconds / 60, seconds % 60)
+            })
+            .collect()
+    }
+
+    /// Switches the clock once the current player moved, and adds the increment to their time
+    pub fn update<const N: usize>(&mut self, game: &Game<N>, now: Instant) {
+        if game.zobrist_hash() == self.position {
+            return;
+        }
+        self.position = game.zobrist_hash();
+        if let Some((seat, _)) = self.running {
+            self.remaining[seat] = self.remaining(seat, now) + self.control.increment;
+        }
+        self.running = if game.is_over() {
+            None
+        } else {
+            Some((game.get_current_player_id(), now))
+        };
+    }
+
+    /// Applies the timeout policy if the current player is out of time
+    pub fn tick<const N: usize>(&mut self, game: &mut Game<N>, now: Instant) -> Option<Timeout> {
+        self.update(game, now);
+        let (seat, _) = self.running?;
+        if !self.remaining(seat, now).is_zero() {
+            return None;
+        }
+        let mv = match self.control.on_timeout {
+            TimeoutPolicy::Forfeit => None,
+            TimeoutPolicy::FirstLegalMove => game.legal_moves().first().copied(),
+            TimeoutPolicy::ToFloor => game.legal_moves().first().map(|mv| Move {
+                destination: Destination::FloorLine,
+                ..*mv
+            }),
+        };
+        let Some(mv) = mv else {
+            self.remaining[seat] = Duration::ZERO;
+            self.running = None;
+            self.flagged = Some(seat);
+            return Some(Timeout::Forfeit { seat });
+        };
+        game.reset_selection();
+        game.apply_move(seat, mv)
+            .expect("The move comes from the legal moves");
+        self.update(game, now);
+        Some(Timeout::Played { seat, mv })
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Instant,
+};
+
+use crate::visor::{
+    backend::{TerminalBackend, TestBackend},
+    input::{InputSource, Recording, ReplayInput},
+    keymap::Keymap,
+    Engine, UserInput,
+};
+
+use super::{
+    bag::Bag,
+    clock::{Clocks, TimeControl, Timeout},
+    player::Player,
+    view::{GameView, LocalGameView},
+    Game,
+};
+
+/// The players of a game at one keyboard
+pub fn local_players() -> [Player; 2] {
+    [
+        Player::default_with_name("Alice".into()),
+        Player::default_with_name("Bob".into()),
+    ]
+}
+
+/// A game where every player sits at the same keyboard, maybe against the clock
+pub struct LocalGame<const N: usize> {
+    pub game: Rc<RefCell<Game<N>>>,
+    pub clocks: Option<Rc<RefCell<Clocks>>>,
+    forfeit: Option<usize>,
+    keymap: Keymap,
+    show_keys: Rc<Cell<bool>>,
+}
+
+impl<const N: usize> LocalGame<N> {
+    pub fn new(game: Game<N>) -> Self {
+        Self {
+            game: Rc::new(RefCell::new(game)),
+            clocks: None,
+            forfeit: None,
+            keymap: Keymap::default(),
+            show_keys: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// The keys shown on the help screen
+    pub fn with_keymap(self, keymap: Keymap) -> Self {
+        Self { keymap, ..self }
+    }
+
+    pub fn with_clocks(self, control: TimeControl, now: Instant) -> Self {
+        let clocks = Clocks::start(control, &self.game.borrow(), now);
+        Self {
+            clocks: Some(Rc::new(RefCell::new(clocks))),
+            ..self
+        }
+    }
+
+    pub fn view(&self) -> LocalGameView<N> {
+        LocalGameView {
+            game: GameView {
+                game: self.game.clone(),
+                clocks: self.clocks.clone(),
+            },
+            keys: self.keymap.help(),
+            show_keys: self.show_keys.clone(),
+        }
+    }
+
+    /// The player who lost on time, if any
+    pub fn forfeit(&self) -> Option<usize> {
+        self.forfeit
+    }
+
+    /// Returns true once the game is over, or the players quit
+    pub fn handle_input(&mut self, input: UserInput, now: Instant) -> bool {
+        if input == UserInput::Help {
+            self.show_keys.set(!self.show_keys.get());
+            return false;
+        }
+        if self.game.borrow_mut().handle(input) {
+            return true;
+        }
+        self.tick(now)
+    }
+
+    /// Lets the clocks run, returns true once a player lost on time
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let Some(clocks) = &self.clocks else {
+            return false;
+        };
+        let timeout = clocks.borrow_mut().tick(&mut self.game.borrow_mut(), now);
+        if let Some(Timeout::Forfeit { seat }) = timeout {
+            self.forfeit = Some(seat);
+            return true;
+        }
+        false
+    }
+
+    /// Renders after every input of `source`, until it runs dry or the game ends
+    pub fn play<T: TerminalBackend>(
+        &mut self,
+        engine: &mut Engine<T>,
+        source: &mut dyn InputSource,
+    ) {
+        engine.render();
+        while let Some(input) = source.next_input() {
+            if self.handle_input(engine.resolve(input), Instant::now()) {
+                return;
+            }
+            engine.render();
+        }
+    }
+}
+
+/// Plays a recorded game again without a terminal, on a screen as big as the one it was recorded
+/// on, and returns what the screen showed last
+pub fn replay(recording: &Recording) -> String {
+    let game = Game::for_players_with_bag(local_players(), Bag::seeded(recording.seed));
+    let mut local = LocalGame::new(game);
+    let (width, height) = recording.size;
+    let backend = TestBackend::with_size(width, height);
+    let mut engine = Engine::new(backend, Box::new(local.view()) as Box<_>);
+    local.play(&mut engine, &mut ReplayInput::new(recording));
+    engine.get_contents()
+}
@@ -1,10 +1,8 @@
 use crate::visor::view::PanelBuilder;
 use crate::visor::UserInput;
-use crate::{
-    model::player::Player,
-    visor::{view::TextView, Component},
-};
+use crate::{model::player::Player, visor::Component};
 use rand::{distributions::Standard, prelude::Distribution};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::ops::{Index, IndexMut};
 use std::{
     fmt::{Debug, Display},
@@ -13,15 +11,22 @@ use std::{
 
 use self::bag::Bag;
 
-use self::buildingarea::IsGameOver;
+use self::buildingarea::{Destination, IsGameOver};
+use self::rules::Rules;
 use self::tilecollection::{HasTileCollection, TileCollection};
-use self::view::render_pickables;
+use self::view::PickablesView;
+use self::zobrist::ZobristHash;
 
 pub mod bag;
 pub mod buildingarea;
+pub mod clock;
+pub mod local;
 pub mod player;
+pub mod rules;
+pub mod snapshot;
 pub mod tilecollection;
 pub mod view;
+pub mod zobrist;
 
 pub enum AppEvent {
     Select(Direction),
@@ -131,6 +136,19 @@ impl Display for Tile {
     }
 }
 
+impl Serialize for Tile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 impl FromStr for Tile {
     type Err = String;
 
@@ -150,7 +168,7 @@ impl FromStr for Tile {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommonArea(Vec<Tile>);
 
 impl HasTileCollection for CommonArea {
@@ -183,7 +201,7 @@ impl CommonArea {
 
     pub fn pick_tile(&mut self, tile: Tile) -> (usize, bool) {
         let count = self.count_tile(tile);
-        let has_firstplayer = self.0.iter().any(|&t| t == Tile::FirstPlayer);
+        let has_firstplayer = self.0.contains(&Tile::FirstPlayer);
         let new: Vec<_> = self
             .0
             .iter()
@@ -193,6 +211,18 @@ impl CommonArea {
         self.0 = new;
         (count, has_firstplayer)
     }
+
+    pub(crate) fn zobrist(&self) -> u64 {
+        let keys = zobrist::keys();
+        let mut hash = 0;
+        for tile in self.0.as_slice().distinct_tiles() {
+            hash ^= keys.common_area[zobrist::tile_index(tile)][self.count_tile(tile)];
+        }
+        if self.0.contains(&Tile::FirstPlayer) {
+            hash ^= keys.first_player_in_common_area;
+        }
+        hash
+    }
 }
 
 enum CommonAreaStateView {
@@ -240,12 +270,17 @@ impl<'a> Component for CommonAreaView<'a> {
             CommonAreaStateView::Passive => (false, vec![]),
         };
 
-        let output = render_pickables(is_selected, &self.common_area.0, &selected_tiles);
+        let pickables = PickablesView {
+            source: Some(TileSource::CommonArea),
+            is_selected,
+            tiles: &self.common_area.0,
+            selected_tiles,
+        };
 
         let panel = PanelBuilder::default()
             .name("Common")
             .padding(0)
-            .component(Box::new(TextView::from(output)))
+            .component(Box::new(pickables))
             .build()
             .unwrap();
         panel.render(writer);
@@ -257,6 +292,7 @@ impl<'a> Component for CommonAreaView<'a> {
 }
 
 pub const TILE_PER_FACTORY: usize = 4;
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Factory(Option<[Tile; TILE_PER_FACTORY]>);
 
 impl HasTileCollection for Factory {
@@ -299,8 +335,17 @@ impl Factory {
     pub fn get_tiles(&self) -> Option<&[Tile]> {
         self.0.as_ref().map(|t| t.as_slice())
     }
+
+    pub(crate) fn zobrist(&self, factory_id: FactoryId) -> u64 {
+        let keys = &zobrist::keys().factory[factory_id.0];
+        self.distinct_tiles().into_iter().fold(0, |acc, tile| {
+            acc ^ keys[zobrist::tile_index(tile)][self.count_tile(tile)]
+        })
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Direction {
     Next,
     Prev,
@@ -315,9 +360,10 @@ pub struct Game<const N: usize> {
     pub(crate) common_area: CommonArea,
     current_player_id: usize,
     current_source: TileSource,
+    zobrist: ZobristHash,
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Ord, PartialOrd, Hash)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct FactoryId(usize);
 
 impl PartialEq<usize> for FactoryId {
@@ -354,12 +400,37 @@ impl Index<FactoryId> for Vec<Factory> {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TileSource {
     Factory(FactoryId),
     CommonArea,
 }
 
+/// A complete turn of a player: which tiles they take, from where, and where they put them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Move {
+    pub source: TileSource,
+    pub tile: Tile,
+    pub destination: Destination,
+}
+
+/// Something on the board a player clicked
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pick {
+    Source(TileSource),
+    Tile(TileSource, Tile),
+    Destination(Destination),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TurnOutcome {
+    NextPlayer,
+    RoundOver,
+    GameOver,
+}
+
 impl<const N: usize> Game<N> {
     pub fn get_players(&self) -> &[Player] {
         &self.players
@@ -381,13 +452,20 @@ impl<const N: usize> Game<N> {
     }
 
     pub fn for_players(players: [Player; N]) -> Self {
-        let mut bag = Bag::default();
-        let factories: Vec<_> = [0, 1, 2, 3]
-            .iter()
+        Self::for_players_with_bag(players, Bag::default())
+    }
+
+    pub fn for_players_with_bag(players: [Player; N], bag: Bag) -> Self {
+        Self::with_rules(players, bag, Rules::default())
+    }
+
+    /// The rules are expected to be checked already, see [`Rules::check`].
+    pub fn with_rules(players: [Player; N], mut bag: Bag, rules: Rules) -> Self {
+        let factories: Vec<_> = (0..rules.factories)
             .map(|_| Factory::new_from_bag(&mut bag))
             .collect();
 
-        Game {
+        let mut game = Game {
             players,
             factories,
             state: GameState::PickSource,
@@ -396,7 +474,65 @@ impl<const N: usize> Game<N> {
             common_area: CommonArea::default(),
             current_player_id: 0,
             current_source: TileSource::Factory(0.into()),
+            zobrist: ZobristHash::default(),
+        };
+        game.zobrist = game.compute_zobrist_hash();
+        game
+    }
+
+    pub fn get_current_player_id(&self) -> usize {
+        self.current_player_id
+    }
+
+    /// The incrementally maintained hash of the current position.
+    pub fn zobrist_hash(&self) -> ZobristHash {
+        self.zobrist
+    }
+
+    /// Hashes the whole position from scratch, this should always be equal to
+    /// [`Game::zobrist_hash`].
+    pub fn compute_zobrist_hash(&self) -> ZobristHash {
+        let keys = zobrist::keys();
+        let mut hash = ZobristHash::default();
+        for (i, player) in self.players.iter().enumerate() {
+            hash ^= player.get_buildingarea().zobrist(i);
         }
+        for (i, factory) in self.factories.iter().enumerate() {
+            hash ^= factory.zobrist(i.into());
+        }
+        hash ^= self.common_area.zobrist();
+        hash ^= keys.side_to_move[self.current_player_id];
+        hash
+    }
+
+    fn set_current_player(&mut self, player_id: usize) {
+        let keys = &zobrist::keys().side_to_move;
+        self.zobrist ^= keys[self.current_player_id] ^ keys[player_id];
+        self.current_player_id = player_id;
+    }
+
+    // Hash of everything that can change when a player picks tiles
+    fn zobrist_for_pick(
+        &self,
+        source: TileSource,
+        player_id: usize,
+        destination: Destination,
+    ) -> u64 {
+        let buildingarea = self.players[player_id].get_buildingarea();
+        let source_hash = match source {
+            TileSource::Factory(factory_id) => self.factories[factory_id].zobrist(factory_id),
+            TileSource::CommonArea => 0,
+        };
+        let row_hash = match destination {
+            Destination::PatternLine(row_id) => {
+                buildingarea.get_row(row_id).zobrist(player_id, row_id)
+            }
+            Destination::FloorLine => 0,
+        };
+        source_hash
+            ^ row_hash
+            ^ self.common_area.zobrist()
+            ^ buildingarea.get_floorline().zobrist(player_id)
     }
 
     pub fn get_factories(&self) -> &[Factory] {
@@ -410,16 +546,108 @@ impl<const N: usize> Game<N> {
         }
     }
 
-    fn find_adjacent_selectable_row(
+    fn find_adjacent_destination(
         &self,
         tile: Tile,
         player_id: usize,
-        current_row: usize,
+        current: Destination,
         direction: Direction,
-    ) -> Option<usize> {
+    ) -> Option<Destination> {
         let barea = self.get_players()[player_id].get_buildingarea();
-        let rows = barea.get_rows_that_can_accept(tile);
-        rows.scroll(current_row, direction)
+        let destinations = barea.get_destinations_for(tile);
+        destinations.scroll(current, direction)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.is_over
+    }
+
+    /// The move the current player is about to make, once they have picked a source, a tile and
+    /// a destination.
+    pub fn selected_move(&self) -> Option<Move> {
+        match self.state {
+            GameState::PickRowToPutTiles { tile, destination } => Some(Move {
+                source: self.current_source,
+                tile,
+                destination,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Puts the cursor back on the first source that has tiles, throwing away any selection in
+    /// progress.
+    pub fn reset_selection(&mut self) {
+        self.state = GameState::PickSource;
+        if let Some(source) = self.find_pickable_sources().first() {
+            self.current_source = *source;
+        }
+    }
+
+    /// Every move the current player could make
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.is_over {
+            return vec![];
+        }
+        let buildingarea = self.players[self.current_player_id].get_buildingarea();
+        let mut moves = vec![];
+        for source in self.find_pickable_sources() {
+            for tile in self.find_source(source).distinct_tiles() {
+                for destination in buildingarea.get_destinations_for(tile) {
+                    moves.push(Move {
+                        source,
+                        tile,
+                        destination,
+                    });
+                }
+            }
+        }
+        moves
+    }
+
+    fn check_move(&self, player_id: usize, mv: &Move) -> Result<(), String> {
+        if self.is_over {
+            return Err(String::from("The game is already over"));
+        }
+        if player_id != self.current_player_id {
+            return Err(format!("It is not the turn of player {}", player_id));
+        }
+        if !self.find_pickable_sources().contains(&mv.source) {
+            return Err(format!("You cannot pick from {:?}", mv.source));
+        }
+        if mv.tile == Tile::FirstPlayer || self.count_tiles_in(mv.source, mv.tile) == 0 {
+            return Err(format!("There is no tile {} in {:?}", mv.tile, mv.source));
+        }
+        let buildingarea = self.players[player_id].get_buildingarea();
+        if !buildingarea.can_place(mv.tile, mv.destination) {
+            return Err(format!(
+                "You cannot put tile {} on {:?}",
+                mv.tile, mv.destination
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validates and plays a move for a player, then passes the turn to the next player (or
+    /// finishes the round, if there is nothing left to pick).
+    pub fn apply_move(&mut self, player_id: usize, mv: Move) -> Result<TurnOutcome, String> {
+        self.check_move(player_id, &mv)?;
+        self.pick(mv.source, mv.tile, player_id, mv.destination)?;
+
+        let pickable_sources = self.find_pickable_sources();
+        let outcome = if let Some(next_source) = pickable_sources.first() {
+            self.set_current_player((self.current_player_id + 1) % N);
+            self.current_source = *next_source;
+            TurnOutcome::NextPlayer
+        } else {
+            self.advance_round();
+            if self.is_over {
+                TurnOutcome::GameOver
+            } else {
+                TurnOutcome::RoundOver
+            }
+        };
+        Ok(outcome)
     }
 
     pub fn handle(&mut self, input: UserInput) -> bool {
@@ -446,22 +674,11 @@ impl<const N: usize> Game<N> {
                         selected_tile: next_tile,
                     })
                 }
-                GameState::PickRowToPutTiles {
-                    tile,
-                    selected_row_id,
-                } => {
-                    let selected_row_id = self
-                        .find_adjacent_selectable_row(
-                            tile,
-                            self.current_player_id,
-                            selected_row_id,
-                            dir,
-                        )
-                        .unwrap_or(selected_row_id);
-                    Some(GameState::PickRowToPutTiles {
-                        tile,
-                        selected_row_id,
-                    })
+                GameState::PickRowToPutTiles { tile, destination } => {
+                    let destination = self
+                        .find_adjacent_destination(tile, self.current_player_id, destination, dir)
+                        .unwrap_or(destination);
+                    Some(GameState::PickRowToPutTiles { tile, destination })
                 }
             },
             UserInput::Confirm => match self.state {
@@ -480,51 +697,62 @@ impl<const N: usize> Game<N> {
                 } => {
                     let buildingarea =
                         self.get_players()[self.current_player_id].get_buildingarea();
-                    let rows = buildingarea.get_rows_that_can_accept(tile);
-                    let first_that_can_fit = rows.first();
-                    if first_that_can_fit.is_none() {
-                        todo!("No rows can fit the tiles, all should go to the floorline");
-                    }
-                    let selected_row_id = *first_that_can_fit.unwrap();
-                    Some(GameState::PickRowToPutTiles {
-                        tile,
-                        selected_row_id,
-                    })
+                    // The floorline is always the last destination, so this never fails
+                    let destination = buildingarea.get_destinations_for(tile)[0];
+                    Some(GameState::PickRowToPutTiles { tile, destination })
                 }
-                GameState::PickRowToPutTiles {
-                    tile,
-                    selected_row_id: row_id,
-                } => {
-                    self.pick(self.current_source, tile, self.current_player_id, row_id)
-                        .unwrap();
-
-                    let pickable_sources = self.find_pickable_sources();
-                    let next_source = pickable_sources.first();
-                    let next = if let Some(next_source) = next_source {
-                        self.current_player_id = (N - 1) - self.current_player_id;
-                        self.current_source = *next_source;
-                        GameState::PickSource
-                    } else {
-                        self.advance_round()
+                GameState::PickRowToPutTiles { tile, destination } => {
+                    let mv = Move {
+                        source: self.current_source,
+                        tile,
+                        destination,
                     };
-                    Some(next)
+                    self.apply_move(self.current_player_id, mv).unwrap();
+                    Some(GameState::PickSource)
                 }
             },
             UserInput::Back => match self.state {
                 GameState::PickSource => None,
                 GameState::PickTileFromSource { selected_tile: _ } => Some(GameState::PickSource),
-                GameState::PickRowToPutTiles {
-                    tile,
-                    selected_row_id: _,
-                } => Some(GameState::PickTileFromSource {
+                GameState::PickRowToPutTiles { tile, .. } => Some(GameState::PickTileFromSource {
                     selected_tile: tile,
                 }),
             },
+            UserInput::Select(n) => match self.state {
+                GameState::PickSource => {
+                    // Factories count from 1, like on the screen, 0 is the common area
+                    let source = match n {
+                        0 => TileSource::CommonArea,
+                        n => TileSource::Factory(FactoryId(n - 1)),
+                    };
+                    self.can_pick_from(source).then(|| {
+                        self.current_source = source;
+                        GameState::PickSource
+                    })
+                }
+                GameState::PickTileFromSource { .. } => None,
+                GameState::PickRowToPutTiles { tile, .. } => {
+                    // Pattern lines count from 1, 0 is the floor line
+                    let destination = match n {
+                        0 => Destination::FloorLine,
+                        n => Destination::PatternLine(n - 1),
+                    };
+                    let buildingarea =
+                        self.get_players()[self.current_player_id].get_buildingarea();
+                    buildingarea
+                        .get_destinations_for(tile)
+                        .contains(&destination)
+                        .then_some(GameState::PickRowToPutTiles { tile, destination })
+                }
+            },
+            UserInput::Pick(pick) => return self.handle_pick(pick),
             UserInput::Exit => {
                 self.is_over = true;
                 None
             }
-            UserInput::Character(_) | UserInput::Noop => None,
+            UserInput::Character(_) | UserInput::Help | UserInput::Click(..) | UserInput::Noop => {
+                None
+            }
         };
 
         if let Some(new_state) = new_state {
@@ -533,6 +761,57 @@ impl<const N: usize> Game<N> {
         self.is_over
     }
 
+    // The first player tile alone is nothing to pick
+    fn can_pick_from(&self, source: TileSource) -> bool {
+        self.find_pickable_sources().contains(&source)
+            && self.find_source(source).find_first_tile().is_some()
+    }
+
+    /// Selects what was clicked. Clicking the source or the tile that is already selected goes
+    /// on to the next step, like confirming would.
+    fn handle_pick(&mut self, pick: Pick) -> bool {
+        let new_state = match (pick, &self.state) {
+            (Pick::Source(source), GameState::PickSource) if source == self.current_source => {
+                return self.handle(UserInput::Confirm);
+            }
+            (Pick::Source(source), _) => self.can_pick_from(source).then(|| {
+                self.current_source = source;
+                GameState::PickSource
+            }),
+            (Pick::Tile(source, tile), GameState::PickTileFromSource { selected_tile })
+                if source == self.current_source && tile == *selected_tile =>
+            {
+                return self.handle(UserInput::Confirm);
+            }
+            (Pick::Tile(source, tile), _) => {
+                let pickable = tile != Tile::FirstPlayer
+                    && self.can_pick_from(source)
+                    && self.count_tiles_in(source, tile) > 0;
+                pickable.then(|| {
+                    self.current_source = source;
+                    GameState::PickTileFromSource {
+                        selected_tile: tile,
+                    }
+                })
+            }
+            (Pick::Destination(destination), GameState::PickRowToPutTiles { tile, .. }) => {
+                let buildingarea = self.get_players()[self.current_player_id].get_buildingarea();
+                buildingarea
+                    .get_destinations_for(*tile)
+                    .contains(&destination)
+                    .then_some(GameState::PickRowToPutTiles {
+                        tile: *tile,
+                        destination,
+                    })
+            }
+            (Pick::Destination(_), _) => None,
+        };
+        if let Some(new_state) = new_state {
+            self.state = new_state;
+        }
+        self.is_over
+    }
+
     fn reset_first_player_token(&mut self) -> usize {
         let player_id = self
             .players
@@ -543,34 +822,45 @@ impl<const N: usize> Game<N> {
         if player_id.is_none() {
             panic!("Nobody has the first player token at the end of the round?");
         }
+        self.zobrist ^= self.common_area.zobrist();
         self.common_area.add(&[Tile::FirstPlayer]);
+        self.zobrist ^= self.common_area.zobrist();
         player_id.unwrap()
     }
 
-    fn advance_round(&mut self) -> GameState {
+    fn advance_round(&mut self) {
         let next_player = self.reset_first_player_token();
         self.is_over = self.flush_tiles().into();
         self.refill_factories();
-        self.current_player_id = next_player;
+        // Without any tiles left to deal, nobody could make a move
+        if self.factories.iter().all(Factory::is_empty) {
+            self.is_over = true;
+        }
+        self.set_current_player(next_player);
         self.current_source = TileSource::Factory(FactoryId(0));
-        GameState::PickSource
     }
 
     fn flush_tiles(&mut self) -> IsGameOver {
         let mut is_game_over = IsGameOver::No;
-        for p in self.players.iter_mut() {
+        let mut discards = vec![];
+        for (i, p) in self.players.iter_mut().enumerate() {
             let bg = p.get_buildingarea_mut();
-            if let IsGameOver::Yes = bg.move_tiles_to_wall() {
+            if let IsGameOver::Yes = bg.move_tiles_to_wall(i, &mut self.zobrist, &mut discards) {
                 is_game_over = IsGameOver::Yes;
             }
-            bg.flush_floorline();
+            self.zobrist ^= bg.get_floorline().zobrist(i);
+            discards.append(&mut bg.flush_floorline());
         }
+        self.bag.discard(&discards);
         is_game_over
     }
 
     fn refill_factories(&mut self) {
-        for f in self.factories.iter_mut() {
+        for (i, f) in self.factories.iter_mut().enumerate() {
+            let factory_id = FactoryId(i);
+            self.zobrist ^= f.zobrist(factory_id);
             self.bag.fill_factory(f);
+            self.zobrist ^= f.zobrist(factory_id);
         }
     }
 
@@ -579,19 +869,22 @@ impl<const N: usize> Game<N> {
         source: TileSource,
         tile: Tile,
         player_id: usize,
-        row_id: usize,
+        destination: Destination,
     ) -> Result<(), String> {
+        let hash_before = self.zobrist_for_pick(source, player_id, destination);
         let buildingarea = self.players[player_id].get_buildingarea_mut();
         let remaining = match source {
             TileSource::Factory(factory_id) => {
                 let factory = &mut self.factories[factory_id];
-                buildingarea.pick_factory(factory, &mut self.common_area, row_id, tile)
+                buildingarea.pick_factory(factory, &mut self.common_area, destination, tile)
             }
             TileSource::CommonArea => {
                 let (count, has_firstplayer) = self.common_area.pick_tile(tile);
-                buildingarea.pick_from_common_area(row_id, tile, count, has_firstplayer)
+                buildingarea.pick_from_common_area(destination, tile, count, has_firstplayer)
             }
-        }?;
+        };
+        self.zobrist ^= hash_before ^ self.zobrist_for_pick(source, player_id, destination);
+        let remaining = remaining?;
         self.bag.discard(&remaining);
         Ok(())
     }
@@ -606,14 +899,19 @@ impl<const N: usize> Game<N> {
 
 pub enum GameState {
     PickSource,
-    PickTileFromSource { selected_tile: Tile },
-    PickRowToPutTiles { tile: Tile, selected_row_id: usize },
+    PickTileFromSource {
+        selected_tile: Tile,
+    },
+    PickRowToPutTiles {
+        tile: Tile,
+        destination: Destination,
+    },
 }
 
 impl GameState {
     pub fn find_selected_tile(&self) -> Option<Tile> {
         match self {
-            GameState::PickSource { .. } => None,
+            GameState::PickSource => None,
             GameState::PickTileFromSource { selected_tile, .. } => Some(*selected_tile),
             GameState::PickRowToPutTiles { tile, .. } => Some(*tile),
         }
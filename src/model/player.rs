@@ -1,5 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 use super::buildingarea::BuildingArea;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Player {
     name: String,
     building_area: BuildingArea,
@@ -29,6 +32,10 @@ impl Player {
         &mut self.building_area
     }
 
+    pub fn get_score(&self) -> u8 {
+        self.building_area.get_wall().count_points()
+    }
+
     pub fn has_first_player_token(&self) -> bool {
         self.building_area.get_floorline().has_first_player_token()
     }
@@ -0,0 +1,38 @@
+use std::cmp::min;
+
+use serde::{Deserialize, Serialize};
+
+use super::zobrist::MAX_FACTORIES;
+
+/// The parts of the rules that can be changed when setting up a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rules {
+    pub factories: usize,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self { factories: 4 }
+    }
+}
+
+impl Rules {
+    /// The most factories a game for `players` can have: 5 for 2 players, 7 for 3 and 9 for 4,
+    /// like in the board game. The tiles would run out too fast with more. The default of 4 fits
+    /// every game, from 2 to 4 players.
+    pub fn max_factories(players: usize) -> usize {
+        min(2 * players + 1, MAX_FACTORIES)
+    }
+
+    pub fn check(&self, players: usize) -> Result<(), String> {
+        let max = Self::max_factories(players);
+        if !(1..=max).contains(&self.factories) {
+            return Err(format!(
+                "A game for {} players needs between 1 and {} factories, not {}",
+                players, max, self.factories
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bag::Bag,
+    buildingarea::patternline::PatternLine,
+    player::Player,
+    zobrist::{ZobristHash, MAX_COMMON_AREA_TILES, MAX_FACTORIES},
+    CommonArea, Factory, FactoryId, Game, GameState, Tile, TileSource,
+};
+
+/// Everything about a game that players are allowed to see. The bag is left out on purpose,
+/// knowing its order would tell you which tiles come next.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub players: Vec<Player>,
+    pub factories: Vec<Factory>,
+    pub common_area: CommonArea,
+    pub current_player_id: usize,
+    pub is_over: bool,
+}
+
+impl<const N: usize> Game<N> {
+    pub fn snapshot(&self) -> GameSnapshot {
+        GameSnapshot {
+            players: self.players.to_vec(),
+            factories: self.factories.clone(),
+            common_area: self.common_area.clone(),
+            current_player_id: self.current_player_id,
+            is_over: self.is_over,
+        }
+    }
+
+    /// Restores a game from a snapshot. As the snapshot has no bag, the restored game can be
+    /// displayed and played until the end of the current round, but it cannot refill its
+    /// factories.
+    pub fn from_snapshot(snapshot: GameSnapshot) -> Result<Self, String> {
+        snapshot.check()?;
+        let player_count = snapshot.players.len();
+        let players: [Player; N] = snapshot
+            .players
+            .try_into()
+            .map_err(|_| format!("The snapshot has {} players, expected {}", player_count, N))?;
+        if snapshot.current_player_id >= N {
+            return Err(format!(
+                "Invalid current player in snapshot: {}",
+                snapshot.current_player_id
+            ));
+        }
+
+        let mut game = Game {
+            players,
+            factories: snapshot.factories,
+            state: GameState::PickSource,
+            bag: Bag::new(vec![], vec![], 0),
+            is_over: snapshot.is_over,
+            common_area: snapshot.common_area,
+            current_player_id: snapshot.current_player_id,
+            current_source: TileSource::Factory(FactoryId(0)),
+            zobrist: ZobristHash::default(),
+        };
+        game.reset_selection();
+        game.zobrist = game.compute_zobrist_hash();
+        Ok(game)
+    }
+}
+
+impl GameSnapshot {
+    /// Snapshots come from the other side of a connection, anything out of range would break the
+    /// game (and its hash) later on.
+    fn check(&self) -> Result<(), String> {
+        if self.factories.len() > MAX_FACTORIES {
+            return Err(format!(
+                "The snapshot has {} factories, at most {} are allowed",
+                self.factories.len(),
+                MAX_FACTORIES
+            ));
+        }
+        if self.common_area.0.len() > MAX_COMMON_AREA_TILES {
+            return Err(format!(
+                "The snapshot has {} tiles in the common area, at most {} are allowed",
+                self.common_area.0.len(),
+                MAX_COMMON_AREA_TILES
+            ));
+        }
+        for player in &self.players {
+            if !player.get_buildingarea().get_wall().has_usual_layout() {
+                return Err(format!(
+                    "The wall of {} in the snapshot has tiles out of place",
+                    player.get_name()
+                ));
+            }
+            let rows = player.get_buildingarea().get_rows();
+            for (row, line) in rows.iter().enumerate() {
+                let valid = match *line {
+                    PatternLine::Free { length } => length == row + 1,
+                    PatternLine::Taken {
+                        tile,
+                        length,
+                        taken,
+                    } => length == row + 1 && taken <= length && tile != Tile::FirstPlayer,
+                };
+                if !valid {
+                    return Err(format!(
+                        "Invalid pattern line {} of {} in snapshot: {:?}",
+                        row + 1,
+                        player.get_name(),
+                        line
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
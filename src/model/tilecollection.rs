@@ -68,7 +68,7 @@ impl<const N: usize> TileCollection for [Tile; N] {
     }
 }
 
-impl<'a> TileCollection for &'a [Tile] {
+impl TileCollection for &[Tile] {
     fn distinct_tiles(&self) -> Vec<Tile> {
         let mut result: Vec<Tile> = Vec::with_capacity(4);
         for t in *self {
@@ -1,14 +1,23 @@
 use self::player::PlayerAreaView;
 
 use super::buildingarea::ScoreView;
-use super::{CommonAreaView, Factory, FactoryId, Game, Tile};
+use super::clock::Clocks;
+use super::{CommonAreaView, Factory, FactoryId, Game, Pick, Tile, TileSource};
 use crate::visor::renderer::RootedRenderer;
-use crate::visor::view::PanelBuilder;
+use crate::visor::theme::Theme;
+use crate::visor::view::{PanelBuilder, TextView};
 use crate::{
     model::GameState,
-    visor::{layout::Layout, Component, UserEventHandled, UserInput},
+    visor::{
+        layout::{Align, Constraint, Layout},
+        Component, Coords, UserEventHandled, UserInput,
+    },
+};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Instant,
 };
-use std::{cell::RefCell, rc::Rc};
 
 pub mod player;
 
@@ -32,12 +41,14 @@ impl TileView {
 
 impl Component for TileView {
     fn render(&self, writer: &mut RootedRenderer) {
-        let s = if self.selected {
-            format!("|{}|", self.tile)
-        } else {
-            self.tile.to_string()
-        };
-        writer.write(&s);
+        let theme = writer.theme();
+        if self.selected {
+            writer.write(theme.markers.open.to_string());
+        }
+        writer.write(theme.tile(self.tile).span());
+        if self.selected {
+            writer.write(theme.markers.close.to_string());
+        }
     }
 
     fn handle(&mut self, _event: &UserInput) -> UserEventHandled {
@@ -49,6 +60,8 @@ pub struct FactoryView<'a> {
     factory: &'a Factory,
     selected_tile: Option<Tile>,
     is_selected: bool,
+    /// Where the tiles are picked from, once the factory can be clicked
+    source: Option<TileSource>,
 }
 
 impl<'a> FactoryView<'a> {
@@ -57,6 +70,14 @@ impl<'a> FactoryView<'a> {
             factory,
             selected_tile,
             is_selected,
+            source: None,
+        }
+    }
+
+    pub fn clickable(self, id: FactoryId) -> Self {
+        Self {
+            source: Some(TileSource::Factory(id)),
+            ..self
         }
     }
 }
@@ -68,18 +89,30 @@ impl<'a> From<FactoryView<'a>> for Box<dyn Component + 'a> {
 }
 
 pub fn render_pickables(is_selected: bool, tiles: &[Tile], selected_tiles: &[Tile]) -> String {
+    layout_pickables(Theme::default_ref(), is_selected, tiles, selected_tiles).0
+}
+
+/// The text of [`render_pickables`] in a theme, with the column of every tile in it
+fn layout_pickables(
+    theme: &Theme,
+    is_selected: bool,
+    tiles: &[Tile],
+    selected_tiles: &[Tile],
+) -> (String, Vec<(u16, Tile)>) {
     let mut output = String::new();
+    let mut columns = vec![];
     let mut iter = tiles.iter().peekable();
     if is_selected {
-        output += "--> ";
+        output += &theme.markers.source;
     }
     let mut began_selection = false;
     while let Some(t) = iter.next() {
         if !began_selection && selected_tiles.contains(t) {
-            output += "|";
+            output.push(theme.markers.open);
             began_selection = true;
         }
-        output += &t.to_string();
+        columns.push((output.chars().count() as u16, *t));
+        output.push(theme.tile(*t).glyph);
         if began_selection {
             let render_closing = match iter.peek() {
                 Some(next_tile) if !selected_tiles.contains(*next_tile) => true,
@@ -87,23 +120,57 @@ pub fn render_pickables(is_selected: bool, tiles: &[Tile], selected_tiles: &[Til
                 _ => false,
             };
             if render_closing {
-                output += "|";
+                output.push(theme.markers.close);
                 began_selection = false;
             }
         }
     }
-    output
+    (output, columns)
+}
+
+/// The tiles of a factory or of the common area, where a click picks the source or a tile
+pub struct PickablesView<'a> {
+    pub source: Option<TileSource>,
+    pub is_selected: bool,
+    pub tiles: &'a [Tile],
+    pub selected_tiles: Vec<Tile>,
+}
+
+impl Component for PickablesView<'_> {
+    fn render(&self, writer: &mut RootedRenderer) {
+        let theme = writer.theme();
+        let (output, columns) =
+            layout_pickables(theme, self.is_selected, self.tiles, &self.selected_tiles);
+        let chars: Vec<char> = output.chars().collect();
+        let mut written = 0;
+        for &(column, tile) in &columns {
+            let column = usize::from(column);
+            writer.write(chars[written..column].iter().collect::<String>());
+            writer.write(theme.tile(tile).span());
+            written = column + 1;
+        }
+        writer.write(chars[written..].iter().collect::<String>());
+        let Some(source) = self.source else {
+            return;
+        };
+        writer.on_click(UserInput::Pick(Pick::Source(source)));
+        for (column, tile) in columns {
+            let pick = UserInput::Pick(Pick::Tile(source, tile));
+            writer.add_hitbox(Coords::new(column + 1, 1), (1, 1), pick);
+        }
+    }
 }
 
 impl Component for FactoryView<'_> {
     fn render(&self, writer: &mut RootedRenderer) {
         if let Some(tiles) = self.factory.get_tiles() {
-            let selected_tiles = self
-                .selected_tile
-                .map(|t| vec![t])
-                .unwrap_or_default()
-                .into_boxed_slice();
-            writer.write(&render_pickables(self.is_selected, tiles, &selected_tiles));
+            PickablesView {
+                source: self.source,
+                is_selected: self.is_selected,
+                tiles,
+                selected_tiles: self.selected_tile.into_iter().collect(),
+            }
+            .render(writer);
         }
         writer.write("\n");
     }
@@ -128,21 +195,19 @@ impl<'a, const N: usize> From<&'a Game<N>> for FactoryAreaView<'a> {
     fn from(game: &'a Game<N>) -> Self {
         let factory_state = match game.state {
             GameState::PickSource => match game.current_source {
-                super::TileSource::Factory(factory_id) => {
-                    FactoryAreaState::SelectFactory(factory_id)
-                }
-                super::TileSource::CommonArea => FactoryAreaState::Passive,
+                TileSource::Factory(factory_id) => FactoryAreaState::SelectFactory(factory_id),
+                TileSource::CommonArea => FactoryAreaState::Passive,
             },
             GameState::PickTileFromSource { selected_tile, .. }
             | GameState::PickRowToPutTiles {
                 tile: selected_tile,
                 ..
             } => match game.current_source {
-                super::TileSource::Factory(factory_id) => FactoryAreaState::SelectTile {
+                TileSource::Factory(factory_id) => FactoryAreaState::SelectTile {
                     factory_id,
                     tile: selected_tile,
                 },
-                super::TileSource::CommonArea => FactoryAreaState::Passive,
+                TileSource::CommonArea => FactoryAreaState::Passive,
             },
         };
         let factories = game.get_factories();
@@ -169,7 +234,7 @@ impl Component for FactoryAreaView<'_> {
                         (false, None)
                     }
                 };
-                let view = FactoryView::new(f, selected_tile, is_selected);
+                let view = FactoryView::new(f, selected_tile, is_selected).clickable(i.into());
                 Box::new(view) as Box<dyn Component>
             })
             .collect();
@@ -187,29 +252,64 @@ impl Component for FactoryAreaView<'_> {
 
 pub struct GameView<const N: usize> {
     pub game: Rc<RefCell<Game<N>>>,
+    pub clocks: Option<Rc<RefCell<Clocks>>>,
 }
 
+/// The players on top, and the factories next to the common area and the scores below them. On
+/// narrow screens, everything goes one under the other.
 impl<const N: usize> Component for GameView<N> {
     fn render(&self, writer: &mut RootedRenderer) {
         let game: &Game<N> = &self.game.as_ref().borrow();
-        let player_area: PlayerAreaView = game.into();
+        let mut player_area: PlayerAreaView = game.into();
+        if let Some(clocks) = &self.clocks {
+            player_area = player_area.with_clocks(clocks.borrow().readings(Instant::now()));
+        }
         let factory_area: FactoryAreaView = game.into();
         let common_area: CommonAreaView = game.into();
         let score_view = ScoreView::new(game.get_players());
 
+        // The common area and the scores take the room the factories leave, so that the panels
+        // line up
+        let common_and_score = Layout::vertical(vec![Box::new(common_area), Box::new(score_view)])
+            .with_constraints(vec![Constraint::Auto, Constraint::Fill(1)])
+            .with_align(Align::Stretch);
+        let tiles = Layout::fitting(vec![Box::new(factory_area), Box::new(common_and_score)])
+            .with_constraints(vec![Constraint::Auto, Constraint::Fill(1)])
+            .with_align(Align::Stretch);
         let gameview = PanelBuilder::default()
-            .component(Box::new(Layout::vertical(vec![
-                Box::new(player_area),
-                Box::new(Layout::horizontal(vec![
-                    Box::new(factory_area),
-                    Box::new(Layout::vertical(vec![
-                        Box::new(common_area),
-                        Box::new(score_view),
-                    ])),
-                ])),
-            ])))
+            .component(Box::new(
+                Layout::vertical(vec![Box::new(player_area), Box::new(tiles)])
+                    .with_align(Align::Stretch),
+            ))
             .build()
             .unwrap();
         gameview.render(writer);
     }
 }
+
+/// A game at one keyboard, with the keys beside it while the players ask for them
+pub struct LocalGameView<const N: usize> {
+    pub game: GameView<N>,
+    pub keys: Vec<String>,
+    pub show_keys: Rc<Cell<bool>>,
+}
+
+impl<const N: usize> Component for LocalGameView<N> {
+    fn render(&self, writer: &mut RootedRenderer) {
+        if !self.show_keys.get() {
+            self.game.render(writer);
+            return;
+        }
+        let keys = PanelBuilder::default()
+            .name("Keys")
+            .padding(1)
+            .component(Box::new(TextView::new(self.keys.join("\n"))))
+            .build()
+            .unwrap();
+        let game = GameView {
+            game: self.game.game.clone(),
+            clocks: self.game.clocks.clone(),
+        };
+        Layout::fitting(vec![Box::new(game), Box::new(keys)]).render(writer);
+    }
+}
@@ -1,25 +1,41 @@
 use crate::{
-    model::{buildingarea::BuildingAreaView, player::Player, Game, GameState},
-    visor::{layout::Layout, renderer::RootedRenderer, view::PanelBuilder, Component},
+    model::{
+        buildingarea::{BuildingAreaView, Destination},
+        player::Player,
+        Game, GameState,
+    },
+    visor::{
+        layout::{Align, Constraint, Layout},
+        renderer::RootedRenderer,
+        view::PanelBuilder,
+        Component,
+    },
 };
 
 pub struct PlayerView<'a> {
     player: &'a Player,
-    selected_building_row: Option<usize>,
+    selected_building_row: Option<Destination>,
+    clickable: bool,
 }
 
 impl<'a> PlayerView<'a> {
-    pub fn new(player: &'a Player, selected_building_row: Option<usize>) -> Self {
+    pub fn new(player: &'a Player, selected_building_row: Option<Destination>) -> Self {
         Self {
             player,
             selected_building_row,
+            clickable: false,
         }
     }
+
+    pub fn clickable(self, clickable: bool) -> Self {
+        Self { clickable, ..self }
+    }
 }
 
 impl<'a> Component for PlayerView<'a> {
     fn render(&self, writer: &mut RootedRenderer) {
         BuildingAreaView::new(self.player.get_buildingarea(), self.selected_building_row)
+            .clickable(self.clickable)
             .render(writer);
     }
 }
@@ -27,7 +43,9 @@ impl<'a> Component for PlayerView<'a> {
 pub struct PlayerAreaView<'a> {
     players: &'a [Player],
     current_player_id: usize,
-    selected_building_row: Option<usize>,
+    selected_building_row: Option<Destination>,
+    /// What the clock of each player shows, if the game has clocks
+    clocks: Vec<String>,
 }
 
 impl<'a, const N: usize> From<&'a Game<N>> for PlayerAreaView<'a> {
@@ -37,10 +55,9 @@ impl<'a, const N: usize> From<&'a Game<N>> for PlayerAreaView<'a> {
             GameState::PickSource | GameState::PickTileFromSource { .. } => {
                 PlayerAreaView::new(players, game.current_player_id, None)
             }
-            GameState::PickRowToPutTiles {
-                tile: _,
-                selected_row_id,
-            } => PlayerAreaView::new(players, game.current_player_id, Some(selected_row_id)),
+            GameState::PickRowToPutTiles { destination, .. } => {
+                PlayerAreaView::new(players, game.current_player_id, Some(destination))
+            }
         }
     }
 }
@@ -49,14 +66,19 @@ impl<'a> PlayerAreaView<'a> {
     pub fn new(
         players: &'a [Player],
         current_player_id: usize,
-        selected_building_row: Option<usize>,
+        selected_building_row: Option<Destination>,
     ) -> Self {
         Self {
             players,
             current_player_id,
             selected_building_row,
+            clocks: vec![],
         }
     }
+
+    pub fn with_clocks(self, clocks: Vec<String>) -> Self {
+        Self { clocks, ..self }
+    }
 }
 
 impl<'a> Component for PlayerAreaView<'a> {
@@ -68,13 +90,19 @@ impl<'a> Component for PlayerAreaView<'a> {
             .map(|(i, player)| {
                 let is_active_player = i == self.current_player_id;
                 let selected_row = match (is_active_player, self.selected_building_row) {
-                    (true, Some(row_number)) => Some(row_number),
+                    (true, Some(destination)) => Some(destination),
                     _ => None,
                 };
+                let name = match self.clocks.get(i) {
+                    Some(clock) => format!("{} {}", player.get_name(), clock),
+                    None => player.get_name().to_owned(),
+                };
                 let p = PanelBuilder::default()
-                    .name(player.get_name().to_owned())
+                    .name(name)
                     .padding(0)
-                    .component(Box::new(PlayerView::new(player, selected_row)))
+                    .component(Box::new(
+                        PlayerView::new(player, selected_row).clickable(is_active_player),
+                    ))
                     .build()
                     .unwrap();
 
@@ -83,7 +111,12 @@ impl<'a> Component for PlayerAreaView<'a> {
             .collect();
         let panel = PanelBuilder::default()
             .name(String::from("Player area"))
-            .component(Box::new(Layout::horizontal(players)))
+            .component(Box::new(
+                // The boards share the width evenly
+                Layout::fitting(players)
+                    .with_constraints(vec![Constraint::Fill(1); self.players.len()])
+                    .with_align(Align::Stretch),
+            ))
             .build()
             .unwrap();
 
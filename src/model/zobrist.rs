@@ -0,0 +1,143 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::BitXorAssign;
+use std::sync::OnceLock;
+
+use super::Tile;
+
+pub const MAX_PLAYERS: usize = 4;
+pub const MAX_FACTORIES: usize = 9;
+
+const ROWS: usize = 5;
+const FLOORLINE_SLOTS: usize = 7;
+const TILE_KINDS: usize = 6;
+// Every tile of the game could theoretically end up in the common area
+pub(crate) const MAX_COMMON_AREA_TILES: usize = 100;
+// Fixed, so hashes of the same position match across processes and machines
+const SEED: u64 = 0x617a_756c_2d72_7321;
+
+/// The random keys that get XORed together to produce the hash of a position. A component only
+/// contributes keys for the things that are actually on it, so an empty patternline or an empty
+/// factory hashes to 0.
+pub(crate) struct ZobristKeys {
+    /// `[player][row][col]`, toggled when the slot is filled
+    pub wall: [[[u64; ROWS]; ROWS]; MAX_PLAYERS],
+    /// `[player][row][tile][taken]`
+    pub pattern_line: [[[[u64; ROWS + 1]; TILE_KINDS]; ROWS]; MAX_PLAYERS],
+    /// `[player][slot][tile]`
+    pub floor_line: [[[u64; TILE_KINDS]; FLOORLINE_SLOTS]; MAX_PLAYERS],
+    /// `[factory][tile][count]`
+    pub factory: [[[u64; super::TILE_PER_FACTORY + 1]; TILE_KINDS]; MAX_FACTORIES],
+    /// `[tile][count]`
+    pub common_area: [[u64; MAX_COMMON_AREA_TILES + 1]; TILE_KINDS],
+    pub first_player_in_common_area: u64,
+    pub side_to_move: [u64; MAX_PLAYERS],
+}
+
+impl ZobristKeys {
+    fn generate() -> Self {
+        // splitmix64, good enough for spreading out keys and has no dependencies
+        let mut state = SEED;
+        let mut next = move || {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+
+        Self {
+            wall: std::array::from_fn(|_| std::array::from_fn(|_| std::array::from_fn(|_| next()))),
+            pattern_line: std::array::from_fn(|_| {
+                std::array::from_fn(|_| std::array::from_fn(|_| std::array::from_fn(|_| next())))
+            }),
+            floor_line: std::array::from_fn(|_| {
+                std::array::from_fn(|_| std::array::from_fn(|_| next()))
+            }),
+            factory: std::array::from_fn(|_| {
+                std::array::from_fn(|_| std::array::from_fn(|_| next()))
+            }),
+            common_area: std::array::from_fn(|_| std::array::from_fn(|_| next())),
+            first_player_in_common_area: next(),
+            side_to_move: std::array::from_fn(|_| next()),
+        }
+    }
+}
+
+pub(crate) fn keys() -> &'static ZobristKeys {
+    static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+    KEYS.get_or_init(ZobristKeys::generate)
+}
+
+pub(crate) fn tile_index(tile: Tile) -> usize {
+    tile as usize
+}
+
+/// A 64 bit Zobrist hash of a game position.
+///
+/// Two positions with the same tiles in the same places and the same player to move hash to the
+/// same value, regardless of the moves that led there.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ZobristHash(u64);
+
+impl ZobristHash {
+    pub fn new(value: u64) -> Self {
+        Self(value)
+    }
+
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+
+    pub(crate) fn toggle(&mut self, key: u64) {
+        self.0 ^= key;
+    }
+}
+
+impl BitXorAssign<u64> for ZobristHash {
+    fn bitxor_assign(&mut self, rhs: u64) {
+        self.toggle(rhs);
+    }
+}
+
+impl std::fmt::Display for ZobristHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl std::str::FromStr for ZobristHash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16)
+            .map(Self)
+            .map_err(|e| format!("Invalid hash {}: {}", s, e))
+    }
+}
+
+// Serialized as a hex string, a 64 bit number does not survive a trip through a JSON parser that
+// only knows doubles
+impl Serialize for ZobristHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ZobristHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::keys;
+
+    // Peers compare hashes with each other, so the keys must not change between builds
+    #[test]
+    fn test_keys_are_stable() {
+        assert_eq!(keys().wall[0][0][0], 0x389f_95d9_ff12_0207);
+        assert_ne!(keys().side_to_move[0], keys().side_to_move[1]);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use super::protocol::ChatMessage;
+
+/// In characters, not bytes
+pub const MAX_CHAT_LENGTH: usize = 200;
+/// Everybody can send this many messages in every window
+pub const CHAT_BURST: usize = 5;
+pub const CHAT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Cleans up a chat message, or explains why it cannot be sent.
+pub fn check_chat_text(text: &str) -> Result<String, String> {
+    // Control characters would mess up the terminals of the others
+    let text: String = text.trim().chars().filter(|c| !c.is_control()).collect();
+    if text.is_empty() {
+        return Err(String::from("Chat messages cannot be empty"));
+    }
+    if text.chars().count() > MAX_CHAT_LENGTH {
+        return Err(format!(
+            "Chat messages can be at most {} characters",
+            MAX_CHAT_LENGTH
+        ));
+    }
+    Ok(text)
+}
+
+/// Keeps track of when somebody last talked, so nobody can flood the table.
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    sent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    /// Returns false if the message has to be dropped, otherwise it counts towards the limit.
+    pub(crate) fn allow(&mut self, now: Instant) -> bool {
+        while self
+            .sent
+            .front()
+            .is_some_and(|&sent| now.duration_since(sent) >= CHAT_WINDOW)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= CHAT_BURST {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+}
+
+/// The chat as the client sees it: what was said, and what we are typing
+#[derive(Default)]
+pub struct Chat {
+    pub messages: Vec<ChatMessage>,
+    /// `Some` while we are typing a message
+    pub draft: Option<String>,
+}
+
+impl Chat {
+    pub fn is_typing(&self) -> bool {
+        self.draft.is_some()
+    }
+
+    pub fn start_typing(&mut self) {
+        self.draft = Some(String::new());
+    }
+
+    /// Anything beyond the length limit is dropped, the server would refuse it anyway
+    pub fn type_char(&mut self, c: char) {
+        if let Some(draft) = &mut self.draft {
+            if !c.is_control() && draft.chars().count() < MAX_CHAT_LENGTH {
+                draft.push(c);
+            }
+        }
+    }
+
+    pub fn delete_char(&mut self) {
+        if let Some(draft) = &mut self.draft {
+            draft.pop();
+        }
+    }
+
+    /// Stops typing, and returns what we typed
+    pub fn take_draft(&mut self) -> Option<String> {
+        self.draft.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::{check_chat_text, RateLimiter, CHAT_BURST, CHAT_WINDOW, MAX_CHAT_LENGTH};
+
+    #[test]
+    fn test_chat_text_is_cleaned_up() {
+        assert_eq!(check_chat_text("  hi\u{1b}[2J "), Ok(String::from("hi[2J")));
+        assert!(check_chat_text(" \n ").is_err());
+        assert!(check_chat_text(&"é".repeat(MAX_CHAT_LENGTH)).is_ok());
+        assert!(check_chat_text(&"a".repeat(MAX_CHAT_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter_forgets_old_messages() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        for _ in 0..CHAT_BURST {
+            assert!(limiter.allow(start));
+        }
+        assert!(!limiter.allow(start + CHAT_WINDOW / 2));
+        assert!(limiter.allow(start + CHAT_WINDOW));
+    }
+}
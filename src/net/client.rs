@@ -0,0 +1,479 @@
+use std::{
+    cell::RefCell,
+    io::{self, BufReader},
+    net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs},
+    rc::Rc,
+    sync::mpsc::Sender,
+    thread,
+};
+
+use crate::{
+    model::{player::Player, rules::Rules, Game, Move},
+    visor::UserInput,
+};
+
+use super::{
+    chat::Chat,
+    protocol::{
+        read_message, write_message, ArchivedTable, ClientMessage, GameEvent, ServerMessage,
+        TableId, TableInfo, PROTOCOL_VERSION,
+    },
+    view::RemoteGameView,
+};
+
+// Sends a single request on a new connection, and waits for the matching answer
+fn request<A: ToSocketAddrs, T>(
+    address: A,
+    message: &ClientMessage,
+    mut matcher: impl FnMut(ServerMessage) -> Option<T>,
+) -> io::Result<T> {
+    let mut writer = TcpStream::connect(address)?;
+    let mut reader = BufReader::new(writer.try_clone()?);
+    write_message(&mut writer, message)?;
+    loop {
+        match read_message(&mut reader)? {
+            Some(ServerMessage::Error { message }) => return Err(io::Error::other(message)),
+            Some(message) => {
+                if let Some(found) = matcher(message) {
+                    return Ok(found);
+                }
+            }
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+pub fn list_tables<A: ToSocketAddrs>(address: A) -> io::Result<Vec<TableInfo>> {
+    request(
+        address,
+        &ClientMessage::ListTables,
+        |message| match message {
+            ServerMessage::Tables { tables } => Some(tables),
+            _ => None,
+        },
+    )
+}
+
+pub fn create_table<A: ToSocketAddrs>(
+    address: A,
+    players: usize,
+    rules: Rules,
+    seed: Option<u64>,
+) -> io::Result<TableId> {
+    let create = ClientMessage::CreateTable {
+        version: PROTOCOL_VERSION,
+        players,
+        rules,
+        seed,
+    };
+    request(address, &create, |message| match message {
+        ServerMessage::TableCreated { table } => Some(table),
+        _ => None,
+    })
+}
+
+pub fn list_archive<A: ToSocketAddrs>(address: A) -> io::Result<Vec<ArchivedTable>> {
+    request(
+        address,
+        &ClientMessage::ListArchive,
+        |message| match message {
+            ServerMessage::Archive { tables } => Some(tables),
+            _ => None,
+        },
+    )
+}
+
+pub enum NetworkEvent {
+    Message(ServerMessage),
+    Disconnected,
+}
+
+/// A connection to a server, where we already have a seat (or watch as a spectator)
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    address: SocketAddr,
+    table: TableId,
+    seat: Option<usize>,
+    /// Lets us resume the session, `None` for spectators
+    token: Option<String>,
+    players: usize,
+}
+
+impl Connection {
+    /// Connects to the server and joins the table (or any open table), waiting until the server
+    /// gives us a seat.
+    pub fn open<A: ToSocketAddrs>(
+        address: A,
+        name: &str,
+        table: Option<TableId>,
+    ) -> io::Result<Self> {
+        let join = ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            name: name.to_owned(),
+            table,
+        };
+        Self::handshake(address, &join)
+    }
+
+    /// Joins any table with a free seat. Without an open table there is nothing to join, so this
+    /// starts a new table for two.
+    pub fn join_any<A: ToSocketAddrs + Copy>(address: A, name: &str) -> io::Result<Self> {
+        let tables = list_tables(address)?;
+        let table = match tables.iter().any(TableInfo::has_free_seat) {
+            true => None,
+            false => Some(create_table(address, 2, Rules::default(), None)?),
+        };
+        Self::open(address, name, table)
+    }
+
+    /// Connects to the server to watch the game at the table (or the first table).
+    pub fn spectate<A: ToSocketAddrs>(
+        address: A,
+        name: &str,
+        table: Option<TableId>,
+    ) -> io::Result<Self> {
+        let spectate = ClientMessage::Spectate {
+            version: PROTOCOL_VERSION,
+            name: name.to_owned(),
+            table,
+        };
+        Self::handshake(address, &spectate)
+    }
+
+    /// Connects to the server and takes back the seat we had before losing the connection.
+    pub fn resume<A: ToSocketAddrs>(address: A, token: &str) -> io::Result<Self> {
+        let resume = ClientMessage::Resume {
+            version: PROTOCOL_VERSION,
+            token: token.to_owned(),
+        };
+        Self::handshake(address, &resume)
+    }
+
+    fn handshake<A: ToSocketAddrs>(address: A, hello: &ClientMessage) -> io::Result<Self> {
+        let mut writer = TcpStream::connect(address)?;
+        let address = writer.peer_addr()?;
+        let mut reader = BufReader::new(writer.try_clone()?);
+        write_message(&mut writer, hello)?;
+
+        loop {
+            let (table, seat, token, players) = match read_message(&mut reader)? {
+                Some(ServerMessage::Welcome {
+                    table,
+                    seat,
+                    players,
+                    token,
+                    ..
+                }) => (table, Some(seat), Some(token), players),
+                Some(ServerMessage::Spectating { table, players, .. }) => {
+                    (table, None, None, players)
+                }
+                Some(ServerMessage::Error { message }) => return Err(io::Error::other(message)),
+                Some(_) => continue,
+                None => return Err(io::ErrorKind::UnexpectedEof.into()),
+            };
+            return Ok(Self {
+                reader,
+                writer,
+                address,
+                table,
+                seat,
+                token,
+                players,
+            });
+        }
+    }
+
+    pub fn table(&self) -> TableId {
+        self.table
+    }
+
+    /// `None` for spectators
+    pub fn seat(&self) -> Option<usize> {
+        self.seat
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    pub fn players(&self) -> usize {
+        self.players
+    }
+
+    /// Forwards everything the server sends to `events` on a separate thread, and returns the
+    /// writing half of the connection.
+    fn listen<E: From<NetworkEvent> + Send + 'static>(self, events: Sender<E>) -> TcpStream {
+        let mut reader = self.reader;
+        thread::spawn(move || loop {
+            let event = match read_message(&mut reader) {
+                Ok(Some(message)) => NetworkEvent::Message(message),
+                Ok(None) | Err(_) => NetworkEvent::Disconnected,
+            };
+            let disconnected = matches!(event, NetworkEvent::Disconnected);
+            if events.send(event.into()).is_err() || disconnected {
+                return;
+            }
+        });
+        self.writer
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionStatus {
+    Connected,
+    Disconnected,
+}
+
+/// Everything the client knows, apart from the game itself
+pub struct ClientStatus {
+    pub address: SocketAddr,
+    pub table: TableId,
+    /// `None` if we are only watching
+    pub seat: Option<usize>,
+    pub spectators: usize,
+    /// Seats of the players who lost their connection, the game is paused until they are back
+    pub away: Vec<usize>,
+    pub connection: ConnectionStatus,
+    /// The move we have sent, but the server has not acknowledged yet
+    pub pending_move: Option<Move>,
+    pub last_error: Option<String>,
+    /// True once we told the server we want to start
+    pub ready: bool,
+    /// False until the server sends the first state
+    pub game_started: bool,
+}
+
+/// A game that is played on a server. The server is the source of truth, the local copy of the
+/// game is only used for displaying it, and for moving the selection around.
+pub struct RemoteGame<const N: usize> {
+    pub game: Rc<RefCell<Game<N>>>,
+    pub status: Rc<RefCell<ClientStatus>>,
+    pub chat: Rc<RefCell<Chat>>,
+    writer: TcpStream,
+    token: Option<String>,
+}
+
+impl<const N: usize> RemoteGame<N> {
+    /// Starts listening to the server, everything it sends will arrive through `events`, and
+    /// should be passed to [`RemoteGame::handle_network_event`].
+    pub fn new<E: From<NetworkEvent> + Send + 'static>(
+        connection: Connection,
+        events: Sender<E>,
+    ) -> io::Result<Self> {
+        if connection.players != N {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("The game is for {} players, not {}", connection.players, N),
+            ));
+        }
+        let status = ClientStatus {
+            address: connection.address,
+            table: connection.table,
+            seat: connection.seat,
+            spectators: 0,
+            away: vec![],
+            connection: ConnectionStatus::Connected,
+            pending_move: None,
+            last_error: None,
+            ready: false,
+            game_started: false,
+        };
+        let token = connection.token.clone();
+        let writer = connection.listen(events);
+        // Placeholder until the first state arrives
+        let players = std::array::from_fn(|_| Player::default_with_name(String::new()));
+        Ok(Self {
+            game: Rc::new(RefCell::new(Game::for_players(players))),
+            status: Rc::new(RefCell::new(status)),
+            chat: Rc::new(RefCell::new(Chat::default())),
+            writer,
+            token,
+        })
+    }
+
+    /// Opens a new connection after losing the old one, and takes back our seat. The server
+    /// sends the full state again, which arrives through `events` like everything else.
+    pub fn reconnect<E: From<NetworkEvent> + Send + 'static>(
+        &mut self,
+        events: Sender<E>,
+    ) -> io::Result<()> {
+        let token = self
+            .token
+            .as_deref()
+            .ok_or_else(|| io::Error::other("Spectators have no session to resume"))?;
+        let address = self.status.borrow().address;
+        let connection = Connection::resume(address, token)?;
+
+        let mut status = self.status.borrow_mut();
+        if connection.seat != status.seat {
+            return Err(io::Error::other("The server gave us a different seat"));
+        }
+        self.writer = connection.listen(events);
+        status.connection = ConnectionStatus::Connected;
+        // The server tells us again who is away
+        status.away.clear();
+        status.pending_move = None;
+        status.last_error = None;
+        Ok(())
+    }
+
+    pub fn view(&self) -> RemoteGameView<N> {
+        RemoteGameView::new(self.game.clone(), self.status.clone(), self.chat.clone())
+    }
+
+    pub fn is_my_turn(&self) -> bool {
+        let status = self.status.borrow();
+        status.connection == ConnectionStatus::Connected
+            && status.game_started
+            && status.pending_move.is_none()
+            && status.away.is_empty()
+            && !self.game.borrow().is_over()
+            && Some(self.game.borrow().get_current_player_id()) == status.seat
+    }
+
+    /// While typing a chat message, every character goes into the message.
+    pub fn is_typing(&self) -> bool {
+        self.chat.borrow().is_typing()
+    }
+
+    /// Returns true if the user wants to quit.
+    pub fn handle_input(&mut self, input: UserInput) -> bool {
+        if self.is_typing() {
+            self.handle_chat_input(input);
+            return false;
+        }
+        match input {
+            UserInput::Exit => return true,
+            UserInput::Character('t') => {
+                self.chat.borrow_mut().start_typing();
+                return false;
+            }
+            _ => {}
+        }
+        if self.can_get_ready() {
+            if let UserInput::Confirm = input {
+                self.send(&ClientMessage::Ready);
+                self.status.borrow_mut().ready = true;
+            }
+            return false;
+        }
+        if !self.is_my_turn() {
+            return false;
+        }
+
+        let selected_move = self.game.borrow().selected_move();
+        match (input, selected_move) {
+            (UserInput::Confirm, Some(mv)) => self.submit(mv),
+            (input, _) => {
+                self.game.borrow_mut().handle(input);
+            }
+        }
+        false
+    }
+
+    fn handle_chat_input(&mut self, input: UserInput) {
+        let mut chat = self.chat.borrow_mut();
+        match input {
+            UserInput::Character(c) => chat.type_char(c),
+            UserInput::Back => chat.delete_char(),
+            UserInput::Confirm => {
+                let text = chat.take_draft().unwrap_or_default();
+                drop(chat);
+                if !text.trim().is_empty() {
+                    self.send(&ClientMessage::Chat { text });
+                }
+            }
+            UserInput::Exit => {
+                chat.take_draft();
+            }
+            UserInput::Direction(_)
+            | UserInput::Select(_)
+            | UserInput::Help
+            | UserInput::Click(..)
+            | UserInput::Pick(_)
+            | UserInput::Noop => {}
+        }
+    }
+
+    /// Players have to tell the server when they are ready to start
+    pub fn can_get_ready(&self) -> bool {
+        let status = self.status.borrow();
+        status.connection == ConnectionStatus::Connected
+            && status.seat.is_some()
+            && !status.ready
+            && !status.game_started
+    }
+
+    fn submit(&mut self, mv: Move) {
+        self.status.borrow_mut().last_error = None;
+        if self.send(&ClientMessage::Move(mv)) {
+            self.status.borrow_mut().pending_move = Some(mv);
+        }
+    }
+
+    fn send(&mut self, message: &ClientMessage) -> bool {
+        let sent = write_message(&mut self.writer, message).is_ok();
+        if !sent {
+            self.status.borrow_mut().connection = ConnectionStatus::Disconnected;
+        }
+        sent
+    }
+
+    pub fn handle_network_event(&mut self, event: NetworkEvent) {
+        match event {
+            NetworkEvent::Message(message) => self.handle_message(message),
+            NetworkEvent::Disconnected => {
+                self.status.borrow_mut().connection = ConnectionStatus::Disconnected
+            }
+        }
+    }
+
+    fn handle_message(&mut self, message: ServerMessage) {
+        let mut status = self.status.borrow_mut();
+        match message {
+            // Nobody else can move while we wait, so the first state after our move is the
+            // server acknowledging it
+            ServerMessage::State { game, .. } => match Game::from_snapshot(game) {
+                Ok(game) => {
+                    *self.game.borrow_mut() = game;
+                    status.game_started = true;
+                    status.pending_move = None;
+                }
+                Err(e) => status.last_error = Some(e),
+            },
+            ServerMessage::Event {
+                event: GameEvent::SpectatorsChanged { count },
+            } => status.spectators = count,
+            ServerMessage::Event {
+                event: GameEvent::Chat(message),
+            } => self.chat.borrow_mut().messages.push(message),
+            ServerMessage::Event {
+                event: GameEvent::PlayerDisconnected { seat },
+            } => status.away.push(seat),
+            ServerMessage::Event {
+                event: GameEvent::PlayerReconnected { seat },
+            } => status.away.retain(|&s| s != seat),
+            ServerMessage::MoveRefused { mv, message } => {
+                if status.pending_move == Some(mv) {
+                    status.pending_move = None;
+                }
+                status.last_error = Some(message);
+            }
+            ServerMessage::Error { message } => status.last_error = Some(message),
+            ServerMessage::Welcome { .. }
+            | ServerMessage::Spectating { .. }
+            | ServerMessage::Event { .. }
+            | ServerMessage::Tables { .. }
+            | ServerMessage::TableCreated { .. }
+            | ServerMessage::Archive { .. } => {}
+        }
+    }
+}
+
+impl<const N: usize> Drop for RemoteGame<N> {
+    fn drop(&mut self) {
+        // The listening thread holds the other half, dropping ours alone would not hang up
+        let _ = self.writer.shutdown(Shutdown::Both);
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    model::{bag::Bag, player::Player, rules::Rules, zobrist::ZobristHash, Game, Move},
+    visor::UserInput,
+};
+
+use super::view::TurnFileView;
+
+/// Bumped whenever the format of turn files changes
+pub const TURN_FILE_VERSION: u32 = 1;
+
+/// A game played by mail: everything needed to replay it, and every move so far. Each player
+/// adds one move and sends the file on to the next one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnFile {
+    pub version: u32,
+    /// Tells games apart, so that a file cannot be mixed up with the file of another game
+    pub game: u64,
+    pub players: Vec<String>,
+    pub rules: Rules,
+    pub seed: u64,
+    pub moves: Vec<TurnRecord>,
+}
+
+/// A move, with the game right after it, chained to every move before it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnRecord {
+    pub seat: usize,
+    #[serde(rename = "move")]
+    pub mv: Move,
+    pub hash: ZobristHash,
+    /// Covers this move and the whole history before it
+    pub chain: String,
+}
+
+/// How far a player has seen a game, to catch files that go back in time or rewrite history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Seen {
+    pub game: u64,
+    pub moves: usize,
+    pub chain: String,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn link(previous: &str, seat: usize, mv: Move, hash: ZobristHash) -> String {
+    let mv = serde_json::to_string(&mv).expect("Moves can always be serialized");
+    let digest = Sha256::new()
+        .chain_update(previous.as_bytes())
+        .chain_update(seat.to_le_bytes())
+        .chain_update(mv.as_bytes())
+        .chain_update(hash.value().to_le_bytes())
+        .finalize();
+    hex(&digest)
+}
+
+fn check_setup(players: &[String], rules: Rules) -> Result<(), String> {
+    if !(2..=4).contains(&players.len()) {
+        return Err(format!(
+            "A game is for 2 to 4 players, not {}",
+            players.len()
+        ));
+    }
+    if (1..players.len()).any(|i| players[..i].contains(&players[i])) {
+        return Err(String::from("Every player needs a different name"));
+    }
+    rules.check(players.len())
+}
+
+impl TurnFile {
+    pub fn new(players: Vec<String>, rules: Rules, seed: u64, game: u64) -> Result<Self, String> {
+        check_setup(&players, rules)?;
+        Ok(Self {
+            version: TURN_FILE_VERSION,
+            game,
+            players,
+            rules,
+            seed,
+            moves: vec![],
+        })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let file: Self =
+            serde_json::from_str(json).map_err(|e| format!("This is not a turn file: {}", e))?;
+        if file.version != TURN_FILE_VERSION {
+            return Err(format!(
+                "The turn file is version {}, this azulrs reads version {}",
+                file.version, TURN_FILE_VERSION
+            ));
+        }
+        // Anybody can write a file, it is checked like a new game
+        check_setup(&file.players, file.rules)?;
+        Ok(file)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Turn files can always be serialized")
+    }
+
+    /// The start of the chain, covering the setup of the game
+    fn genesis(&self) -> String {
+        let setup = serde_json::to_string(&(self.game, &self.players, self.rules, self.seed))
+            .expect("The setup can always be serialized");
+        hex(&Sha256::digest(setup.as_bytes()))
+    }
+
+    /// The end of the chain, which changes with every move
+    pub fn head(&self) -> String {
+        self.moves
+            .last()
+            .map_or_else(|| self.genesis(), |record| record.chain.clone())
+    }
+
+    pub fn seen(&self) -> Seen {
+        Seen {
+            game: self.game,
+            moves: self.moves.len(),
+            chain: self.head(),
+        }
+    }
+
+    /// Refuses a file for another game, one older than what we saw, or one with another history
+    pub fn check_follows(&self, seen: &Seen) -> Result<(), String> {
+        if seen.game != self.game {
+            return Err(String::from("This file is for another game"));
+        }
+        if self.moves.len() < seen.moves {
+            return Err(format!(
+                "This file is older than the last one you saw: it has {} moves, you saw {}",
+                self.moves.len(),
+                seen.moves
+            ));
+        }
+        let chain = match seen.moves {
+            0 => self.genesis(),
+            n => self.moves[n - 1].chain.clone(),
+        };
+        if chain != seen.chain {
+            return Err(String::from(
+                "This file does not continue the game you saw, its history was changed",
+            ));
+        }
+        Ok(())
+    }
+
+    fn new_game<const N: usize>(&self) -> Result<Game<N>, String> {
+        let players: [Player; N] = self
+            .players
+            .iter()
+            .cloned()
+            .map(Player::default_with_name)
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| {
+                format!(
+                    "The game has {} players, expected {}",
+                    self.players.len(),
+                    N
+                )
+            })?;
+        Ok(Game::with_rules(
+            players,
+            Bag::seeded(self.seed),
+            self.rules,
+        ))
+    }
+
+    /// Plays every move again, checking each one against the chain and the recorded game
+    pub fn replay<const N: usize>(&self) -> Result<Game<N>, String> {
+        let mut game = self.new_game::<N>()?;
+        let mut chain = self.genesis();
+        for (i, record) in self.moves.iter().enumerate() {
+            let number = i + 1;
+            if link(&chain, record.seat, record.mv, record.hash) != record.chain {
+                return Err(format!(
+                    "Move {} does not follow the move before it",
+                    number
+                ));
+            }
+            let current = game.get_current_player_id();
+            if record.seat != current || game.is_over() {
+                return Err(format!(
+                    "Move {} was played by {}, out of turn",
+                    number,
+                    self.name(record.seat)
+                ));
+            }
+            game.apply_move(record.seat, record.mv)
+                .map_err(|e| format!("Move {} is illegal: {}", number, e))?;
+            if game.zobrist_hash() != record.hash {
+                return Err(format!(
+                    "The game after move {} is not the one in the file",
+                    number
+                ));
+            }
+            chain = record.chain.clone();
+        }
+        Ok(game)
+    }
+
+    /// Plays the move of the current player on `game`, which is this file replayed, and records it
+    pub fn play<const N: usize>(&mut self, game: &mut Game<N>, mv: Move) -> Result<(), String> {
+        if game.is_over() {
+            return Err(String::from("The game is over"));
+        }
+        let seat = game.get_current_player_id();
+        game.apply_move(seat, mv)?;
+        let hash = game.zobrist_hash();
+        let chain = link(&self.head(), seat, mv, hash);
+        self.moves.push(TurnRecord {
+            seat,
+            mv,
+            hash,
+            chain,
+        });
+        Ok(())
+    }
+
+    pub fn name(&self, seat: usize) -> &str {
+        self.players.get(seat).map_or("Nobody", String::as_str)
+    }
+}
+
+/// Where a player keeps how far they have seen each of their games
+pub struct SeenGames {
+    dir: PathBuf,
+}
+
+impl SeenGames {
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_owned(),
+        })
+    }
+
+    // Names come from turn files, so they only go into the file name hashed
+    fn path(&self, game: u64, name: &str) -> PathBuf {
+        let name = hex(&Sha256::digest(name.as_bytes())[..8]);
+        self.dir.join(format!("{:016x}-{}.json", game, name))
+    }
+
+    pub fn get(&self, game: u64, name: &str) -> io::Result<Option<Seen>> {
+        match fs::read_to_string(self.path(game, name)) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, name: &str, seen: &Seen) -> io::Result<()> {
+        fs::write(self.path(seen.game, name), serde_json::to_vec(seen)?)
+    }
+}
+
+/// The turn of one player on a game played by mail
+pub struct CorrespondenceTurn<const N: usize> {
+    pub game: Rc<RefCell<Game<N>>>,
+    pub file: TurnFile,
+    pub last_error: Rc<RefCell<Option<String>>>,
+    played: bool,
+}
+
+impl<const N: usize> CorrespondenceTurn<N> {
+    /// Replays `file`, and checks that it is the turn of `name`
+    pub fn new(file: TurnFile, name: &str) -> Result<Self, String> {
+        let game = file.replay::<N>()?;
+        if game.is_over() {
+            return Err(String::from("The game is over"));
+        }
+        let current = file.name(game.get_current_player_id());
+        if current != name {
+            return Err(format!("It is the turn of {}, not {}", current, name));
+        }
+        Ok(Self {
+            game: Rc::new(RefCell::new(game)),
+            file,
+            last_error: Rc::default(),
+            played: false,
+        })
+    }
+
+    pub fn view(&self) -> TurnFileView<N> {
+        TurnFileView::new(self.game.clone(), self.last_error.clone())
+    }
+
+    /// Returns true once the move is played, or the player gave up on it
+    pub fn handle_input(&mut self, input: UserInput) -> bool {
+        if let UserInput::Exit = input {
+            return true;
+        }
+        let selected_move = self.game.borrow().selected_move();
+        match (input, selected_move) {
+            (UserInput::Confirm, Some(mv)) => {
+                let result = self.file.play(&mut self.game.borrow_mut(), mv);
+                self.played = result.is_ok();
+                *self.last_error.borrow_mut() = result.err();
+                return self.played;
+            }
+            (input, _) => {
+                self.game.borrow_mut().handle(input);
+            }
+        }
+        false
+    }
+
+    /// True once the move of the player is in the file
+    pub fn has_played(&self) -> bool {
+        self.played
+    }
+}
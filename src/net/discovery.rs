@@ -0,0 +1,234 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::mpsc::Sender,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{model::Direction, visor::UserInput};
+
+use super::{
+    client,
+    protocol::{Announcement, TableId, TableInfo, PROTOCOL_VERSION},
+};
+
+pub const DISCOVERY_PORT: u16 = 4243;
+pub const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+/// Servers that have not been heard from for this long are gone
+pub const ANNOUNCEMENT_LIFETIME: Duration = Duration::from_secs(5);
+
+// Big enough for a lobby with a few dozen tables
+const MAX_ANNOUNCEMENT_SIZE: usize = 65_507;
+
+/// Where announcements go by default: every machine on the local network
+pub fn broadcast_address() -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::BROADCAST, DISCOVERY_PORT))
+}
+
+/// Tells the local network about the tables of a server, every [`ANNOUNCE_INTERVAL`]
+pub struct Announcer {
+    socket: UdpSocket,
+    name: String,
+    server: SocketAddr,
+    target: SocketAddr,
+    interval: Duration,
+}
+
+impl Announcer {
+    pub fn new(name: &str, server: SocketAddr, target: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        Ok(Self {
+            socket,
+            name: name.to_owned(),
+            server,
+            target,
+            interval: ANNOUNCE_INTERVAL,
+        })
+    }
+
+    pub fn with_interval(self, interval: Duration) -> Self {
+        Self { interval, ..self }
+    }
+
+    /// Asks the server for its tables, and sends them out once
+    pub fn announce(&self) -> io::Result<()> {
+        let announcement = Announcement {
+            version: PROTOCOL_VERSION,
+            name: self.name.clone(),
+            port: self.server.port(),
+            tables: client::list_tables(self.server)?,
+        };
+        let bytes = serde_json::to_vec(&announcement)?;
+        self.socket.send_to(&bytes, self.target)?;
+        Ok(())
+    }
+
+    pub fn run(self) {
+        loop {
+            // The server may be busy, or the network down for a moment, just try again later
+            let _ = self.announce();
+            thread::sleep(self.interval);
+        }
+    }
+}
+
+/// A server that announced itself, and where to reach it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discovered {
+    pub address: SocketAddr,
+    pub announcement: Announcement,
+}
+
+/// Listens for the announcements of servers on the local network
+pub struct Discovery {
+    socket: UdpSocket,
+}
+
+impl Discovery {
+    pub fn bind<A: std::net::ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        Ok(Self { socket })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Waits for the next announcement, `None` if nothing arrived in time
+    pub fn receive(&self, timeout: Duration) -> io::Result<Option<Discovered>> {
+        self.socket.set_read_timeout(Some(timeout))?;
+        let mut buf = vec![0; MAX_ANNOUNCEMENT_SIZE];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(e) => return Err(e),
+            };
+            // Anybody can send anything to this port, skip what is not an announcement
+            let Ok(announcement) = serde_json::from_slice::<Announcement>(&buf[..len]) else {
+                continue;
+            };
+            let address = SocketAddr::new(from.ip(), announcement.port);
+            return Ok(Some(Discovered {
+                address,
+                announcement,
+            }));
+        }
+    }
+
+    /// Sends every announcement to `events`, until nobody listens to them anymore
+    pub fn listen<E: From<Discovered> + Send + 'static>(self, events: Sender<E>) {
+        thread::spawn(move || loop {
+            match self.receive(ANNOUNCE_INTERVAL) {
+                Ok(Some(discovered)) => {
+                    if events.send(discovered.into()).is_err() {
+                        return;
+                    }
+                }
+                Ok(None) => {}
+                Err(_) => return,
+            }
+        });
+    }
+}
+
+/// A table on one of the discovered servers
+pub struct FoundTable<'a> {
+    pub server: &'a str,
+    pub address: SocketAddr,
+    pub version: u32,
+    pub table: &'a TableInfo,
+}
+
+impl FoundTable<'_> {
+    pub fn can_join(&self) -> bool {
+        self.version == PROTOCOL_VERSION && self.table.has_free_seat()
+    }
+}
+
+/// What the player picked on the "find games" screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FindGamesChoice {
+    Join(SocketAddr, TableId),
+    Quit,
+}
+
+/// The state of the "find games" screen: every table announced on the local network
+#[derive(Default)]
+pub struct FindGames {
+    servers: BTreeMap<SocketAddr, (Announcement, Instant)>,
+    pub selected: usize,
+    pub last_error: Option<String>,
+}
+
+impl FindGames {
+    pub fn update(&mut self, discovered: Discovered, now: Instant) {
+        self.servers
+            .insert(discovered.address, (discovered.announcement, now));
+        self.clamp_selection();
+    }
+
+    /// Forgets the servers that stopped announcing themselves
+    pub fn forget_stale(&mut self, now: Instant) {
+        self.servers
+            .retain(|_, (_, seen)| now.duration_since(*seen) < ANNOUNCEMENT_LIFETIME);
+        self.clamp_selection();
+    }
+
+    fn clamp_selection(&mut self) {
+        self.selected = self.selected.min(self.tables().len().saturating_sub(1));
+    }
+
+    pub fn tables(&self) -> Vec<FoundTable<'_>> {
+        self.servers
+            .iter()
+            .flat_map(|(&address, (announcement, _))| {
+                announcement.tables.iter().map(move |table| FoundTable {
+                    server: &announcement.name,
+                    address,
+                    version: announcement.version,
+                    table,
+                })
+            })
+            .collect()
+    }
+
+    pub fn handle_input(&mut self, input: UserInput) -> Option<FindGamesChoice> {
+        let count = self.tables().len();
+        match input {
+            UserInput::Exit => return Some(FindGamesChoice::Quit),
+            UserInput::Direction(Direction::Next) if count > 0 => {
+                self.selected = (self.selected + 1) % count;
+            }
+            UserInput::Direction(Direction::Prev) if count > 0 => {
+                self.selected = (self.selected + count - 1) % count;
+            }
+            UserInput::Confirm => {
+                let tables = self.tables();
+                let found = tables.get(self.selected)?;
+                if found.version != PROTOCOL_VERSION {
+                    self.last_error = Some(format!(
+                        "{} speaks version {} of the protocol, we speak {}",
+                        found.server, found.version, PROTOCOL_VERSION
+                    ));
+                } else if !found.table.has_free_seat() {
+                    self.last_error = Some(String::from("There is no free seat at this table"));
+                } else {
+                    return Some(FindGamesChoice::Join(found.address, found.table.id));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
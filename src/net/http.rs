@@ -0,0 +1,442 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{
+    bag::Bag, player::Player, rules::Rules, snapshot::GameSnapshot, zobrist::ZobristHash, Game,
+    Move, TurnOutcome,
+};
+
+use super::protocol::{check_name, GameEvent};
+
+/// Only this machine can reach the API, unless asked otherwise
+pub const DEFAULT_HTTP_ADDRESS: &str = "127.0.0.1:8080";
+
+// Nobody needs a bigger request to create a game or post a move
+const MAX_BODY_SIZE: usize = 64 * 1024;
+// Nor longer lines or more headers than this
+const MAX_LINE_LENGTH: usize = 8 * 1024;
+const MAX_HEADERS: usize = 64;
+
+pub type GameId = usize;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateGame {
+    pub players: Vec<String>,
+    #[serde(default)]
+    pub rules: Rules,
+    /// The seed of the bag, random if left out
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameCreated {
+    pub id: GameId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostMove {
+    pub seat: usize,
+    #[serde(rename = "move")]
+    pub mv: Move,
+}
+
+/// The full state of a game: the factories, the common area and the building area (pattern
+/// lines, wall and floor line) of every player
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameState {
+    pub id: GameId,
+    pub hash: ZobristHash,
+    pub scores: Vec<u8>,
+    pub game: GameSnapshot,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiError {
+    pub error: String,
+}
+
+/// A game with any number of players, so they can all be kept in one list
+trait HostedGame: Send {
+    fn state(&self, id: GameId) -> GameState;
+    fn legal_moves(&self) -> Vec<Move>;
+    fn play(&mut self, seat: usize, mv: Move) -> Result<TurnOutcome, String>;
+    fn scores(&self) -> Vec<u8>;
+    fn is_over(&self) -> bool;
+}
+
+impl<const N: usize> HostedGame for Game<N> {
+    fn state(&self, id: GameId) -> GameState {
+        GameState {
+            id,
+            hash: self.zobrist_hash(),
+            scores: self.scores(),
+            game: self.snapshot(),
+        }
+    }
+
+    fn legal_moves(&self) -> Vec<Move> {
+        Game::legal_moves(self)
+    }
+
+    fn play(&mut self, seat: usize, mv: Move) -> Result<TurnOutcome, String> {
+        if seat != self.get_current_player_id() {
+            return Err(format!("It is not the turn of seat {}", seat));
+        }
+        self.apply_move(seat, mv)
+    }
+
+    fn scores(&self) -> Vec<u8> {
+        self.get_players().iter().map(Player::get_score).collect()
+    }
+
+    fn is_over(&self) -> bool {
+        Game::is_over(self)
+    }
+}
+
+fn host<const N: usize>(players: Vec<Player>, bag: Bag, rules: Rules) -> Box<dyn HostedGame> {
+    let players: [Player; N] = match players.try_into() {
+        Ok(players) => players,
+        Err(_) => unreachable!("The number of players is checked first"),
+    };
+    Box::new(Game::with_rules(players, bag, rules))
+}
+
+fn new_game(request: CreateGame) -> Result<Box<dyn HostedGame>, String> {
+    request.rules.check(request.players.len())?;
+    let bag = Bag::seeded(request.seed.unwrap_or_else(rand::random));
+    let players = request
+        .players
+        .iter()
+        .map(|name| check_name(name).map(Player::default_with_name))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(match players.len() {
+        2 => host::<2>(players, bag, request.rules),
+        3 => host::<3>(players, bag, request.rules),
+        4 => host::<4>(players, bag, request.rules),
+        n => return Err(format!("A game is for 2 to 4 players, not {}", n)),
+    })
+}
+
+type SubscriberId = usize;
+
+struct Hosted {
+    game: Box<dyn HostedGame>,
+    /// Everybody following the events of the game
+    subscribers: BTreeMap<SubscriberId, Sender<GameEvent>>,
+    next_subscriber: SubscriberId,
+}
+
+impl Hosted {
+    fn publish(&mut self, event: GameEvent) {
+        self.subscribers
+            .retain(|_, subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Returns `None` once the game is over, there is nothing left to follow
+    fn subscribe(&mut self, subscriber: Sender<GameEvent>) -> Option<SubscriberId> {
+        if self.game.is_over() {
+            return None;
+        }
+        let id = self.next_subscriber;
+        self.next_subscriber += 1;
+        self.subscribers.insert(id, subscriber);
+        Some(id)
+    }
+}
+
+#[derive(Default)]
+struct Games {
+    games: BTreeMap<GameId, Hosted>,
+    next_id: GameId,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+fn invalid_request(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Like `read_line`, without reading more than a line can hold
+fn read_limited_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<usize> {
+    let limit = MAX_LINE_LENGTH as u64 + 1;
+    let read = reader.by_ref().take(limit).read_line(line)?;
+    if read > MAX_LINE_LENGTH {
+        return Err(invalid_request("The request has a line that is too long"));
+    }
+    Ok(read)
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if read_limited_line(reader, &mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(invalid_request("Invalid request line"));
+    };
+    let path = target.split('?').next().unwrap_or_default().to_owned();
+    let method = method.to_owned();
+
+    let mut length = 0;
+    let mut headers = 0;
+    loop {
+        let mut header = String::new();
+        read_limited_line(reader, &mut header)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return Err(invalid_request("The request has too many headers"));
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid_request("Invalid Content-Length"))?;
+            }
+        }
+    }
+    if length > MAX_BODY_SIZE {
+        return Err(invalid_request("The request is too big"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(Request { method, path, body }))
+}
+
+struct Response {
+    status: u16,
+    body: String,
+}
+
+impl Response {
+    fn json<T: Serialize>(status: u16, body: &T) -> Self {
+        Self {
+            status,
+            body: serde_json::to_string(body).expect("Responses can always be serialized"),
+        }
+    }
+
+    fn error(status: u16, error: String) -> Self {
+        Self::json(status, &ApiError { error })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            self.body.len(),
+            self.body
+        )?;
+        writer.flush()
+    }
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(request: &'a Request) -> Result<T, Response> {
+    serde_json::from_slice(&request.body)
+        .map_err(|e| Response::error(400, format!("Invalid request: {}", e)))
+}
+
+/// Serves games as JSON over HTTP, for dashboards and scripts:
+///
+/// * `POST /games` creates a game,
+/// * `GET /games/{id}` is the full state of a game,
+/// * `GET /games/{id}/moves` lists the legal moves, and `POST /games/{id}/moves` plays one,
+/// * `GET /games/{id}/events` streams what happens in the game as server-sent events.
+pub struct HttpApi {
+    listener: TcpListener,
+    games: Arc<Mutex<Games>>,
+}
+
+impl HttpApi {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        Ok(Self {
+            listener,
+            games: Arc::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn run(self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let games = self.games.clone();
+            thread::spawn(move || {
+                let _ = handle_connection(stream, &games);
+            });
+        }
+        Ok(())
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, games: &Mutex<Games>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = match read_request(&mut reader) {
+        Ok(Some(request)) => request,
+        Ok(None) => return Ok(()),
+        Err(e) => return Response::error(400, e.to_string()).write(&mut stream),
+    };
+    let segments: Vec<_> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+    let response = match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["games"]) => create(games, &request),
+        (_, ["games"]) => method_not_allowed(),
+        (method, ["games", id, rest @ ..]) => match id.parse::<GameId>() {
+            Ok(id) => match (method, rest) {
+                ("GET", []) => with_game(games, id, |hosted| {
+                    Response::json(200, &hosted.game.state(id))
+                }),
+                ("GET", ["moves"]) => with_game(games, id, |hosted| {
+                    Response::json(200, &hosted.game.legal_moves())
+                }),
+                ("POST", ["moves"]) => match parse_body::<PostMove>(&request) {
+                    Ok(post) => with_game(games, id, |hosted| play(hosted, id, post)),
+                    Err(response) => response,
+                },
+                ("GET", ["events"]) => return stream_events(stream, games, id),
+                (_, [] | ["moves"] | ["events"]) => method_not_allowed(),
+                _ => not_found(),
+            },
+            Err(_) => not_found(),
+        },
+        _ => not_found(),
+    };
+    response.write(&mut stream)
+}
+
+fn not_found() -> Response {
+    Response::error(404, String::from("Not found"))
+}
+
+fn method_not_allowed() -> Response {
+    Response::error(405, String::from("Method not allowed"))
+}
+
+fn with_game(
+    games: &Mutex<Games>,
+    id: GameId,
+    action: impl FnOnce(&mut Hosted) -> Response,
+) -> Response {
+    let mut games = games.lock().expect("A request panicked");
+    match games.games.get_mut(&id) {
+        Some(hosted) => action(hosted),
+        None => Response::error(404, format!("There is no game {}", id)),
+    }
+}
+
+fn create(games: &Mutex<Games>, request: &Request) -> Response {
+    let create = match parse_body::<CreateGame>(request) {
+        Ok(create) => create,
+        Err(response) => return response,
+    };
+    match new_game(create) {
+        Ok(game) => {
+            let mut games = games.lock().expect("A request panicked");
+            let id = games.next_id;
+            games.next_id += 1;
+            let hosted = Hosted {
+                game,
+                subscribers: BTreeMap::new(),
+                next_subscriber: 0,
+            };
+            games.games.insert(id, hosted);
+            Response::json(201, &GameCreated { id })
+        }
+        Err(error) => Response::error(400, error),
+    }
+}
+
+fn play(hosted: &mut Hosted, id: GameId, post: PostMove) -> Response {
+    let outcome = match hosted.game.play(post.seat, post.mv) {
+        Ok(outcome) => outcome,
+        Err(error) => return Response::error(400, error),
+    };
+    hosted.publish(GameEvent::MovePlayed {
+        seat: post.seat,
+        mv: post.mv,
+    });
+    match outcome {
+        TurnOutcome::NextPlayer => {}
+        TurnOutcome::RoundOver => hosted.publish(GameEvent::RoundOver),
+        TurnOutcome::GameOver => {
+            hosted.publish(GameEvent::RoundOver);
+            let scores = hosted.game.scores();
+            hosted.publish(GameEvent::GameOver { scores });
+            // Nothing else is going to happen, which ends the streams
+            hosted.subscribers.clear();
+        }
+    }
+    Response::json(200, &hosted.game.state(id))
+}
+
+/// Keeps the connection open, and sends every event of the game until the client hangs up or the
+/// game is over
+fn stream_events(mut stream: TcpStream, games: &Mutex<Games>, id: GameId) -> io::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let subscribed = games
+        .lock()
+        .expect("A request panicked")
+        .games
+        .get_mut(&id)
+        .map(|hosted| hosted.subscribe(sender));
+    let Some(subscriber) = subscribed else {
+        return Response::error(404, format!("There is no game {}", id)).write(&mut stream);
+    };
+
+    let result = send_events(&mut stream, receiver);
+    if let (Err(_), Some(subscriber)) = (&result, subscriber) {
+        // The client is gone, stop publishing to it
+        let mut games = games.lock().expect("A request panicked");
+        if let Some(hosted) = games.games.get_mut(&id) {
+            hosted.subscribers.remove(&subscriber);
+        }
+    }
+    result
+}
+
+fn send_events(stream: &mut TcpStream, events: Receiver<GameEvent>) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n"
+    )?;
+    stream.flush()?;
+    for event in events {
+        let data = serde_json::to_string(&event)?;
+        write!(stream, "data: {}\n\n", data)?;
+        stream.flush()?;
+    }
+    Ok(())
+}
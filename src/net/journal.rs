@@ -0,0 +1,242 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::model::rules::Rules;
+
+use super::protocol::{ArchivedTable, ChatMessage, RecordedMove, TableId};
+
+const PREFIX: &str = "table-";
+const EXTENSION: &str = "jsonl";
+const ARCHIVE_PREFIX: &str = "archived-";
+const ARCHIVE_EXTENSION: &str = "json";
+
+/// Everything needed to bring a table back after a restart, in the order it happened
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Created {
+        players: usize,
+        rules: Rules,
+        seed: u64,
+    },
+    Started {
+        seats: Vec<JournalSeat>,
+    },
+    Move(RecordedMove),
+    Chat(ChatMessage),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalSeat {
+    pub name: String,
+    /// Lets the player take the seat back after the restart
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalLine {
+    /// Catches lines that were damaged on disk, but still happen to be valid JSON
+    sum: String,
+    entry: JournalEntry,
+}
+
+fn checksum(entry: &JournalEntry) -> String {
+    let json = serde_json::to_string(entry).expect("Entries can always be serialized");
+    let digest = Sha256::digest(json.as_bytes());
+    digest[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The entries of a journal that can be trusted
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalContents {
+    pub entries: Vec<JournalEntry>,
+    /// Why the last entry was dropped, if it was cut off while it was written
+    pub cut_off: Option<String>,
+}
+
+/// Reads a whole journal, and refuses it if anything is missing or damaged. Only a last line that
+/// was cut off, by a crash in the middle of writing it, is dropped and the rest kept.
+pub fn read_entries(contents: &str) -> Result<JournalContents, String> {
+    if contents.is_empty() {
+        return Err(String::from("The journal is empty"));
+    }
+    let mut entries = vec![];
+    let mut cut_off = None;
+    for (i, line) in contents.split_inclusive('\n').enumerate() {
+        let number = i + 1;
+        let Some(line) = line.strip_suffix('\n') else {
+            if entries.is_empty() {
+                return Err(format!("The journal is truncated at line {}", number));
+            }
+            cut_off = Some(format!(
+                "The last entry of the journal, on line {}, was cut off",
+                number
+            ));
+            break;
+        };
+        let parsed: JournalLine = serde_json::from_str(line)
+            .map_err(|e| format!("Line {} of the journal is corrupt: {}", number, e))?;
+        if parsed.sum != checksum(&parsed.entry) {
+            return Err(format!(
+                "Line {} of the journal does not match its checksum",
+                number
+            ));
+        }
+        entries.push(parsed.entry);
+    }
+    if !matches!(entries.first(), Some(JournalEntry::Created { .. })) {
+        return Err(String::from("The journal does not start with the table"));
+    }
+    Ok(JournalContents { entries, cut_off })
+}
+
+/// The entries of a table, or why they cannot be trusted
+pub type LoadedJournal = Result<JournalContents, String>;
+
+/// A directory with one append-only file per table, and one file per finished table
+pub struct Journal {
+    dir: PathBuf,
+}
+
+impl Journal {
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_owned(),
+        })
+    }
+
+    pub fn path(&self, table: TableId) -> PathBuf {
+        self.dir.join(format!("{}{}.{}", PREFIX, table, EXTENSION))
+    }
+
+    /// Starts the journal of a new table, `created` is its first entry
+    pub fn create(&self, table: TableId, created: &JournalEntry) -> io::Result<TableJournal> {
+        let file = File::create(self.path(table))?;
+        let mut journal = TableJournal { file };
+        journal.append(created)?;
+        Ok(journal)
+    }
+
+    /// Opens the journal of a table that was brought back, to go on where it left off
+    pub fn reopen(&self, table: TableId) -> io::Result<TableJournal> {
+        let file = OpenOptions::new().append(true).open(self.path(table))?;
+        Ok(TableJournal { file })
+    }
+
+    /// Once a table is over, there is nothing left to bring back
+    pub fn remove(&self, table: TableId) -> io::Result<()> {
+        fs::remove_file(self.path(table))
+    }
+
+    pub fn archive_path(&self, table: TableId) -> PathBuf {
+        self.dir
+            .join(format!("{}{}.{}", ARCHIVE_PREFIX, table, ARCHIVE_EXTENSION))
+    }
+
+    /// Keeps a finished table for good. The file is written aside and then moved in place, so it
+    /// is never there only in part.
+    pub fn archive(&self, table: &ArchivedTable) -> io::Result<()> {
+        let path = self.archive_path(table.id);
+        let written = path.with_extension("tmp");
+        let mut file = File::create(&written)?;
+        file.write_all(&serde_json::to_vec(table)?)?;
+        file.sync_data()?;
+        fs::rename(written, path)
+    }
+
+    /// Reads the journal of every table, by table id. A damaged journal only loses its own table.
+    /// A last line that was cut off is removed from the file, so the next entry starts a line of
+    /// its own.
+    pub fn load(&self) -> io::Result<Vec<(TableId, LoadedJournal)>> {
+        let tables = self.files(PREFIX, EXTENSION)?;
+        Ok(tables
+            .into_iter()
+            .map(|(table, path)| {
+                let entries = match fs::read_to_string(&path) {
+                    Ok(contents) => read_entries(&contents).and_then(|read| {
+                        if read.cut_off.is_some() {
+                            let end = contents.rfind('\n').map_or(0, |i| i + 1);
+                            drop_cut_off_line(&path, end)
+                                .map_err(|e| format!("The journal cannot be repaired: {}", e))?;
+                        }
+                        Ok(read)
+                    }),
+                    Err(e) => Err(format!("The journal cannot be read: {}", e)),
+                };
+                (table, entries)
+            })
+            .collect())
+    }
+
+    /// Reads every finished table, by table id, or why it cannot be read
+    pub fn load_archive(&self) -> io::Result<Vec<(TableId, Result<ArchivedTable, String>)>> {
+        let tables = self.files(ARCHIVE_PREFIX, ARCHIVE_EXTENSION)?;
+        Ok(tables
+            .into_iter()
+            .map(|(table, path)| {
+                let archived = fs::read_to_string(&path)
+                    .map_err(|e| format!("The archived table cannot be read: {}", e))
+                    .and_then(|contents| {
+                        serde_json::from_str(&contents)
+                            .map_err(|e| format!("The archived table is corrupt: {}", e))
+                    });
+                (table, archived)
+            })
+            .collect())
+    }
+
+    // The files named after a table, by table id
+    fn files(&self, prefix: &str, extension: &str) -> io::Result<Vec<(TableId, PathBuf)>> {
+        let mut tables = vec![];
+        for file in fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            let table = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.strip_prefix(prefix))
+                .and_then(|id| id.parse().ok());
+            let Some(table) = table else { continue };
+            if path.extension().and_then(|e| e.to_str()) != Some(extension) {
+                continue;
+            }
+            tables.push((table, path));
+        }
+        tables.sort_by_key(|(table, _)| *table);
+        Ok(tables)
+    }
+}
+
+fn drop_cut_off_line(path: &Path, end: usize) -> io::Result<()> {
+    let file = OpenOptions::new().write(true).open(path)?;
+    file.set_len(end as u64)?;
+    file.sync_data()
+}
+
+/// The journal of a single table, every entry is on disk before `append` returns
+pub struct TableJournal {
+    file: File,
+}
+
+impl TableJournal {
+    pub fn append(&mut self, entry: &JournalEntry) -> io::Result<()> {
+        let line = JournalLine {
+            sum: checksum(entry),
+            entry: entry.clone(),
+        };
+        let mut bytes = serde_json::to_vec(&line)?;
+        bytes.push(b'\n');
+        // A single write, so a crash can only cut off the last line
+        self.file.write_all(&bytes)?;
+        self.file.sync_data()
+    }
+}
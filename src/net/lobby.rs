@@ -0,0 +1,299 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    time::{Duration, Instant},
+};
+
+use crate::model::{rules::Rules, Move};
+
+use super::{
+    journal::{Journal, JournalEntry},
+    protocol::{check_name, ArchivedTable, ServerMessage, TableId},
+    server::{ConnectionId, Connections},
+    table::{new_table, restore_table, AnyTable},
+};
+
+/// Every table on the server, and who sits where. A connection is at one table at most, and
+/// everything it sends after joining goes to that table.
+pub(crate) struct Lobby {
+    tables: BTreeMap<TableId, Box<dyn AnyTable>>,
+    members: HashMap<ConnectionId, TableId>,
+    archive: Vec<ArchivedTable>,
+    next_id: TableId,
+    grace_period: Duration,
+    journal: Option<Journal>,
+}
+
+fn send_error(connections: &mut Connections, id: ConnectionId, message: String) {
+    connections.send(id, &ServerMessage::Error { message });
+}
+
+fn remove_journal(journal: &Journal, table_id: TableId) {
+    if let Err(e) = journal.remove(table_id) {
+        eprintln!("Could not remove the journal of table {}: {}", table_id, e);
+    }
+}
+
+impl Lobby {
+    pub(crate) fn new(grace_period: Duration) -> Self {
+        Self {
+            tables: BTreeMap::new(),
+            members: HashMap::new(),
+            archive: vec![],
+            next_id: 0,
+            grace_period,
+            journal: None,
+        }
+    }
+
+    /// Brings back the archive and every table in the journal, and keeps writing to it from now
+    /// on. Returns what went wrong with the tables, the ones that could not be brought back and
+    /// the ones that lost their last entry.
+    pub(crate) fn restore(&mut self, journal: Journal) -> io::Result<Vec<(TableId, String)>> {
+        let mut problems = vec![];
+        for (table_id, archived) in journal.load_archive()? {
+            self.next_id = self.next_id.max(table_id + 1);
+            match archived {
+                Ok(archived) => self.archive.push(archived),
+                Err(problem) => problems.push((table_id, problem)),
+            }
+        }
+        for (table_id, entries) in journal.load()? {
+            // Even a broken journal keeps its id, so that it is not overwritten
+            self.next_id = self.next_id.max(table_id + 1);
+            if self.archive.iter().any(|archived| archived.id == table_id) {
+                // The table was archived, but its journal was left behind
+                remove_journal(&journal, table_id);
+                continue;
+            }
+            let restored = entries.and_then(|contents| {
+                if let Some(cut_off) = contents.cut_off {
+                    problems.push((table_id, format!("{}, it is lost", cut_off)));
+                }
+                let mut table = restore_table(table_id, contents.entries, self.grace_period)?;
+                let file = journal.reopen(table_id).map_err(|e| e.to_string())?;
+                table.attach_journal(file);
+                Ok(table)
+            });
+            match restored {
+                Ok(table) => {
+                    self.tables.insert(table_id, table);
+                }
+                Err(problem) => problems.push((
+                    table_id,
+                    format!("{}, the table cannot be brought back", problem),
+                )),
+            }
+        }
+        self.journal = Some(journal);
+        Ok(problems)
+    }
+
+    pub(crate) fn list(&self, connections: &mut Connections, id: ConnectionId) {
+        let tables = self.tables.values().map(|table| table.info()).collect();
+        connections.send(id, &ServerMessage::Tables { tables });
+    }
+
+    pub(crate) fn list_archive(&self, connections: &mut Connections, id: ConnectionId) {
+        let tables = self.archive.clone();
+        connections.send(id, &ServerMessage::Archive { tables });
+    }
+
+    pub(crate) fn create(
+        &mut self,
+        connections: &mut Connections,
+        id: ConnectionId,
+        players: usize,
+        rules: Rules,
+        seed: Option<u64>,
+    ) {
+        let table_id = self.next_id;
+        let seed = seed.unwrap_or_else(rand::random);
+        let table =
+            new_table(table_id, players, rules, seed, self.grace_period).and_then(|mut table| {
+                if let Some(journal) = &self.journal {
+                    let created = JournalEntry::Created {
+                        players,
+                        rules,
+                        seed,
+                    };
+                    let file = journal
+                        .create(table_id, &created)
+                        .map_err(|e| format!("Could not save the table: {}", e))?;
+                    table.attach_journal(file);
+                }
+                Ok(table)
+            });
+        match table {
+            Ok(table) => {
+                self.next_id += 1;
+                self.tables.insert(table_id, table);
+                connections.send(id, &ServerMessage::TableCreated { table: table_id });
+            }
+            Err(message) => send_error(connections, id, message),
+        }
+    }
+
+    fn check_not_seated(&self, connections: &mut Connections, id: ConnectionId) -> bool {
+        if self.members.contains_key(&id) {
+            send_error(connections, id, String::from("You have already joined"));
+            return false;
+        }
+        true
+    }
+
+    pub(crate) fn join(
+        &mut self,
+        connections: &mut Connections,
+        id: ConnectionId,
+        name: String,
+        table: Option<TableId>,
+    ) {
+        if !self.check_not_seated(connections, id) {
+            return;
+        }
+        let name = match check_name(&name) {
+            Ok(name) => name,
+            Err(message) => return send_error(connections, id, message),
+        };
+        let table_id = match table {
+            Some(table_id) if self.tables.contains_key(&table_id) => table_id,
+            Some(table_id) => {
+                return send_error(connections, id, format!("There is no table {}", table_id))
+            }
+            None => match self.tables.iter().find(|(_, t)| t.info().has_free_seat()) {
+                Some((&table_id, _)) => table_id,
+                None => return send_error(connections, id, String::from("There is no open table")),
+            },
+        };
+        let table = self.tables.get_mut(&table_id).expect("Checked above");
+        match table.join(connections, id, name) {
+            Ok(()) => {
+                self.members.insert(id, table_id);
+            }
+            Err(message) => send_error(connections, id, message),
+        }
+    }
+
+    pub(crate) fn spectate(
+        &mut self,
+        connections: &mut Connections,
+        id: ConnectionId,
+        name: String,
+        table: Option<TableId>,
+    ) {
+        if !self.check_not_seated(connections, id) {
+            return;
+        }
+        let name = match check_name(&name) {
+            Ok(name) => name,
+            Err(message) => return send_error(connections, id, message),
+        };
+        let table_id = match table.or_else(|| self.tables.keys().next().copied()) {
+            Some(table_id) if self.tables.contains_key(&table_id) => table_id,
+            Some(table_id) => {
+                return send_error(connections, id, format!("There is no table {}", table_id))
+            }
+            None => return send_error(connections, id, String::from("There is no table to watch")),
+        };
+        let table = self.tables.get_mut(&table_id).expect("Checked above");
+        table.spectate(connections, id, name);
+        self.members.insert(id, table_id);
+    }
+
+    pub(crate) fn resume(&mut self, connections: &mut Connections, id: ConnectionId, token: &str) {
+        if !self.check_not_seated(connections, id) {
+            return;
+        }
+        match self.tables.iter_mut().find(|(_, t)| t.has_session(token)) {
+            Some((&table_id, table)) => {
+                table.resume(connections, id, token);
+                self.members.insert(id, table_id);
+            }
+            None => {
+                send_error(connections, id, String::from("Unknown session"));
+                connections.close(id);
+            }
+        }
+    }
+
+    fn table_of(
+        &mut self,
+        connections: &mut Connections,
+        id: ConnectionId,
+    ) -> Option<&mut Box<dyn AnyTable>> {
+        let table = self
+            .members
+            .get(&id)
+            .and_then(|table_id| self.tables.get_mut(table_id));
+        if table.is_none() {
+            send_error(connections, id, String::from("You have not joined a table"));
+        }
+        table
+    }
+
+    pub(crate) fn ready(&mut self, connections: &mut Connections, id: ConnectionId) {
+        if let Some(table) = self.table_of(connections, id) {
+            table.ready(connections, id);
+        }
+    }
+
+    pub(crate) fn play(&mut self, connections: &mut Connections, id: ConnectionId, mv: Move) {
+        if let Some(table) = self.table_of(connections, id) {
+            table.play(connections, id, mv);
+        }
+    }
+
+    pub(crate) fn chat(&mut self, connections: &mut Connections, id: ConnectionId, text: &str) {
+        if let Some(table) = self.table_of(connections, id) {
+            table.chat(connections, id, text);
+        }
+    }
+
+    pub(crate) fn leave(&mut self, connections: &mut Connections, id: ConnectionId) {
+        let table = self
+            .members
+            .remove(&id)
+            .and_then(|table_id| self.tables.get_mut(&table_id));
+        if let Some(table) = table {
+            table.leave(connections, id);
+        }
+    }
+
+    /// When the next player who is away, at any table, runs out of time
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.tables.values().filter_map(|t| t.next_deadline()).min()
+    }
+
+    pub(crate) fn expire(&mut self, connections: &mut Connections, now: Instant) {
+        for table in self.tables.values_mut() {
+            table.expire(connections, now);
+        }
+    }
+
+    /// Moves finished tables to the archive, and sends everybody at them home.
+    pub(crate) fn close_finished_tables(&mut self, connections: &mut Connections) {
+        let finished: Vec<_> = self
+            .tables
+            .iter()
+            .filter(|(_, t)| t.is_finished())
+            .map(|(&table_id, _)| table_id)
+            .collect();
+        for table_id in finished {
+            let table = self.tables.remove(&table_id).expect("Found above");
+            let archived = table.archive();
+            if let Some(journal) = &self.journal {
+                // Without the archive, the journal brings the table back to be archived again
+                match journal.archive(&archived) {
+                    Ok(()) => remove_journal(journal, table_id),
+                    Err(e) => eprintln!("Could not archive table {}: {}", table_id, e),
+                }
+            }
+            self.archive.push(archived);
+            self.members.retain(|_, t| *t != table_id);
+            for connection in table.members() {
+                connections.close(connection);
+            }
+        }
+    }
+}
@@ -0,0 +1,13 @@
+pub mod chat;
+pub mod client;
+pub mod correspondence;
+pub mod discovery;
+pub mod http;
+pub mod journal;
+mod lobby;
+pub mod peer;
+pub mod protocol;
+pub mod server;
+pub mod table;
+pub mod telnet;
+pub mod view;
@@ -0,0 +1,474 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    io::{self, BufReader},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    rc::Rc,
+    sync::mpsc::Sender,
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    model::{bag::Bag, player::Player, zobrist::ZobristHash, Game, Move},
+    visor::UserInput,
+};
+
+use super::{
+    protocol::{read_message, write_message, PROTOCOL_VERSION},
+    view::PeerGameView,
+};
+
+/// What peers send each other, one JSON message per line like the server protocol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PeerMessage {
+    Hello {
+        version: u32,
+        name: String,
+        players: usize,
+    },
+    /// The hash of a seed, so that nobody can pick their seed after seeing the others
+    Commit {
+        commitment: String,
+    },
+    Reveal {
+        seed: u64,
+        nonce: String,
+    },
+    Move(Move),
+    /// The state of the game after the move `turn`, as the sender sees it
+    StateHash {
+        turn: usize,
+        hash: ZobristHash,
+    },
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn commitment(seed: u64, nonce: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(seed.to_le_bytes())
+        .chain_update(nonce.as_bytes())
+        .finalize();
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The seed of the bag, which nobody can choose as long as one of the seeds is random
+pub fn combine_seeds(seeds: &[u64]) -> u64 {
+    let mut hasher = Sha256::new();
+    for seed in seeds {
+        hasher.update(seed.to_le_bytes());
+    }
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[..8].try_into().expect("A digest has 32 bytes"))
+}
+
+struct Link {
+    name: String,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Link {
+    fn receive(&mut self) -> io::Result<PeerMessage> {
+        read_message(&mut self.reader)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} hung up", self.name),
+            )
+        })
+    }
+}
+
+/// A connection to every other peer, each of them knows their seat
+pub struct Mesh {
+    seat: usize,
+    names: Vec<String>,
+    /// One per seat, `None` for our own
+    links: Vec<Option<Link>>,
+}
+
+impl Mesh {
+    /// Connects to every peer in `addresses`, and waits for the remaining ones to connect to
+    /// `listener`. Seats go by name, so everybody agrees on them without talking it over.
+    pub fn connect(
+        listener: &TcpListener,
+        name: &str,
+        players: usize,
+        addresses: &[SocketAddr],
+    ) -> io::Result<Self> {
+        let incoming = (players - 1).checked_sub(addresses.len()).ok_or_else(|| {
+            invalid_data(format!("A game for {} players has too many peers", players))
+        })?;
+        let mut streams = vec![];
+        for address in addresses {
+            streams.push(TcpStream::connect(address)?);
+        }
+        for _ in 0..incoming {
+            streams.push(listener.accept()?.0);
+        }
+
+        let hello = PeerMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: name.to_owned(),
+            players,
+        };
+        let mut links = vec![];
+        for mut writer in streams {
+            write_message(&mut writer, &hello)?;
+            let mut link = Link {
+                name: String::new(),
+                reader: BufReader::new(writer.try_clone()?),
+                writer,
+            };
+            match link.receive()? {
+                PeerMessage::Hello {
+                    version,
+                    name: peer,
+                    players: wanted,
+                } => {
+                    if version != PROTOCOL_VERSION {
+                        return Err(invalid_data(format!(
+                            "{} speaks version {} of the protocol, we speak {}",
+                            peer, version, PROTOCOL_VERSION
+                        )));
+                    }
+                    if wanted != players {
+                        return Err(invalid_data(format!(
+                            "{} wants a game for {} players, not {}",
+                            peer, wanted, players
+                        )));
+                    }
+                    link.name = peer;
+                }
+                other => return Err(invalid_data(format!("Expected a hello, got {:?}", other))),
+            }
+            links.push(link);
+        }
+
+        let mut names: Vec<_> = links.iter().map(|link| link.name.clone()).collect();
+        names.push(name.to_owned());
+        names.sort();
+        if names.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(invalid_data(String::from(
+                "Every peer needs a different name",
+            )));
+        }
+        let seat = names.iter().position(|n| n == name).expect("Added above");
+        let mut by_seat: Vec<Option<Link>> = names.iter().map(|_| None).collect();
+        for link in links {
+            let seat = names
+                .iter()
+                .position(|n| *n == link.name)
+                .expect("Added above");
+            by_seat[seat] = Some(link);
+        }
+        Ok(Self {
+            seat,
+            names,
+            links: by_seat,
+        })
+    }
+
+    pub fn seat(&self) -> usize {
+        self.seat
+    }
+
+    pub fn players(&self) -> usize {
+        self.names.len()
+    }
+
+    fn broadcast(&mut self, message: &PeerMessage) -> io::Result<()> {
+        for link in self.links.iter_mut().flatten() {
+            write_message(&mut link.writer, message)?;
+        }
+        Ok(())
+    }
+
+    /// Everybody commits to a seed, then reveals it, `seed` is our part. Returns the seed of the
+    /// bag, which every peer ends up with.
+    pub fn agree_on_seed(&mut self, seed: u64) -> io::Result<u64> {
+        let nonce = format!("{:032x}", rand::random::<u128>());
+        self.broadcast(&PeerMessage::Commit {
+            commitment: commitment(seed, &nonce),
+        })?;
+        let mut commitments = vec![String::new(); self.players()];
+        for (seat, link) in self.links.iter_mut().enumerate() {
+            let Some(link) = link else { continue };
+            match link.receive()? {
+                PeerMessage::Commit { commitment } => commitments[seat] = commitment,
+                other => {
+                    return Err(invalid_data(format!(
+                        "Expected a commitment from {}, got {:?}",
+                        link.name, other
+                    )))
+                }
+            }
+        }
+
+        // Only reveal once we have everybody's commitment
+        self.broadcast(&PeerMessage::Reveal { seed, nonce })?;
+        let mut seeds = vec![seed; self.players()];
+        for (seat, link) in self.links.iter_mut().enumerate() {
+            let Some(link) = link else { continue };
+            match link.receive()? {
+                PeerMessage::Reveal { seed, nonce }
+                    if commitment(seed, &nonce) == commitments[seat] =>
+                {
+                    seeds[seat] = seed
+                }
+                PeerMessage::Reveal { .. } => {
+                    return Err(invalid_data(format!(
+                        "{} revealed a seed that does not match their commitment",
+                        link.name
+                    )))
+                }
+                other => {
+                    return Err(invalid_data(format!(
+                        "Expected a seed from {}, got {:?}",
+                        link.name, other
+                    )))
+                }
+            }
+        }
+        Ok(combine_seeds(&seeds))
+    }
+}
+
+pub enum PeerEvent {
+    Message { seat: usize, message: PeerMessage },
+    Disconnected { seat: usize },
+}
+
+pub struct PeerStatus {
+    pub seat: usize,
+    pub seed: u64,
+    /// How many moves were played
+    pub turn: usize,
+    pub disconnected: Vec<usize>,
+    pub last_error: Option<String>,
+    /// Set when a peer cheats or we disagree about the game, there is no way to go on after that
+    pub fault: Option<String>,
+}
+
+/// A game without a server: every peer has its own copy, and checks every move against it
+pub struct PeerGame<const N: usize> {
+    pub game: Rc<RefCell<Game<N>>>,
+    pub status: Rc<RefCell<PeerStatus>>,
+    names: Vec<String>,
+    writers: Vec<Option<TcpStream>>,
+    /// The hashes everybody reported after each move, by seat
+    hashes: BTreeMap<usize, Vec<Option<ZobristHash>>>,
+}
+
+impl<const N: usize> PeerGame<N> {
+    /// Starts the game with the bag from [`Mesh::agree_on_seed`]. Everything the peers send from
+    /// then on arrives through `events`, and should be passed to [`PeerGame::handle_peer_event`].
+    pub fn new<E: From<PeerEvent> + Send + 'static>(
+        mesh: Mesh,
+        seed: u64,
+        events: Sender<E>,
+    ) -> io::Result<Self> {
+        if mesh.players() != N {
+            return Err(invalid_data(format!(
+                "The game is for {} players, not {}",
+                mesh.players(),
+                N
+            )));
+        }
+        let players =
+            std::array::from_fn(|seat| Player::default_with_name(mesh.names[seat].clone()));
+        let game = Game::for_players_with_bag(players, Bag::seeded(seed));
+
+        let mut writers = vec![];
+        for (seat, link) in mesh.links.into_iter().enumerate() {
+            let Some(mut link) = link else {
+                writers.push(None);
+                continue;
+            };
+            writers.push(Some(link.writer));
+            let events = events.clone();
+            thread::spawn(move || {
+                while let Ok(Some(message)) = read_message(&mut link.reader) {
+                    if events
+                        .send(PeerEvent::Message { seat, message }.into())
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+                let _ = events.send(PeerEvent::Disconnected { seat }.into());
+            });
+        }
+
+        let status = PeerStatus {
+            seat: mesh.seat,
+            seed,
+            turn: 0,
+            disconnected: vec![],
+            last_error: None,
+            fault: None,
+        };
+        Ok(Self {
+            game: Rc::new(RefCell::new(game)),
+            status: Rc::new(RefCell::new(status)),
+            names: mesh.names,
+            writers,
+            hashes: BTreeMap::new(),
+        })
+    }
+
+    pub fn view(&self) -> PeerGameView<N> {
+        PeerGameView::new(self.game.clone(), self.status.clone())
+    }
+
+    pub fn is_my_turn(&self) -> bool {
+        let status = self.status.borrow();
+        let game = self.game.borrow();
+        status.fault.is_none()
+            && status.disconnected.is_empty()
+            && !game.is_over()
+            && game.get_current_player_id() == status.seat
+    }
+
+    /// Returns true if the user wants to quit.
+    pub fn handle_input(&mut self, input: UserInput) -> bool {
+        if let UserInput::Exit = input {
+            return true;
+        }
+        if !self.is_my_turn() {
+            return false;
+        }
+        let selected_move = self.game.borrow().selected_move();
+        match (input, selected_move) {
+            (UserInput::Confirm, Some(mv)) => {
+                self.status.borrow_mut().last_error = self.play(mv).err();
+            }
+            (input, _) => {
+                self.game.borrow_mut().handle(input);
+            }
+        }
+        false
+    }
+
+    /// Plays our move, and sends it to everybody
+    pub fn play(&mut self, mv: Move) -> Result<(), String> {
+        if !self.is_my_turn() {
+            return Err(String::from("It is not your turn"));
+        }
+        let seat = self.status.borrow().seat;
+        self.game.borrow_mut().apply_move(seat, mv)?;
+        self.broadcast(&PeerMessage::Move(mv));
+        self.record_own_hash();
+        Ok(())
+    }
+
+    fn broadcast(&mut self, message: &PeerMessage) {
+        for (seat, writer) in self.writers.iter_mut().enumerate() {
+            let Some(writer) = writer else { continue };
+            if write_message(writer, message).is_err() {
+                let mut status = self.status.borrow_mut();
+                if !status.disconnected.contains(&seat) {
+                    status.disconnected.push(seat);
+                }
+            }
+        }
+    }
+
+    // After every move, tell everybody what we think the game looks like
+    fn record_own_hash(&mut self) {
+        let hash = self.game.borrow().zobrist_hash();
+        let (seat, turn) = {
+            let mut status = self.status.borrow_mut();
+            status.turn += 1;
+            (status.seat, status.turn)
+        };
+        self.broadcast(&PeerMessage::StateHash { turn, hash });
+        self.record_hash(turn, seat, hash);
+    }
+
+    fn record_hash(&mut self, turn: usize, seat: usize, hash: ZobristHash) {
+        let (own_seat, current) = {
+            let status = self.status.borrow();
+            (status.seat, status.turn)
+        };
+        // The hash of a peer can overtake the move it is about, which comes from another peer, or
+        // trail it by a move. Anything further away is made up.
+        if turn + 1 < current || turn > current + 1 {
+            self.status.borrow_mut().fault = Some(format!(
+                "{} sent the game after move {}, during move {}",
+                self.names[seat], turn, current
+            ));
+            return;
+        }
+        // Whoever did not report by now would be too late
+        self.hashes.retain(|&t, _| t + 1 >= current);
+        let hashes = self.hashes.entry(turn).or_insert_with(|| vec![None; N]);
+        hashes[seat] = Some(hash);
+        let Some(own) = hashes[own_seat] else {
+            // We have not seen that move yet
+            return;
+        };
+        let disagreeing = hashes.iter().position(|h| h.is_some_and(|h| h != own));
+        if let Some(other) = disagreeing {
+            self.status.borrow_mut().fault = Some(format!(
+                "{} disagrees about the game after move {}",
+                self.names[other], turn
+            ));
+        } else if hashes.iter().all(Option::is_some) {
+            self.hashes.remove(&turn);
+        }
+    }
+
+    pub fn handle_peer_event(&mut self, event: PeerEvent) {
+        if self.status.borrow().fault.is_some() {
+            return;
+        }
+        match event {
+            PeerEvent::Message { seat, message } => self.handle_message(seat, message),
+            PeerEvent::Disconnected { seat } => self.status.borrow_mut().disconnected.push(seat),
+        }
+    }
+
+    fn handle_message(&mut self, seat: usize, message: PeerMessage) {
+        let name = &self.names[seat];
+        match message {
+            PeerMessage::Move(mv) => {
+                let result = {
+                    let mut game = self.game.borrow_mut();
+                    if game.get_current_player_id() != seat {
+                        Err(format!("{} played out of turn", name))
+                    } else {
+                        game.apply_move(seat, mv)
+                            .map_err(|e| format!("{} played an illegal move: {}", name, e))
+                    }
+                };
+                match result {
+                    Ok(_) => self.record_own_hash(),
+                    Err(fault) => self.status.borrow_mut().fault = Some(fault),
+                }
+            }
+            PeerMessage::StateHash { turn, hash } => self.record_hash(turn, seat, hash),
+            other => {
+                self.status.borrow_mut().fault = Some(format!(
+                    "{} sent {:?} in the middle of the game",
+                    name, other
+                ))
+            }
+        }
+    }
+}
+
+impl<const N: usize> Drop for PeerGame<N> {
+    fn drop(&mut self) {
+        // The listening threads hold the other halves
+        for writer in self.writers.iter().flatten() {
+            let _ = writer.shutdown(Shutdown::Both);
+        }
+    }
+}
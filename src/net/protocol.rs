@@ -0,0 +1,232 @@
+use std::io::{self, BufRead, Read, Write};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::model::{rules::Rules, snapshot::GameSnapshot, zobrist::ZobristHash, Move};
+
+/// Bumped every time a message changes in a way older clients would not understand
+pub const PROTOCOL_VERSION: u32 = 6;
+
+pub type TableId = usize;
+
+/// In characters, not bytes. Longer names would not fit in the player area.
+pub const MAX_NAME_LENGTH: usize = 20;
+/// In bytes. A line longer than this is not a message, and the connection is dropped.
+pub const MAX_MESSAGE_LENGTH: usize = 1 << 20;
+
+/// Cleans up the name of a player or a spectator, or explains why nobody can be called that.
+pub fn check_name(name: &str) -> Result<String, String> {
+    // Everybody at the table sees the name, control characters would mess up their terminals
+    let name: String = name.trim().chars().filter(|c| !c.is_control()).collect();
+    if name.is_empty() {
+        return Err(String::from("Names cannot be empty"));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "Names can be at most {} characters",
+            MAX_NAME_LENGTH
+        ));
+    }
+    Ok(name)
+}
+
+/// Messages are sent as JSON, one message per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    ListTables,
+    /// The server picks a seed for the bag if there is none
+    CreateTable {
+        version: u32,
+        players: usize,
+        #[serde(default)]
+        rules: Rules,
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+    /// Takes a seat at the table, or at any table with a free seat
+    Join {
+        version: u32,
+        name: String,
+        #[serde(default)]
+        table: Option<TableId>,
+    },
+    /// Watch the game without taking a seat
+    Spectate {
+        version: u32,
+        name: String,
+        #[serde(default)]
+        table: Option<TableId>,
+    },
+    /// Take back a seat after losing the connection, with the token from the welcome message
+    Resume {
+        version: u32,
+        token: String,
+    },
+    /// The game starts once every seat is taken and every player is ready
+    Ready,
+    Move(Move),
+    /// Says something to everybody at the table
+    Chat {
+        text: String,
+    },
+    ListArchive,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Tables {
+        tables: Vec<TableInfo>,
+    },
+    TableCreated {
+        table: TableId,
+    },
+    Welcome {
+        version: u32,
+        table: TableId,
+        seat: usize,
+        players: usize,
+        /// Secret, lets the player resume the session on a new connection
+        token: String,
+    },
+    Spectating {
+        version: u32,
+        table: TableId,
+        players: usize,
+    },
+    State {
+        game: GameSnapshot,
+        hash: ZobristHash,
+    },
+    Event {
+        event: GameEvent,
+    },
+    Error {
+        message: String,
+    },
+    /// The move was not played, the player has to make another one
+    MoveRefused {
+        mv: Move,
+        message: String,
+    },
+    Archive {
+        tables: Vec<ArchivedTable>,
+    },
+}
+
+/// A table as it shows up in the lobby
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableInfo {
+    pub id: TableId,
+    pub players: usize,
+    /// The names of the players sitting at the table
+    pub seats: Vec<String>,
+    pub rules: Rules,
+    pub started: bool,
+    pub spectators: usize,
+}
+
+impl TableInfo {
+    pub fn has_free_seat(&self) -> bool {
+        !self.started && self.seats.len() < self.players
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecordedMove {
+    pub seat: usize,
+    #[serde(rename = "move")]
+    pub mv: Move,
+}
+
+/// A finished game. With the seed and the rules, the moves replay to the exact same game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedTable {
+    pub id: TableId,
+    pub players: Vec<String>,
+    pub rules: Rules,
+    pub seed: u64,
+    pub moves: Vec<RecordedMove>,
+    pub scores: Vec<u8>,
+    pub chat: Vec<ChatMessage>,
+    /// True if a player left before the end
+    pub abandoned: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub from: String,
+    pub text: String,
+}
+
+/// Sent over UDP to everybody on the local network, so that players can find the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Announcement {
+    pub version: u32,
+    pub name: String,
+    /// The port of the lobby, on the address the announcement came from
+    pub port: u16,
+    pub tables: Vec<TableInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GameEvent {
+    PlayerJoined {
+        seat: usize,
+        name: String,
+    },
+    PlayerReady {
+        seat: usize,
+    },
+    PlayerLeft {
+        seat: usize,
+    },
+    /// The seat is held for a while, the game is paused until the player is back
+    PlayerDisconnected {
+        seat: usize,
+    },
+    PlayerReconnected {
+        seat: usize,
+    },
+    SpectatorsChanged {
+        count: usize,
+    },
+    GameStarted,
+    MovePlayed {
+        seat: usize,
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    RoundOver,
+    GameOver {
+        scores: Vec<u8>,
+    },
+    Chat(ChatMessage),
+}
+
+pub fn write_message<W: Write, T: Serialize>(writer: &mut W, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
+
+/// Reads the next message, returns `None` if the other side has closed the connection. A line
+/// that is too long is an error, without reading the rest of it.
+pub fn read_message<R: BufRead, T: DeserializeOwned>(reader: &mut R) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    let limit = MAX_MESSAGE_LENGTH as u64 + 1;
+    if reader.by_ref().take(limit).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.len() > MAX_MESSAGE_LENGTH {
+        return Err(io::Error::other(format!(
+            "Messages can be at most {} bytes",
+            MAX_MESSAGE_LENGTH
+        )));
+    }
+    let message = serde_json::from_str(&line)?;
+    Ok(Some(message))
+}
@@ -0,0 +1,291 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufReader, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    journal::Journal,
+    lobby::Lobby,
+    protocol::{read_message, write_message, ClientMessage, ServerMessage, PROTOCOL_VERSION},
+};
+
+pub type ConnectionId = usize;
+
+/// How long a seat is held for a player who lost the connection
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+pub(crate) enum ServerEvent {
+    Connected(ConnectionId, TcpStream),
+    Message(ConnectionId, ClientMessage),
+    Invalid(ConnectionId, String),
+    Disconnected(ConnectionId),
+}
+
+/// How many messages may wait for a client before it is cut off as too slow
+const WRITE_QUEUE_LENGTH: usize = 256;
+/// How long a client may take to accept a message before it is cut off
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An open connection. A thread writes the queued messages, so a client that does not read
+/// cannot hold up the lobby.
+struct Connection {
+    queue: SyncSender<Vec<u8>>,
+    stream: TcpStream,
+}
+
+/// The writing halves of every open connection. Every connection also has a thread reading from
+/// it, which reports back through the event channel.
+#[derive(Default)]
+pub(crate) struct Connections {
+    connections: HashMap<ConnectionId, Connection>,
+}
+
+impl Connections {
+    fn add(&mut self, id: ConnectionId, stream: TcpStream) {
+        let Ok(writer) = stream.try_clone() else {
+            let _ = stream.shutdown(Shutdown::Both);
+            return;
+        };
+        let (queue, messages) = mpsc::sync_channel(WRITE_QUEUE_LENGTH);
+        spawn_writer(writer, messages);
+        self.connections.insert(id, Connection { queue, stream });
+    }
+
+    pub(crate) fn send(&mut self, id: ConnectionId, message: &ServerMessage) {
+        let Some(connection) = self.connections.get(&id) else {
+            return;
+        };
+        let mut line = Vec::new();
+        if write_message(&mut line, message).is_err() {
+            return;
+        }
+        match connection.queue.try_send(line) {
+            Ok(()) => {}
+            // The reader thread will notice, and report the disconnect
+            Err(TrySendError::Full(_)) => self.cut_off(id),
+            Err(TrySendError::Disconnected(_)) => self.close(id),
+        }
+    }
+
+    /// Closes the connection once the messages sent so far are written
+    pub(crate) fn close(&mut self, id: ConnectionId) {
+        // The writer shuts the stream down when the queue runs dry
+        self.connections.remove(&id);
+    }
+
+    // Closes the connection right away, dropping whatever is still queued
+    fn cut_off(&mut self, id: ConnectionId) {
+        if let Some(connection) = self.connections.remove(&id) {
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    pub(crate) fn close_all(&mut self) {
+        let ids: Vec<_> = self.connections.keys().copied().collect();
+        for id in ids {
+            self.close(id);
+        }
+    }
+}
+
+fn spawn_writer(mut stream: TcpStream, messages: Receiver<Vec<u8>>) {
+    thread::spawn(move || {
+        if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_ok() {
+            for line in messages {
+                if stream
+                    .write_all(&line)
+                    .and_then(|_| stream.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+        let _ = stream.shutdown(Shutdown::Both);
+    });
+}
+
+fn spawn_reader(id: ConnectionId, stream: TcpStream, events: Sender<ServerEvent>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let event = match read_message::<_, ClientMessage>(&mut reader) {
+                Ok(Some(message)) => ServerEvent::Message(id, message),
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    ServerEvent::Invalid(id, format!("Could not parse message: {}", e))
+                }
+                Ok(None) | Err(_) => {
+                    let _ = events.send(ServerEvent::Disconnected(id));
+                    return;
+                }
+            };
+            if events.send(event).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+fn spawn_acceptor(listener: TcpListener, events: Sender<ServerEvent>) {
+    thread::spawn(move || {
+        for (id, stream) in listener.incoming().enumerate() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let reader = match stream.try_clone() {
+                Ok(reader) => reader,
+                Err(_) => continue,
+            };
+            if events.send(ServerEvent::Connected(id, stream)).is_err() {
+                return;
+            }
+            spawn_reader(id, reader, events.clone());
+        }
+    });
+}
+
+// Sends an error and closes the connection if the client speaks a different protocol
+fn check_version(connections: &mut Connections, id: ConnectionId, version: u32) -> bool {
+    if version != PROTOCOL_VERSION {
+        let message = format!(
+            "Unsupported protocol version {}, the server speaks {}",
+            version, PROTOCOL_VERSION
+        );
+        connections.send(id, &ServerMessage::Error { message });
+        connections.close(id);
+        return false;
+    }
+    true
+}
+
+/// Hosts games over TCP. The server owns the games (and with that, the bags), and checks every
+/// move the clients send.
+pub struct Server {
+    listener: TcpListener,
+    grace_period: Duration,
+    journal: Option<PathBuf>,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        Ok(Self {
+            listener,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            journal: None,
+        })
+    }
+
+    /// How long the seat of a disconnected player is held
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Saves every table in `dir`, and brings them back when the server starts again
+    pub fn with_journal<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.journal = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub(crate) fn listen(self) -> Receiver<ServerEvent> {
+        let (sender, receiver) = mpsc::channel();
+        spawn_acceptor(self.listener, sender);
+        receiver
+    }
+
+    /// Runs the lobby, with as many tables as the clients create.
+    pub fn run(self) -> io::Result<()> {
+        let mut lobby = Lobby::new(self.grace_period);
+        if let Some(dir) = &self.journal {
+            for (table, problem) in lobby.restore(Journal::open(dir)?)? {
+                eprintln!("Table {}: {}", table, problem);
+            }
+        }
+        let events = self.listen();
+        let mut connections = Connections::default();
+        lobby.close_finished_tables(&mut connections);
+
+        loop {
+            let event = match lobby.next_deadline() {
+                Some(deadline) => {
+                    events.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => events.recv().map_err(RecvTimeoutError::from),
+            };
+            match event {
+                Ok(event) => handle_event(&mut lobby, &mut connections, event),
+                Err(RecvTimeoutError::Timeout) => lobby.expire(&mut connections, Instant::now()),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            lobby.close_finished_tables(&mut connections);
+        }
+        connections.close_all();
+        Ok(())
+    }
+}
+
+fn handle_event(lobby: &mut Lobby, connections: &mut Connections, event: ServerEvent) {
+    let (id, message) = match event {
+        ServerEvent::Connected(id, stream) => return connections.add(id, stream),
+        ServerEvent::Message(id, message) => (id, message),
+        ServerEvent::Invalid(id, message) => {
+            return connections.send(id, &ServerMessage::Error { message })
+        }
+        ServerEvent::Disconnected(id) => {
+            lobby.leave(connections, id);
+            return connections.close(id);
+        }
+    };
+
+    match message {
+        ClientMessage::ListTables => lobby.list(connections, id),
+        ClientMessage::ListArchive => lobby.list_archive(connections, id),
+        ClientMessage::CreateTable {
+            version,
+            players,
+            rules,
+            seed,
+        } => {
+            if check_version(connections, id, version) {
+                lobby.create(connections, id, players, rules, seed);
+            }
+        }
+        ClientMessage::Join {
+            version,
+            name,
+            table,
+        } => {
+            if check_version(connections, id, version) {
+                lobby.join(connections, id, name, table);
+            }
+        }
+        ClientMessage::Spectate {
+            version,
+            name,
+            table,
+        } => {
+            if check_version(connections, id, version) {
+                lobby.spectate(connections, id, name, table);
+            }
+        }
+        ClientMessage::Resume { version, token } => {
+            if check_version(connections, id, version) {
+                lobby.resume(connections, id, &token);
+            }
+        }
+        ClientMessage::Ready => lobby.ready(connections, id),
+        ClientMessage::Move(mv) => lobby.play(connections, id, mv),
+        ClientMessage::Chat { text } => lobby.chat(connections, id, &text),
+    }
+}
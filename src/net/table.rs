@@ -0,0 +1,565 @@
+use std::time::{Duration, Instant};
+
+use crate::model::{bag::Bag, player::Player, rules::Rules, Game, Move, TurnOutcome};
+
+use super::{
+    chat::{check_chat_text, RateLimiter},
+    journal::{JournalEntry, JournalSeat, TableJournal},
+    protocol::{
+        ArchivedTable, ChatMessage, GameEvent, RecordedMove, ServerMessage, TableId, TableInfo,
+        PROTOCOL_VERSION,
+    },
+    server::{ConnectionId, Connections},
+};
+
+struct Seat {
+    name: String,
+    token: String,
+    /// `None` while the player is away
+    connection: Option<ConnectionId>,
+    /// The seat is given up if the player is not back by then
+    deadline: Option<Instant>,
+    ready: bool,
+    chat: RateLimiter,
+}
+
+struct Spectator {
+    name: String,
+    connection: ConnectionId,
+    chat: RateLimiter,
+}
+
+fn new_token() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// A single game, and the players sitting at it. The table only starts the game once every seat
+/// is taken and every player is ready. Spectators get every message the players get, but they
+/// cannot make moves.
+///
+/// When a player loses the connection mid-game the seat is held for the grace period, and the
+/// game is paused until they resume their session.
+pub struct Table<const N: usize> {
+    id: TableId,
+    rules: Rules,
+    seed: u64,
+    /// One slot for every player, so that the seats do not move when somebody leaves
+    seats: Vec<Option<Seat>>,
+    spectators: Vec<Spectator>,
+    game: Option<Game<N>>,
+    moves: Vec<RecordedMove>,
+    chat: Vec<ChatMessage>,
+    abandoned: bool,
+    grace_period: Duration,
+    /// Where the table is saved, if the server keeps its tables across restarts
+    journal: Option<TableJournal>,
+}
+
+impl<const N: usize> Table<N> {
+    pub fn new(id: TableId, rules: Rules, seed: u64, grace_period: Duration) -> Self {
+        Self {
+            id,
+            rules,
+            seed,
+            seats: (0..N).map(|_| None).collect(),
+            spectators: vec![],
+            game: None,
+            moves: vec![],
+            chat: vec![],
+            abandoned: false,
+            grace_period,
+            journal: None,
+        }
+    }
+
+    fn record(&mut self, entry: JournalEntry) {
+        if let Some(journal) = &mut self.journal {
+            if let Err(e) = journal.append(&entry) {
+                eprintln!("Could not write the journal of table {}: {}", self.id, e);
+            }
+        }
+    }
+
+    fn taken_seats(&self) -> impl Iterator<Item = &Seat> {
+        self.seats.iter().flatten()
+    }
+
+    fn seat_mut(&mut self, seat: usize) -> &mut Seat {
+        self.seats[seat]
+            .as_mut()
+            .expect("Only taken seats are looked up")
+    }
+
+    /// The name of a player who is away, nobody can move until they are back
+    fn away_player(&self) -> Option<&str> {
+        self.taken_seats()
+            .find(|s| s.connection.is_none())
+            .map(|s| s.name.as_str())
+    }
+
+    fn find_seat(&self, connection: ConnectionId) -> Option<usize> {
+        self.seats
+            .iter()
+            .position(|s| s.as_ref().is_some_and(|s| s.connection == Some(connection)))
+    }
+
+    fn find_spectator(&self, connection: ConnectionId) -> Option<usize> {
+        self.spectators
+            .iter()
+            .position(|s| s.connection == connection)
+    }
+
+    fn broadcast(&self, connections: &mut Connections, message: &ServerMessage) {
+        for connection in self.members() {
+            connections.send(connection, message);
+        }
+    }
+
+    fn broadcast_event(&self, connections: &mut Connections, event: GameEvent) {
+        self.broadcast(connections, &ServerMessage::Event { event });
+    }
+
+    fn state(&self) -> Option<ServerMessage> {
+        self.game.as_ref().map(|game| ServerMessage::State {
+            game: game.snapshot(),
+            hash: game.zobrist_hash(),
+        })
+    }
+
+    fn broadcast_state(&self, connections: &mut Connections) {
+        if let Some(state) = self.state() {
+            self.broadcast(connections, &state);
+        }
+    }
+
+    fn broadcast_spectator_count(&self, connections: &mut Connections) {
+        let count = self.spectators.len();
+        self.broadcast_event(connections, GameEvent::SpectatorsChanged { count });
+    }
+
+    fn start(&mut self, connections: &mut Connections) {
+        let seats = self
+            .taken_seats()
+            .map(|seat| JournalSeat {
+                name: seat.name.clone(),
+                token: seat.token.clone(),
+            })
+            .collect();
+        self.record(JournalEntry::Started { seats });
+        self.deal();
+        self.broadcast_event(connections, GameEvent::GameStarted);
+        self.broadcast_state(connections);
+    }
+
+    fn deal(&mut self) {
+        let players: Vec<_> = self
+            .taken_seats()
+            .map(|seat| Player::default_with_name(seat.name.clone()))
+            .collect();
+        let players: [Player; N] = match players.try_into() {
+            Ok(players) => players,
+            Err(_) => unreachable!("The game is only started when all the seats are taken"),
+        };
+        let bag = Bag::seeded(self.seed);
+        self.game = Some(Game::with_rules(players, bag, self.rules));
+    }
+
+    fn seat_scores(&self) -> Vec<u8> {
+        self.game
+            .as_ref()
+            .map(|game| game.get_players().iter().map(Player::get_score).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// A table with any number of players, so the lobby can keep them all in one list
+pub(crate) trait AnyTable {
+    fn info(&self) -> TableInfo;
+    fn is_finished(&self) -> bool;
+    /// Everybody who gets the messages of this table
+    fn members(&self) -> Vec<ConnectionId>;
+    fn has_session(&self, token: &str) -> bool;
+    fn join(
+        &mut self,
+        connections: &mut Connections,
+        id: ConnectionId,
+        name: String,
+    ) -> Result<(), String>;
+    fn spectate(&mut self, connections: &mut Connections, id: ConnectionId, name: String);
+    fn resume(&mut self, connections: &mut Connections, id: ConnectionId, token: &str);
+    fn ready(&mut self, connections: &mut Connections, id: ConnectionId);
+    fn play(&mut self, connections: &mut Connections, id: ConnectionId, mv: Move);
+    fn chat(&mut self, connections: &mut Connections, id: ConnectionId, text: &str);
+    fn leave(&mut self, connections: &mut Connections, id: ConnectionId);
+    /// When the next player who is away runs out of time
+    fn next_deadline(&self) -> Option<Instant>;
+    fn expire(&mut self, connections: &mut Connections, now: Instant);
+    fn archive(&self) -> ArchivedTable;
+    fn attach_journal(&mut self, journal: TableJournal);
+    /// Plays back an entry of the journal, after a restart. Everybody is away until they resume
+    /// their session.
+    fn replay(&mut self, entry: JournalEntry, now: Instant) -> Result<(), String>;
+}
+
+pub(crate) fn new_table(
+    id: TableId,
+    players: usize,
+    rules: Rules,
+    seed: u64,
+    grace_period: Duration,
+) -> Result<Box<dyn AnyTable>, String> {
+    rules.check(players)?;
+    Ok(match players {
+        2 => Box::new(Table::<2>::new(id, rules, seed, grace_period)),
+        3 => Box::new(Table::<3>::new(id, rules, seed, grace_period)),
+        4 => Box::new(Table::<4>::new(id, rules, seed, grace_period)),
+        n => return Err(format!("A game is for 2 to 4 players, not {}", n)),
+    })
+}
+
+/// Brings back a table from its journal
+pub(crate) fn restore_table(
+    id: TableId,
+    entries: Vec<JournalEntry>,
+    grace_period: Duration,
+) -> Result<Box<dyn AnyTable>, String> {
+    let mut entries = entries.into_iter();
+    let mut table = match entries.next() {
+        Some(JournalEntry::Created {
+            players,
+            rules,
+            seed,
+        }) => new_table(id, players, rules, seed, grace_period)?,
+        _ => return Err(String::from("The journal does not start with the table")),
+    };
+    let now = Instant::now();
+    for entry in entries {
+        table.replay(entry, now)?;
+    }
+    Ok(table)
+}
+
+impl<const N: usize> AnyTable for Table<N> {
+    fn info(&self) -> TableInfo {
+        TableInfo {
+            id: self.id,
+            players: N,
+            seats: self.taken_seats().map(|s| s.name.clone()).collect(),
+            rules: self.rules,
+            started: self.game.is_some(),
+            spectators: self.spectators.len(),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.abandoned || self.game.as_ref().map(Game::is_over).unwrap_or(false)
+    }
+
+    fn members(&self) -> Vec<ConnectionId> {
+        let seats = self.taken_seats().filter_map(|s| s.connection);
+        let spectators = self.spectators.iter().map(|s| s.connection);
+        seats.chain(spectators).collect()
+    }
+
+    fn has_session(&self, token: &str) -> bool {
+        self.taken_seats().any(|s| s.token == token)
+    }
+
+    fn join(
+        &mut self,
+        connections: &mut Connections,
+        id: ConnectionId,
+        name: String,
+    ) -> Result<(), String> {
+        let free = self.seats.iter().position(Option::is_none);
+        let seat = match free {
+            Some(seat) if self.game.is_none() => seat,
+            _ => return Err(String::from("The table is full")),
+        };
+
+        let token = new_token();
+        self.seats[seat] = Some(Seat {
+            name: name.clone(),
+            token: token.clone(),
+            connection: Some(id),
+            deadline: None,
+            ready: false,
+            chat: RateLimiter::default(),
+        });
+        let welcome = ServerMessage::Welcome {
+            version: PROTOCOL_VERSION,
+            table: self.id,
+            seat,
+            players: N,
+            token,
+        };
+        connections.send(id, &welcome);
+        self.broadcast_event(connections, GameEvent::PlayerJoined { seat, name });
+        Ok(())
+    }
+
+    fn spectate(&mut self, connections: &mut Connections, id: ConnectionId, name: String) {
+        self.spectators.push(Spectator {
+            name,
+            connection: id,
+            chat: RateLimiter::default(),
+        });
+        let welcome = ServerMessage::Spectating {
+            version: PROTOCOL_VERSION,
+            table: self.id,
+            players: N,
+        };
+        connections.send(id, &welcome);
+        // Somebody joining mid-game has to catch up first
+        if let Some(state) = self.state() {
+            connections.send(id, &state);
+        }
+        self.broadcast_spectator_count(connections);
+    }
+
+    /// Puts the player back in their seat, and brings them up to date. If the old connection is
+    /// still open, it is closed: it is most likely dead without either side having noticed.
+    fn resume(&mut self, connections: &mut Connections, id: ConnectionId, token: &str) {
+        let found = self
+            .seats
+            .iter()
+            .position(|s| s.as_ref().is_some_and(|s| s.token == token));
+        let seat = match found {
+            Some(seat) => seat,
+            None => return,
+        };
+
+        let old = self.seat_mut(seat).connection.replace(id);
+        self.seat_mut(seat).deadline = None;
+        match old {
+            Some(old) => connections.close(old),
+            None => self.broadcast_event(connections, GameEvent::PlayerReconnected { seat }),
+        }
+        let welcome = ServerMessage::Welcome {
+            version: PROTOCOL_VERSION,
+            table: self.id,
+            seat,
+            players: N,
+            token: token.to_owned(),
+        };
+        connections.send(id, &welcome);
+        if let Some(state) = self.state() {
+            connections.send(id, &state);
+        }
+        for (seat, _) in self
+            .seats
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.as_ref().is_some_and(|s| s.connection.is_none()))
+        {
+            let event = GameEvent::PlayerDisconnected { seat };
+            connections.send(id, &ServerMessage::Event { event });
+        }
+        if !self.spectators.is_empty() {
+            let event = GameEvent::SpectatorsChanged {
+                count: self.spectators.len(),
+            };
+            connections.send(id, &ServerMessage::Event { event });
+        }
+    }
+
+    fn ready(&mut self, connections: &mut Connections, id: ConnectionId) {
+        let seat = match (self.find_seat(id), &self.game) {
+            (None, _) => Err(String::from("Spectators cannot play")),
+            (Some(_), Some(_)) => Err(String::from("The game has already started")),
+            (Some(seat), None) => Ok(seat),
+        };
+        let seat = match seat {
+            Ok(seat) => seat,
+            Err(message) => return connections.send(id, &ServerMessage::Error { message }),
+        };
+        if self.seat_mut(seat).ready {
+            return;
+        }
+
+        self.seat_mut(seat).ready = true;
+        self.broadcast_event(connections, GameEvent::PlayerReady { seat });
+        let all_ready = self
+            .seats
+            .iter()
+            .all(|s| s.as_ref().is_some_and(|s| s.ready));
+        if all_ready {
+            self.start(connections);
+        }
+    }
+
+    fn play(&mut self, connections: &mut Connections, id: ConnectionId, mv: Move) {
+        let is_spectator = self.find_spectator(id).is_some();
+        let paused = self
+            .away_player()
+            .map(|name| format!("The game is paused until {} reconnects", name));
+        let result = match (self.find_seat(id), self.game.as_mut()) {
+            (None, _) if is_spectator => Err(String::from("Spectators cannot make moves")),
+            (None, _) => Err(String::from("You have not joined the game")),
+            (Some(_), None) => Err(String::from("The game has not started yet")),
+            (Some(_), Some(_)) if paused.is_some() => Err(paused.unwrap_or_default()),
+            (Some(seat), Some(game)) => game.apply_move(seat, mv).map(|outcome| (seat, outcome)),
+        };
+
+        match result {
+            Ok((seat, outcome)) => {
+                self.moves.push(RecordedMove { seat, mv });
+                self.record(JournalEntry::Move(RecordedMove { seat, mv }));
+                self.broadcast_event(connections, GameEvent::MovePlayed { seat, mv });
+                match outcome {
+                    TurnOutcome::NextPlayer => {}
+                    TurnOutcome::RoundOver => {
+                        self.broadcast_event(connections, GameEvent::RoundOver)
+                    }
+                    TurnOutcome::GameOver => {
+                        let scores = self.seat_scores();
+                        self.broadcast_event(connections, GameEvent::RoundOver);
+                        self.broadcast_event(connections, GameEvent::GameOver { scores });
+                    }
+                }
+                self.broadcast_state(connections);
+            }
+            Err(message) => connections.send(id, &ServerMessage::MoveRefused { mv, message }),
+        }
+    }
+
+    fn chat(&mut self, connections: &mut Connections, id: ConnectionId, text: &str) {
+        let seat = self.find_seat(id);
+        let spectator = self.find_spectator(id);
+        let (name, limiter) = match (seat, spectator) {
+            (Some(seat), _) => {
+                let seat = self.seat_mut(seat);
+                (&seat.name, &mut seat.chat)
+            }
+            (None, Some(spectator)) => {
+                let spectator = &mut self.spectators[spectator];
+                (&spectator.name, &mut spectator.chat)
+            }
+            (None, None) => return,
+        };
+        let text = match check_chat_text(text) {
+            Ok(text) => text,
+            Err(message) => return connections.send(id, &ServerMessage::Error { message }),
+        };
+        if !limiter.allow(Instant::now()) {
+            let message = String::from("You are sending messages too fast");
+            return connections.send(id, &ServerMessage::Error { message });
+        }
+
+        let message = ChatMessage {
+            from: name.clone(),
+            text,
+        };
+        self.chat.push(message.clone());
+        self.record(JournalEntry::Chat(message.clone()));
+        self.broadcast_event(connections, GameEvent::Chat(message));
+    }
+
+    fn leave(&mut self, connections: &mut Connections, id: ConnectionId) {
+        if let Some(spectator) = self.find_spectator(id) {
+            self.spectators.remove(spectator);
+            self.broadcast_spectator_count(connections);
+            return;
+        }
+        let seat = match self.find_seat(id) {
+            Some(seat) => seat,
+            None => return,
+        };
+        if self.game.is_some() {
+            let deadline = Instant::now() + self.grace_period;
+            let left = self.seat_mut(seat);
+            left.connection = None;
+            left.deadline = Some(deadline);
+            self.broadcast_event(connections, GameEvent::PlayerDisconnected { seat });
+        } else {
+            // The others keep their seats, the next player to join takes this one
+            self.seats[seat] = None;
+            self.broadcast_event(connections, GameEvent::PlayerLeft { seat });
+        }
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        self.taken_seats().filter_map(|s| s.deadline).min()
+    }
+
+    /// Gives up the seats of the players who did not come back in time. Without them the game
+    /// cannot go on.
+    fn expire(&mut self, connections: &mut Connections, now: Instant) {
+        let expired: Vec<_> = (0..N)
+            .filter(|&seat| {
+                self.seats[seat]
+                    .as_ref()
+                    .and_then(|s| s.deadline)
+                    .is_some_and(|d| d <= now)
+            })
+            .collect();
+        for seat in expired {
+            self.seat_mut(seat).deadline = None;
+            self.abandoned = true;
+            self.broadcast_event(connections, GameEvent::PlayerLeft { seat });
+        }
+    }
+
+    fn archive(&self) -> ArchivedTable {
+        ArchivedTable {
+            id: self.id,
+            players: self.taken_seats().map(|s| s.name.clone()).collect(),
+            rules: self.rules,
+            seed: self.seed,
+            moves: self.moves.clone(),
+            scores: self.seat_scores(),
+            chat: self.chat.clone(),
+            abandoned: self.abandoned,
+        }
+    }
+
+    fn attach_journal(&mut self, journal: TableJournal) {
+        self.journal = Some(journal);
+    }
+
+    fn replay(&mut self, entry: JournalEntry, now: Instant) -> Result<(), String> {
+        match entry {
+            JournalEntry::Created { .. } => {
+                return Err(String::from("The table was created twice"))
+            }
+            JournalEntry::Started { .. } if self.game.is_some() => {
+                return Err(String::from("The game was started twice"))
+            }
+            JournalEntry::Started { seats } if seats.len() != N => {
+                return Err(format!(
+                    "The game was started with {} players instead of {}",
+                    seats.len(),
+                    N
+                ))
+            }
+            JournalEntry::Started { seats } => {
+                self.seats = seats
+                    .into_iter()
+                    .map(|seat| {
+                        Some(Seat {
+                            name: seat.name,
+                            token: seat.token,
+                            connection: None,
+                            deadline: Some(now + self.grace_period),
+                            ready: true,
+                            chat: RateLimiter::default(),
+                        })
+                    })
+                    .collect();
+                self.deal();
+            }
+            JournalEntry::Move(recorded) => {
+                let number = self.moves.len() + 1;
+                let game = self
+                    .game
+                    .as_mut()
+                    .ok_or_else(|| format!("Move {} was played before the game started", number))?;
+                game.apply_move(recorded.seat, recorded.mv)
+                    .map_err(|e| format!("Move {} cannot be played: {}", number, e))?;
+                self.moves.push(recorded);
+            }
+            JournalEntry::Chat(message) => self.chat.push(message),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,397 @@
+use std::{
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    thread,
+    time::Duration,
+};
+
+use crate::visor::{
+    backend::{TermionBackend, WindowSize, UNBOUNDED},
+    events::{Event, EventLoop, Flow},
+    Engine, UserInput,
+};
+
+use super::{
+    client::{Connection, NetworkEvent, RemoteGame},
+    protocol::MAX_NAME_LENGTH,
+};
+
+const IAC: u8 = 255;
+const WILL: u8 = 251;
+const WONT: u8 = 252;
+const DO: u8 = 253;
+const DONT: u8 = 254;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+/// Negotiate About Window Size
+const NAWS: u8 = 31;
+
+// Longer subnegotiations are not for us, so they are not kept
+const MAX_SUBNEGOTIATION: usize = 16;
+/// How long a player may take to accept a frame before they are cut off
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy)]
+enum TelnetState {
+    Data,
+    /// Right after a carriage return, which telnet follows with a line feed or a NUL
+    CarriageReturn,
+    Command,
+    /// Waiting for the option of a WILL, WONT, DO or DONT
+    Negotiation,
+    Subnegotiation,
+    SubnegotiationCommand,
+}
+
+type ResizeHandler = Box<dyn FnMut((u16, u16)) + Send>;
+
+/// Strips the telnet commands from what the client sends, leaving only the keys that were
+/// pressed. Clients without telnet (like `nc`) just send the keys, which pass through untouched.
+pub struct TelnetReader<R> {
+    inner: R,
+    state: TelnetState,
+    /// The subnegotiation that is being received
+    subnegotiation: Vec<u8>,
+    on_resize: Option<ResizeHandler>,
+}
+
+impl<R: Read> TelnetReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: TelnetState::Data,
+            subnegotiation: vec![],
+            on_resize: None,
+        }
+    }
+
+    /// Calls `on_resize` with the columns and lines of the window, whenever the client says how
+    /// big it is
+    pub fn with_resize_handler(
+        mut self,
+        on_resize: impl FnMut((u16, u16)) + Send + 'static,
+    ) -> Self {
+        self.on_resize = Some(Box::new(on_resize));
+        self
+    }
+
+    fn keep(&mut self, byte: u8) {
+        if self.subnegotiation.len() < MAX_SUBNEGOTIATION {
+            self.subnegotiation.push(byte);
+        }
+    }
+
+    fn end_subnegotiation(&mut self) {
+        if let [NAWS, w0, w1, h0, h1] = self.subnegotiation[..] {
+            let size = (u16::from_be_bytes([w0, w1]), u16::from_be_bytes([h0, h1]));
+            // A zero means that the client does not know
+            let size = if size.0 == 0 || size.1 == 0 {
+                UNBOUNDED
+            } else {
+                size
+            };
+            if let Some(on_resize) = &mut self.on_resize {
+                on_resize(size);
+            }
+        }
+        self.subnegotiation.clear();
+    }
+
+    // Returns the byte if it is data
+    fn filter(&mut self, byte: u8) -> Option<u8> {
+        let (state, data) = match (self.state, byte) {
+            (TelnetState::Data | TelnetState::CarriageReturn, IAC) => (TelnetState::Command, None),
+            (TelnetState::CarriageReturn, b'\n' | b'\0') => (TelnetState::Data, None),
+            (TelnetState::Data | TelnetState::CarriageReturn, b'\r') => {
+                (TelnetState::CarriageReturn, Some(byte))
+            }
+            (TelnetState::Data | TelnetState::CarriageReturn, _) => (TelnetState::Data, Some(byte)),
+            // An escaped 255
+            (TelnetState::Command, IAC) => (TelnetState::Data, Some(IAC)),
+            (TelnetState::Command, WILL | WONT | DO | DONT) => (TelnetState::Negotiation, None),
+            (TelnetState::Command, SB) => {
+                self.subnegotiation.clear();
+                (TelnetState::Subnegotiation, None)
+            }
+            (TelnetState::Command, _) | (TelnetState::Negotiation, _) => (TelnetState::Data, None),
+            (TelnetState::Subnegotiation, IAC) => (TelnetState::SubnegotiationCommand, None),
+            (TelnetState::Subnegotiation, _) => {
+                self.keep(byte);
+                (TelnetState::Subnegotiation, None)
+            }
+            (TelnetState::SubnegotiationCommand, SE) => {
+                self.end_subnegotiation();
+                (TelnetState::Data, None)
+            }
+            // An escaped 255, like in a window 255 columns wide
+            (TelnetState::SubnegotiationCommand, IAC) => {
+                self.keep(IAC);
+                (TelnetState::Subnegotiation, None)
+            }
+            (TelnetState::SubnegotiationCommand, _) => (TelnetState::Subnegotiation, None),
+        };
+        self.state = state;
+        data
+    }
+}
+
+impl<R: Read> Read for TelnetReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.inner.read(buf)?;
+            if read == 0 {
+                return Ok(0);
+            }
+            let mut kept = 0;
+            for i in 0..read {
+                if let Some(byte) = self.filter(buf[i]) {
+                    buf[kept] = byte;
+                    kept += 1;
+                }
+            }
+            // Only commands, wait for the next keys
+            if kept > 0 {
+                return Ok(kept);
+            }
+        }
+    }
+}
+
+/// Writes to the player. The first write that fails, because the player hung up or stopped
+/// reading, closes the connection: the keys run out, and that ends the session.
+struct SocketWriter {
+    stream: TcpStream,
+    failed: bool,
+}
+
+impl SocketWriter {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            failed: false,
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self::new(self.stream.try_clone()?))
+    }
+}
+
+impl Write for SocketWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.failed && self.stream.write_all(buf).is_err() {
+            self.failed = true;
+            let _ = self.stream.shutdown(Shutdown::Both);
+        }
+        // The engine has nobody to tell about it, the end of the keys tells the session
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum SessionEvent {
+    Network(NetworkEvent),
+    /// The window of the player changed its size
+    Resized,
+}
+
+impl From<NetworkEvent> for SessionEvent {
+    fn from(event: NetworkEvent) -> Self {
+        SessionEvent::Network(event)
+    }
+}
+
+/// Lets people play with nothing but `telnet` or `nc`. Every connection gets its own `Engine`,
+/// which renders the game as ANSI straight into the socket, as big as the window of the player
+/// says it is. Behind the scenes, every connection is a regular client of the game server.
+pub struct TelnetGateway {
+    listener: TcpListener,
+    server: SocketAddr,
+}
+
+impl TelnetGateway {
+    pub fn bind<A: ToSocketAddrs>(addr: A, server: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        Ok(Self { listener, server })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn run(self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let server = self.server;
+            thread::spawn(move || {
+                let _ = run_session(&stream, server);
+                let _ = stream.shutdown(Shutdown::Both);
+            });
+        }
+        Ok(())
+    }
+}
+
+fn run_session(stream: &TcpStream, server: SocketAddr) -> io::Result<()> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut writer = SocketWriter::new(stream.try_clone()?);
+    // We echo what the player types, we want every key as soon as it is pressed, and we want to
+    // know how big the window is
+    writer.write_all(&[IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD, IAC, DO, NAWS])?;
+
+    let mut events = EventLoop::new();
+    let window = WindowSize::default();
+    let resized = events.sender();
+    let resized_window = window.clone();
+    let keys = TelnetReader::new(stream.try_clone()?).with_resize_handler(move |size| {
+        resized_window.set(size);
+        let _ = resized.send(SessionEvent::Resized);
+    });
+    events.forward_keys(keys);
+
+    write!(
+        writer,
+        "{}{}Welcome to azulrs!\r\nWhat is your name? ",
+        termion::clear::All,
+        termion::cursor::Goto(1, 1)
+    )?;
+    let name = match read_name(&mut events, &mut writer)? {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+    writeln!(writer, "\r\nLooking for a table...\r")?;
+    let connection = match Connection::join_any(server, &name) {
+        Ok(connection) => connection,
+        Err(e) => return writeln!(writer, "Could not join a game: {}\r", e),
+    };
+
+    match connection.players() {
+        2 => play::<2>(connection, events, writer, window),
+        3 => play::<3>(connection, events, writer, window),
+        4 => play::<4>(connection, events, writer, window),
+        n => writeln!(writer, "Games for {} players are not supported\r", n),
+    }
+}
+
+/// Returns `None` if the player hangs up before telling us their name
+fn read_name<W: Write>(
+    events: &mut EventLoop<SessionEvent>,
+    writer: &mut W,
+) -> io::Result<Option<String>> {
+    let mut name = String::new();
+    loop {
+        let key = match events.wait() {
+            Event::Key(key) => key,
+            Event::Closed => return Ok(None),
+            _ => continue,
+        };
+        match UserInput::from_text_key(key) {
+            UserInput::Character(c)
+                if !c.is_control() && name.chars().count() < MAX_NAME_LENGTH =>
+            {
+                name.push(c);
+                write!(writer, "{}", c)?;
+            }
+            UserInput::Back if name.pop().is_some() => write!(writer, "\x08 \x08")?,
+            UserInput::Confirm if !name.trim().is_empty() => {
+                return Ok(Some(name.trim().to_owned()));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn play<const N: usize>(
+    connection: Connection,
+    mut events: EventLoop<SessionEvent>,
+    mut writer: SocketWriter,
+    window: WindowSize,
+) -> io::Result<()> {
+    let mut remote = RemoteGame::<N>::new(connection, events.sender())?;
+    write!(writer, "{}", termion::cursor::Hide)?;
+    let backend = TermionBackend::for_window(Box::new(writer.try_clone()?), window);
+    let mut engine = Engine::new(backend, Box::new(remote.view()) as Box<_>);
+
+    // Without a quit, the player hung up
+    let mut quit = false;
+    engine.run(&mut events, |event| {
+        let input = match event {
+            Event::Key(key) if remote.is_typing() => UserInput::from_text_key(key),
+            Event::Key(key) => UserInput::from_key(key),
+            Event::Input(input) => input,
+            Event::External(SessionEvent::Network(event)) => {
+                remote.handle_network_event(event);
+                return Flow::Continue;
+            }
+            // Rendered again, in the new size
+            _ => return Flow::Continue,
+        };
+        quit = remote.handle_input(input);
+        if quit {
+            Flow::Quit
+        } else {
+            Flow::Continue
+        }
+    });
+    if quit {
+        write!(writer, "{}\r\nBye!\r\n", termion::cursor::Show)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use std::sync::{Arc, Mutex};
+
+    use super::{TelnetReader, DO, IAC, NAWS, SB, SE, WILL};
+    use crate::visor::backend::UNBOUNDED;
+
+    fn filter(bytes: &[u8]) -> Vec<u8> {
+        let mut filtered = vec![];
+        TelnetReader::new(bytes).read_to_end(&mut filtered).unwrap();
+        filtered
+    }
+
+    #[test]
+    fn test_telnet_commands_are_stripped() {
+        assert_eq!(filter(&[IAC, DO, 1, b'j', IAC, WILL, 3]), b"j");
+        assert_eq!(filter(&[b'k', IAC, SB, 31, 0, 80, IAC, SE, b'q']), b"kq");
+        assert_eq!(filter(&[IAC, IAC]), [IAC]);
+    }
+
+    #[test]
+    fn test_enter_is_a_single_key() {
+        assert_eq!(filter(b"a\r\nb\r\0c\nd"), b"a\rb\rc\nd");
+    }
+
+    #[test]
+    fn test_the_window_size_is_reported() {
+        let sizes = Arc::new(Mutex::new(vec![]));
+        let reported = sizes.clone();
+        let bytes = [
+            [IAC, SB, NAWS, 0, 80, 0, 24, IAC, SE].as_slice(),
+            b"j",
+            // 255 columns need an escape
+            &[IAC, SB, NAWS, 0, IAC, IAC, 1, 0, IAC, SE],
+            &[IAC, SB, NAWS, 0, 0, 0, 0, IAC, SE],
+        ]
+        .concat();
+        let mut filtered = vec![];
+        TelnetReader::new(bytes.as_slice())
+            .with_resize_handler(move |size| reported.lock().unwrap().push(size))
+            .read_to_end(&mut filtered)
+            .unwrap();
+        assert_eq!(filtered, b"j");
+        assert_eq!(*sizes.lock().unwrap(), [(80, 24), (255, 256), UNBOUNDED]);
+    }
+}
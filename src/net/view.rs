@@ -0,0 +1,307 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    model::{view::GameView, Game},
+    visor::{
+        layout::Layout,
+        renderer::RootedRenderer,
+        view::{PanelBuilder, TextView},
+        Component,
+    },
+};
+
+use super::{
+    chat::Chat,
+    client::{ClientStatus, ConnectionStatus},
+    discovery::FindGames,
+    peer::PeerStatus,
+    protocol::PROTOCOL_VERSION,
+};
+
+/// How many chat messages are shown, older ones scroll away
+const CHAT_LINES: usize = 5;
+
+/// The usual game view, with the state of the connection and the chat under it
+pub struct RemoteGameView<const N: usize> {
+    game: Rc<RefCell<Game<N>>>,
+    status: Rc<RefCell<ClientStatus>>,
+    chat: Rc<RefCell<Chat>>,
+}
+
+impl<const N: usize> RemoteGameView<N> {
+    pub fn new(
+        game: Rc<RefCell<Game<N>>>,
+        status: Rc<RefCell<ClientStatus>>,
+        chat: Rc<RefCell<Chat>>,
+    ) -> Self {
+        Self { game, status, chat }
+    }
+}
+
+impl<const N: usize> Component for RemoteGameView<N> {
+    fn render(&self, writer: &mut RootedRenderer) {
+        let game = self.game.borrow();
+        let status = self.status.borrow();
+        let chat = self.chat.borrow();
+        let layout = Layout::vertical(vec![
+            Box::new(GameView {
+                game: self.game.clone(),
+                clocks: None,
+            }),
+            Box::new(ClientStatusView::new(&game, &status)),
+            Box::new(ChatView::new(&chat)),
+        ]);
+        layout.render(writer);
+    }
+}
+
+pub struct ClientStatusView<'a, const N: usize> {
+    game: &'a Game<N>,
+    status: &'a ClientStatus,
+}
+
+impl<'a, const N: usize> ClientStatusView<'a, N> {
+    pub fn new(game: &'a Game<N>, status: &'a ClientStatus) -> Self {
+        Self { game, status }
+    }
+
+    fn describe_turn(&self) -> String {
+        if !self.status.game_started {
+            return match (self.status.seat, self.status.ready) {
+                (Some(_), false) => String::from("Press enter when you are ready"),
+                _ => String::from("Waiting for the other players"),
+            };
+        }
+        if self.game.is_over() {
+            return String::from("The game is over");
+        }
+        if let Some(&seat) = self.status.away.first() {
+            let name = self.game.get_players()[seat].get_name();
+            return format!("Paused until {} reconnects", name);
+        }
+        let current = self.game.get_current_player_id();
+        let name = self.game.get_players()[current].get_name();
+        match self.status.seat {
+            None => format!("Spectating, {} to move", name),
+            Some(seat) if seat == current => String::from("Your turn"),
+            Some(_) => format!("Waiting for {}", name),
+        }
+    }
+}
+
+impl<const N: usize> Component for ClientStatusView<'_, N> {
+    fn render(&self, writer: &mut RootedRenderer) {
+        let mut lines = vec![];
+        match self.status.connection {
+            ConnectionStatus::Connected => {
+                lines.push(format!(
+                    "Connected to {}, table {}",
+                    self.status.address, self.status.table
+                ));
+                lines.push(self.describe_turn());
+            }
+            ConnectionStatus::Disconnected => {
+                lines.push(format!("Lost connection to {}", self.status.address))
+            }
+        }
+        match self.status.spectators {
+            0 => {}
+            1 => lines.push(String::from("1 spectator is watching")),
+            n => lines.push(format!("{} spectators are watching", n)),
+        }
+        if self.status.pending_move.is_some() {
+            lines.push(String::from("Move sent, waiting for the server"));
+        }
+        if let Some(error) = &self.status.last_error {
+            lines.push(format!("Error: {}", error));
+        }
+
+        let panel = PanelBuilder::default()
+            .name("Status")
+            .component(Box::new(TextView::from(lines.join("\n"))))
+            .build()
+            .unwrap();
+        panel.render(writer);
+    }
+}
+
+/// The last few chat messages, and the message we are typing
+pub struct ChatView<'a> {
+    chat: &'a Chat,
+}
+
+impl<'a> ChatView<'a> {
+    pub fn new(chat: &'a Chat) -> Self {
+        Self { chat }
+    }
+}
+
+impl Component for ChatView<'_> {
+    fn render(&self, writer: &mut RootedRenderer) {
+        let skip = self.chat.messages.len().saturating_sub(CHAT_LINES);
+        let mut lines: Vec<_> = self.chat.messages[skip..]
+            .iter()
+            .map(|message| format!("{}: {}", message.from, message.text))
+            .collect();
+        match &self.chat.draft {
+            Some(draft) => lines.push(format!("> {}_", draft)),
+            None => lines.push(String::from("Press t to chat")),
+        }
+
+        let panel = PanelBuilder::default()
+            .name("Chat")
+            .component(Box::new(TextView::from(lines.join("\n"))))
+            .build()
+            .unwrap();
+        panel.render(writer);
+    }
+}
+
+/// The tables announced on the local network, one per line
+pub struct FindGamesView {
+    games: Rc<RefCell<FindGames>>,
+}
+
+impl FindGamesView {
+    pub fn new(games: Rc<RefCell<FindGames>>) -> Self {
+        Self { games }
+    }
+}
+
+impl Component for FindGamesView {
+    fn render(&self, writer: &mut RootedRenderer) {
+        let games = self.games.borrow();
+        let tables = games.tables();
+        let mut lines: Vec<_> = tables
+            .iter()
+            .enumerate()
+            .map(|(i, found)| {
+                let marker = if i == games.selected { ">" } else { " " };
+                let state = if found.version != PROTOCOL_VERSION {
+                    "incompatible"
+                } else if found.table.started {
+                    "playing"
+                } else if found.table.has_free_seat() {
+                    "open"
+                } else {
+                    "full"
+                };
+                format!(
+                    "{} {} ({}) table {}: {}/{} players, {}",
+                    marker,
+                    found.server,
+                    found.address,
+                    found.table.id,
+                    found.table.seats.len(),
+                    found.table.players,
+                    state
+                )
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push(String::from("Looking for games on the local network..."));
+        }
+        lines.push(String::from("j/k to choose, enter to join, q to quit"));
+        if let Some(error) = &games.last_error {
+            lines.push(format!("Error: {}", error));
+        }
+
+        let panel = PanelBuilder::default()
+            .name("Find games")
+            .component(Box::new(TextView::from(lines.join("\n"))))
+            .build()
+            .unwrap();
+        panel.render(writer);
+    }
+}
+
+/// The game view of a game between peers, with the state of the other peers under it
+pub struct PeerGameView<const N: usize> {
+    game: Rc<RefCell<Game<N>>>,
+    status: Rc<RefCell<PeerStatus>>,
+}
+
+impl<const N: usize> PeerGameView<N> {
+    pub fn new(game: Rc<RefCell<Game<N>>>, status: Rc<RefCell<PeerStatus>>) -> Self {
+        Self { game, status }
+    }
+}
+
+impl<const N: usize> Component for PeerGameView<N> {
+    fn render(&self, writer: &mut RootedRenderer) {
+        let game = self.game.borrow();
+        let status = self.status.borrow();
+        let name = |seat: usize| game.get_players()[seat].get_name().to_owned();
+        let mut lines = vec![format!("Peer to peer, bag seed {:016x}", status.seed)];
+        if let Some(fault) = &status.fault {
+            lines.push(format!("Game stopped: {}", fault));
+        } else if let Some(&seat) = status.disconnected.first() {
+            lines.push(format!("{} hung up", name(seat)));
+        } else if game.is_over() {
+            lines.push(String::from("The game is over"));
+        } else if game.get_current_player_id() == status.seat {
+            lines.push(String::from("Your turn"));
+        } else {
+            lines.push(format!(
+                "Waiting for {}",
+                name(game.get_current_player_id())
+            ));
+        }
+        if let Some(error) = &status.last_error {
+            lines.push(format!("Error: {}", error));
+        }
+
+        let layout = Layout::vertical(vec![
+            Box::new(GameView {
+                game: self.game.clone(),
+                clocks: None,
+            }),
+            Box::new(
+                PanelBuilder::default()
+                    .name("Status")
+                    .component(Box::new(TextView::from(lines.join("\n"))))
+                    .build()
+                    .unwrap(),
+            ),
+        ]);
+        layout.render(writer);
+    }
+}
+
+/// The board of a game played by mail, during the turn of the player
+pub struct TurnFileView<const N: usize> {
+    game: Rc<RefCell<Game<N>>>,
+    last_error: Rc<RefCell<Option<String>>>,
+}
+
+impl<const N: usize> TurnFileView<N> {
+    pub fn new(game: Rc<RefCell<Game<N>>>, last_error: Rc<RefCell<Option<String>>>) -> Self {
+        Self { game, last_error }
+    }
+}
+
+impl<const N: usize> Component for TurnFileView<N> {
+    fn render(&self, writer: &mut RootedRenderer) {
+        let mut lines = vec![String::from(
+            "Your turn: your move goes into the file, q leaves without playing",
+        )];
+        if let Some(error) = &*self.last_error.borrow() {
+            lines.push(format!("Error: {}", error));
+        }
+
+        let layout = Layout::vertical(vec![
+            Box::new(GameView {
+                game: self.game.clone(),
+                clocks: None,
+            }),
+            Box::new(
+                PanelBuilder::default()
+                    .name("Correspondence")
+                    .component(Box::new(TextView::from(lines.join("\n"))))
+                    .build()
+                    .unwrap(),
+            ),
+        ]);
+        layout.render(writer);
+    }
+}
@@ -1,9 +1,15 @@
-use super::Coords;
-use std::io::Write;
+use super::{style::Style, Coords};
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
 
+/// Keeps the screen as plain text, and the style of every character next to it
 pub struct TestBackend {
     cursor: Coords,
     screen: Vec<String>,
+    styles: Vec<Vec<Style>>,
+    size: (u16, u16),
 }
 
 impl Default for TestBackend {
@@ -11,13 +17,32 @@ impl Default for TestBackend {
         Self {
             cursor: (1, 1).into(),
             screen: Default::default(),
+            styles: Default::default(),
+            size: UNBOUNDED,
         }
     }
 }
 
+impl TestBackend {
+    /// A screen of `width` columns and `height` lines, where everything else has all the room
+    /// it wants
+    pub fn with_size(width: u16, height: u16) -> Self {
+        Self {
+            size: (width, height),
+            ..Self::default()
+        }
+    }
+
+    /// The style of every character on the screen, line by line
+    pub fn styles(&self) -> &[Vec<Style>] {
+        &self.styles
+    }
+}
+
 impl TerminalBackend for TestBackend {
     fn clear(&mut self) {
         self.screen = vec![];
+        self.styles = vec![];
         self.cursor = (1, 1).into();
     }
 
@@ -25,24 +50,31 @@ impl TerminalBackend for TestBackend {
         self.cursor = coords;
     }
 
-    fn write(&mut self, text: &str) {
+    fn write(&mut self, text: &str, style: Style) {
         let (x, y): (usize, usize) = (self.cursor.0.into(), self.cursor.1.into());
         for i in 0..=y {
             if self.screen.get(i).is_none() {
                 self.screen.push(String::new());
+                self.styles.push(vec![]);
             }
             if i == y - 1 {
-                let new_width = x - 1 + text.chars().count();
-                let mut new_string = format!("{:width$}", &self.screen[i], width = new_width);
-
-                let replace_at = new_string
-                    .char_indices()
-                    .nth(x - 1)
-                    .map(|(pos, ch)| (pos..pos + ch.len_utf8()))
-                    .unwrap();
-
-                new_string.replace_range(replace_at, text);
-                self.screen[i] = new_string.trim_end().to_string();
+                // Like a terminal, the text takes the place of what was there
+                let width = text.chars().count();
+                let written = x - 1..x - 1 + width;
+                let mut line: Vec<char> = self.screen[i].chars().collect();
+                if line.len() < written.end {
+                    line.resize(written.end, ' ');
+                }
+                line.splice(written.clone(), text.chars());
+                self.screen[i] = line.into_iter().collect::<String>().trim_end().to_string();
+
+                // The styles follow the characters they belong to
+                let styles = &mut self.styles[i];
+                if styles.len() < written.end {
+                    styles.resize(written.end, Style::default());
+                }
+                styles.splice(written, vec![style; width]);
+                styles.truncate(self.screen[i].chars().count());
                 let casted: (u16, u16) = (
                     (x + text.chars().count()).try_into().unwrap(),
                     y.try_into().unwrap(),
@@ -53,32 +85,113 @@ impl TerminalBackend for TestBackend {
     }
 
     fn flush(&mut self) {}
+
+    fn size(&self) -> (u16, u16) {
+        self.size
+    }
 }
 
 impl DebuggableTerminalBackend for TestBackend {
     fn get_contents(&self) -> String {
         self.screen.join("\n").trim_end().to_string()
     }
+
+    fn style_at(&self, coords: Coords) -> Style {
+        let (x, y): (usize, usize) = (coords.0.into(), coords.1.into());
+        self.styles
+            .get(y - 1)
+            .and_then(|line| line.get(x - 1))
+            .copied()
+            .unwrap_or_default()
+    }
 }
 
+/// The size of a screen that never runs out of room
+pub const UNBOUNDED: (u16, u16) = (u16::MAX, u16::MAX);
+
 pub trait TerminalBackend {
     fn clear(&mut self);
     fn set_cursor_to(&mut self, coords: Coords);
-    fn write(&mut self, text: &str);
+    fn write(&mut self, text: &str, style: Style);
     fn flush(&mut self);
+    /// The columns and lines there are room for
+    fn size(&self) -> (u16, u16) {
+        UNBOUNDED
+    }
+}
+
+/// Draws nothing, to find out how much room a component takes
+pub struct NullBackend;
+
+impl TerminalBackend for NullBackend {
+    fn clear(&mut self) {}
+    fn set_cursor_to(&mut self, _coords: Coords) {}
+    fn write(&mut self, _text: &str, _style: Style) {}
+    fn flush(&mut self) {}
 }
 
 pub trait DebuggableTerminalBackend: TerminalBackend {
     fn get_contents(&self) -> String;
+    /// The style of the character at `coords`, counting from 1 like the cursor
+    fn style_at(&self, coords: Coords) -> Style;
+}
+
+/// The size of a terminal at the other end of a connection, kept up to date by whoever hears
+/// from it
+#[derive(Clone)]
+pub struct WindowSize(Arc<Mutex<(u16, u16)>>);
+
+impl Default for WindowSize {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(UNBOUNDED)))
+    }
+}
+
+impl WindowSize {
+    pub fn get(&self) -> (u16, u16) {
+        *self.0.lock().expect("Nobody panics holding the size")
+    }
+
+    pub fn set(&self, size: (u16, u16)) {
+        *self.0.lock().expect("Nobody panics holding the size") = size;
+    }
+}
+
+enum ScreenSize {
+    Unknown,
+    /// The terminal we run in
+    Terminal,
+    Window(WindowSize),
 }
 
 pub struct TermionBackend {
     sink: Box<dyn Write>,
+    size: ScreenSize,
 }
 
 impl TermionBackend {
+    /// Draws on `sink`, without knowing how big the screen behind it is
     pub fn new(sink: Box<dyn Write>) -> Self {
-        Self { sink }
+        Self {
+            sink,
+            size: ScreenSize::Unknown,
+        }
+    }
+
+    /// Draws on our own terminal, and asks it for its size on every frame
+    pub fn for_terminal(sink: Box<dyn Write>) -> Self {
+        Self {
+            sink,
+            size: ScreenSize::Terminal,
+        }
+    }
+
+    /// Draws on a remote terminal, as big as `window` says on every frame
+    pub fn for_window(sink: Box<dyn Write>, window: WindowSize) -> Self {
+        Self {
+            sink,
+            size: ScreenSize::Window(window),
+        }
     }
 }
 
@@ -101,7 +214,26 @@ impl TerminalBackend for TermionBackend {
         write!(self.sink, "{}", termion::cursor::Goto(coords.0, coords.1)).unwrap();
     }
 
-    fn write(&mut self, text: &str) {
-        write!(self.sink, "{}", text).unwrap();
+    fn write(&mut self, text: &str, style: Style) {
+        if style.is_plain() {
+            write!(self.sink, "{}", text).unwrap();
+        } else {
+            write!(
+                self.sink,
+                "{}{}{}",
+                style.ansi(),
+                text,
+                termion::style::Reset
+            )
+            .unwrap();
+        }
+    }
+
+    fn size(&self) -> (u16, u16) {
+        match &self.size {
+            ScreenSize::Unknown => UNBOUNDED,
+            ScreenSize::Terminal => termion::terminal_size().unwrap_or(UNBOUNDED),
+            ScreenSize::Window(window) => window.get(),
+        }
     }
 }
@@ -0,0 +1,222 @@
+use std::{
+    io::Read,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use signal_hook::{consts::SIGWINCH, iterator::Signals};
+use termion::{
+    event::{Event as TermEvent, Key, MouseButton, MouseEvent},
+    input::TermRead,
+};
+
+use super::{input::InputSource, keymap::Keymap, UserInput};
+
+/// Everything the engine wakes up for
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<E> {
+    Key(Key),
+    /// From an [`InputSource`], for screens that need no raw keys
+    Input(UserInput),
+    /// The tick interval went by
+    Tick,
+    /// The terminal changed its size
+    Resize,
+    /// Anything sent from another thread: network messages, timers, bots...
+    External(E),
+    /// The keys, or the inputs, ran out: nobody is left to play
+    Closed,
+}
+
+impl<E> Event<E> {
+    /// What the player did, for screens that take keys and inputs alike
+    pub fn input(&self, keymap: &Keymap) -> Option<UserInput> {
+        match self {
+            Event::Key(key) => Some(keymap.map(*key)),
+            Event::Input(input) => Some(*input),
+            Event::Tick | Event::Resize | Event::External(_) | Event::Closed => None,
+        }
+    }
+}
+
+/// Whether [`super::Engine::run`] keeps going after an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+/// Brings keys, ticks and external events together on one channel. One loop can serve several
+/// screens one after the other, so that only one thread ever reads the keys.
+pub struct EventLoop<E> {
+    sender: Sender<Event<E>>,
+    receiver: Receiver<Event<E>>,
+    tick: Option<Duration>,
+    next_tick: Option<Instant>,
+    /// Once the input is closed, it stays closed for every screen after it
+    closed: bool,
+}
+
+/// Sends events to an [`EventLoop`] later on
+#[derive(Clone)]
+pub struct Timer<E> {
+    events: Sender<Event<E>>,
+}
+
+impl<E: Send + 'static> Timer<E> {
+    /// Sends `event` once `delay` went by
+    pub fn send_after(&self, delay: Duration, event: E) {
+        let events = self.events.clone();
+        thread::spawn(move || {
+            thread::sleep(delay);
+            let _ = events.send(Event::External(event));
+        });
+    }
+}
+
+impl<E: Send + 'static> Default for EventLoop<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Send + 'static> EventLoop<E> {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            tick: None,
+            next_tick: None,
+            closed: false,
+        }
+    }
+
+    /// Sends a [`Event::Tick`] every `interval`, or never with `None`
+    pub fn set_tick(&mut self, interval: Option<Duration>) {
+        self.tick = interval;
+        self.next_tick = interval.map(|interval| Instant::now() + interval);
+    }
+
+    /// Reads keys, and clicks, from `input` on their own thread, until the input ends or the
+    /// loop is gone. The end of the input, or an error reading it, sends [`Event::Closed`].
+    pub fn forward_keys<R: Read + Send + 'static>(&self, input: R) {
+        let events = self.sender.clone();
+        thread::spawn(move || {
+            for event in input.events() {
+                let event = match event {
+                    Ok(TermEvent::Key(key)) => Event::Key(key),
+                    Ok(TermEvent::Mouse(MouseEvent::Press(MouseButton::Left, x, y))) => {
+                        Event::Input(UserInput::Click(x, y))
+                    }
+                    Ok(_) => continue,
+                    Err(_) => break,
+                };
+                if events.send(event).is_err() {
+                    return;
+                }
+            }
+            let _ = events.send(Event::Closed);
+        });
+    }
+
+    /// Sends a [`Event::Resize`] whenever the terminal changes its size
+    pub fn forward_resizes(&self) {
+        let events = self.sender.clone();
+        let Ok(mut signals) = Signals::new([SIGWINCH]) else {
+            // Without the signal, the screen still fits again on the next event
+            return;
+        };
+        thread::spawn(move || {
+            for _ in signals.forever() {
+                if events.send(Event::Resize).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Pulls inputs from `source` on its own thread, until it runs dry or the loop is gone. A
+    /// source that runs dry sends [`Event::Closed`].
+    pub fn forward_input<S: InputSource + Send + 'static>(&self, mut source: S) {
+        let events = self.sender.clone();
+        thread::spawn(move || {
+            while let Some(input) = source.next_input() {
+                if events.send(Event::Input(input)).is_err() {
+                    return;
+                }
+            }
+            let _ = events.send(Event::Closed);
+        });
+    }
+
+    /// Where other threads send their events. Each sender forwards on its own thread, which
+    /// stops once every clone of the sender is dropped.
+    pub fn sender(&self) -> Sender<E> {
+        let (sender, receiver) = mpsc::channel();
+        let events = self.sender.clone();
+        thread::spawn(move || {
+            for event in receiver {
+                if events.send(Event::External(event)).is_err() {
+                    return;
+                }
+            }
+        });
+        sender
+    }
+
+    /// Sends `event` once `delay` went by
+    pub fn send_after(&self, delay: Duration, event: E) {
+        self.timer().send_after(delay, event);
+    }
+
+    /// Schedules events on this loop, from places that cannot reach the loop itself, like the
+    /// handler of [`super::Engine::run`]
+    pub fn timer(&self) -> Timer<E> {
+        Timer {
+            events: self.sender.clone(),
+        }
+    }
+
+    /// Waits for the next event, or the next tick. Once the input is closed, there is nothing
+    /// left to wait for, and it only returns [`Event::Closed`].
+    pub fn wait(&mut self) -> Event<E> {
+        if self.closed {
+            return Event::Closed;
+        }
+        let event = self.next_event();
+        self.closed = matches!(event, Event::Closed);
+        event
+    }
+
+    fn next_event(&mut self) -> Event<E> {
+        loop {
+            let Some(next_tick) = self.next_tick else {
+                return self
+                    .receiver
+                    .recv()
+                    .expect("The loop keeps a sender of its own");
+            };
+            let now = Instant::now();
+            if now >= next_tick {
+                let interval = self.tick.unwrap_or_default();
+                let following = next_tick + interval;
+                // After a long wait, do not send all the ticks that were missed
+                self.next_tick = Some(if following > now {
+                    following
+                } else {
+                    now + interval
+                });
+                return Event::Tick;
+            }
+            match self.receiver.recv_timeout(next_tick - now) {
+                Ok(event) => return event,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    unreachable!("The loop keeps a sender of its own")
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::cmp::max;
+
+use super::{
+    layout::Components,
+    renderer::{FrameCache, RootedRenderer},
+    Component, Coords,
+};
+
+/// A component in a grid, which can take more than one row or column
+struct GridCell<'a> {
+    row: usize,
+    column: usize,
+    /// How many rows and columns it takes, at least one of each
+    span: (usize, usize),
+    component: Box<dyn Component + 'a>,
+}
+
+/// Components in rows and columns. Every column is as wide as its widest component, every row as
+/// high as its highest one, and the components stretch to the cells they are in.
+#[derive(Default)]
+pub struct Grid<'a> {
+    cells: Vec<GridCell<'a>>,
+    /// Empty columns between the columns, and empty lines between the rows
+    spacing: (u16, u16),
+    arrangement: FrameCache<GridArrangement>,
+}
+
+/// Where the cells of a grid go, worked out from their sizes before anything is drawn
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridArrangement {
+    pub column_widths: Vec<u16>,
+    pub row_heights: Vec<u16>,
+    /// The root of every cell, in the order they were added
+    pub roots: Vec<Coords>,
+    /// The room every cell gets, with what it spans and the spacing in between
+    pub sizes: Vec<(u16, u16)>,
+    /// The room the whole grid takes
+    pub size: (u16, u16),
+}
+
+impl<'a> From<Grid<'a>> for Box<dyn Component + 'a> {
+    fn from(s: Grid<'a>) -> Self {
+        Box::new(s)
+    }
+}
+
+impl<'a> Grid<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// One row after the other, each with its components from left to right
+    pub fn from_rows(rows: Vec<Components<'a>>) -> Self {
+        let mut grid = Self::new();
+        for (row, components) in rows.into_iter().enumerate() {
+            for (column, component) in components.into_iter().enumerate() {
+                grid = grid.cell(row, column, component);
+            }
+        }
+        grid
+    }
+
+    pub fn cell<C: Into<Box<dyn Component + 'a>>>(
+        self,
+        row: usize,
+        column: usize,
+        component: C,
+    ) -> Self {
+        self.spanning(row, column, (1, 1), component)
+    }
+
+    /// A component over `span` rows and columns, from `row` and `column` down and to the right
+    pub fn spanning<C: Into<Box<dyn Component + 'a>>>(
+        mut self,
+        row: usize,
+        column: usize,
+        span: (usize, usize),
+        component: C,
+    ) -> Self {
+        self.cells.push(GridCell {
+            row,
+            column,
+            span: (max(span.0, 1), max(span.1, 1)),
+            component: component.into(),
+        });
+        self
+    }
+
+    /// `columns` empty columns between the columns, and `rows` empty lines between the rows
+    pub fn with_spacing(self, columns: u16, rows: u16) -> Self {
+        Self {
+            spacing: (columns, rows),
+            ..self
+        }
+    }
+
+    /// Sizes the columns and the rows from the cells in them, then places the cells
+    pub fn arrange(&self, renderer: &RootedRenderer, available: (u16, u16)) -> GridArrangement {
+        self.arrangement
+            .get_or_insert_with(renderer, available, || {
+                let measured: Vec<(u16, u16)> = self
+                    .cells
+                    .iter()
+                    .map(|cell| cell.component.measure(renderer, available))
+                    .collect();
+                let columns = self.cells.iter().map(|c| c.column + c.span.1).max();
+                let rows = self.cells.iter().map(|c| c.row + c.span.0).max();
+                let mut column_widths = vec![0; columns.unwrap_or(0)];
+                let mut row_heights = vec![0; rows.unwrap_or(0)];
+
+                // The cells that span less first, so that the wide ones only add what is missing
+                let mut order: Vec<usize> = (0..self.cells.len()).collect();
+                order.sort_by_key(|&i| self.cells[i].span);
+                for i in order {
+                    let cell = &self.cells[i];
+                    let (width, height) = measured[i];
+                    fit(
+                        &mut column_widths[cell.column..cell.column + cell.span.1],
+                        width,
+                        self.spacing.0,
+                    );
+                    fit(
+                        &mut row_heights[cell.row..cell.row + cell.span.0],
+                        height,
+                        self.spacing.1,
+                    );
+                }
+
+                let xs = offsets(&column_widths, self.spacing.0);
+                let ys = offsets(&row_heights, self.spacing.1);
+                let roots = self
+                    .cells
+                    .iter()
+                    .map(|cell| Coords(xs[cell.column], ys[cell.row]))
+                    .collect();
+                let sizes = self
+                    .cells
+                    .iter()
+                    .map(|cell| {
+                        let columns = cell.column..cell.column + cell.span.1;
+                        let rows = cell.row..cell.row + cell.span.0;
+                        (
+                            span_length(&column_widths[columns], self.spacing.0),
+                            span_length(&row_heights[rows], self.spacing.1),
+                        )
+                    })
+                    .collect();
+                let size = (
+                    span_length(&column_widths, self.spacing.0),
+                    span_length(&row_heights, self.spacing.1),
+                );
+                GridArrangement {
+                    column_widths,
+                    row_heights,
+                    roots,
+                    sizes,
+                    size,
+                }
+            })
+    }
+}
+
+/// How long `lengths` are together, with `spacing` between each of them
+fn span_length(lengths: &[u16], spacing: u16) -> u16 {
+    let gaps = lengths.len().saturating_sub(1) as u16;
+    lengths
+        .iter()
+        .fold(spacing.saturating_mul(gaps), |total, length| {
+            total.saturating_add(*length)
+        })
+}
+
+/// Where each of `lengths` starts, with `spacing` between them
+fn offsets(lengths: &[u16], spacing: u16) -> Vec<u16> {
+    let mut offset: u16 = 0;
+    lengths
+        .iter()
+        .map(|length| {
+            let start = offset;
+            offset = offset.saturating_add(*length).saturating_add(spacing);
+            start
+        })
+        .collect()
+}
+
+/// Grows `lengths` evenly until they are `needed` long together, the first ones taking what
+/// does not divide
+fn fit(lengths: &mut [u16], needed: u16, spacing: u16) {
+    let missing = needed.saturating_sub(span_length(lengths, spacing));
+    let count = lengths.len() as u16;
+    for (i, length) in lengths.iter_mut().enumerate() {
+        let extra = missing / count + u16::from((i as u16) < missing % count);
+        *length += extra;
+    }
+}
+
+impl Component for Grid<'_> {
+    fn render(&self, renderer: &mut RootedRenderer) {
+        let arrangement = self.arrange(renderer, renderer.available_size());
+        let placed = arrangement.roots.into_iter().zip(arrangement.sizes);
+        for (cell, (root, size)) in self.cells.iter().zip(placed) {
+            renderer.push_layer("grid-cell", root);
+            renderer.limit(size);
+            renderer.set_min_size(size);
+            cell.component.render(renderer);
+            renderer.pop_layer();
+        }
+    }
+
+    fn measure(&self, renderer: &RootedRenderer, available: (u16, u16)) -> (u16, u16) {
+        self.arrange(renderer, available).size
+    }
+}
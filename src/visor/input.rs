@@ -0,0 +1,204 @@
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use termion::{
+    event::{Event, MouseButton, MouseEvent},
+    input::{Events, TermRead},
+};
+
+use super::{backend::UNBOUNDED, keymap::Keymap, UserInput};
+
+/// Where the inputs of the player come from
+pub trait InputSource {
+    /// Waits for the next input, `None` once there is nothing left
+    fn next_input(&mut self) -> Option<UserInput>;
+}
+
+/// The keys typed in a terminal, and the clicks on it
+pub struct TerminalInput<R: Read> {
+    events: Events<R>,
+    keymap: Keymap,
+}
+
+impl<R: Read> TerminalInput<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            events: input.events(),
+            keymap: Keymap::default(),
+        }
+    }
+
+    pub fn with_keymap(self, keymap: Keymap) -> Self {
+        Self { keymap, ..self }
+    }
+}
+
+impl<R: Read> InputSource for TerminalInput<R> {
+    fn next_input(&mut self) -> Option<UserInput> {
+        loop {
+            match self.events.next()? {
+                Ok(Event::Key(key)) => return Some(self.keymap.map(key)),
+                Ok(Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y))) => {
+                    return Some(UserInput::Click(x, y))
+                }
+                Ok(_) => {}
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// A fixed list of inputs, for tests
+#[derive(Default)]
+pub struct ScriptedInput {
+    inputs: VecDeque<UserInput>,
+}
+
+impl ScriptedInput {
+    pub fn new<I: IntoIterator<Item = UserInput>>(inputs: I) -> Self {
+        Self {
+            inputs: inputs.into_iter().collect(),
+        }
+    }
+
+    /// The inputs of the keys in `keys`, as if they were typed
+    pub fn from_keys(keys: &str) -> Self {
+        Self::new(keys.chars().map(UserInput::from_char))
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn next_input(&mut self) -> Option<UserInput> {
+        self.inputs.pop_front()
+    }
+}
+
+/// An input, and when it came since the start of the recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimedInput {
+    /// In milliseconds
+    pub at: u64,
+    pub input: UserInput,
+}
+
+fn unbounded() -> (u16, u16) {
+    UNBOUNDED
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct RecordingHeader {
+    seed: u64,
+    /// Older recordings were played back on a screen without bounds
+    #[serde(default = "unbounded")]
+    size: (u16, u16),
+}
+
+/// A recorded game: the seed of the bag, the size of the terminal, and every input of the players
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    pub seed: u64,
+    /// The columns and lines of the terminal, which decide where everything is drawn
+    pub size: (u16, u16),
+    pub inputs: Vec<TimedInput>,
+}
+
+impl Recording {
+    /// Reads what a [`Recorder`] wrote: the seed and the size on the first line, then one input
+    /// per line
+    pub fn read(contents: &str) -> Result<Self, String> {
+        let mut lines = contents.lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| String::from("The recording is empty"))?;
+        let header: RecordingHeader = serde_json::from_str(header)
+            .map_err(|e| format!("The recording has no seed: {}", e))?;
+        let inputs = lines
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("Line {} of the recording is invalid: {}", i + 2, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            seed: header.seed,
+            size: header.size,
+            inputs,
+        })
+    }
+}
+
+/// Passes the inputs of another source on, and writes each of them down
+pub struct Recorder<S: InputSource, W: Write> {
+    source: S,
+    writer: W,
+    start: Instant,
+}
+
+impl<S: InputSource, W: Write> Recorder<S, W> {
+    /// Starts the recording of a game with the bag shuffled from `seed`, on a terminal of `size`
+    pub fn new(source: S, mut writer: W, seed: u64, size: (u16, u16)) -> std::io::Result<Self> {
+        serde_json::to_writer(&mut writer, &RecordingHeader { seed, size })?;
+        writeln!(writer)?;
+        writer.flush()?;
+        Ok(Self {
+            source,
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    fn record(&mut self, input: UserInput) -> std::io::Result<()> {
+        let at = u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX);
+        serde_json::to_writer(&mut self.writer, &TimedInput { at, input })?;
+        writeln!(self.writer)?;
+        self.writer.flush()
+    }
+}
+
+impl<S: InputSource, W: Write> InputSource for Recorder<S, W> {
+    fn next_input(&mut self) -> Option<UserInput> {
+        let input = self.source.next_input()?;
+        // A recording that cannot be written should not stop the game
+        let _ = self.record(input);
+        Some(input)
+    }
+}
+
+/// Plays a recording back, as fast as possible or at the pace it was recorded
+pub struct ReplayInput {
+    inputs: VecDeque<TimedInput>,
+    /// When the replay started, if it keeps the pace of the recording
+    paced_from: Option<Instant>,
+}
+
+impl ReplayInput {
+    pub fn new(recording: &Recording) -> Self {
+        Self {
+            inputs: recording.inputs.iter().copied().collect(),
+            paced_from: None,
+        }
+    }
+
+    pub fn paced(self) -> Self {
+        Self {
+            paced_from: Some(Instant::now()),
+            ..self
+        }
+    }
+}
+
+impl InputSource for ReplayInput {
+    fn next_input(&mut self) -> Option<UserInput> {
+        let next = self.inputs.pop_front()?;
+        if let Some(start) = self.paced_from {
+            let due = start + Duration::from_millis(next.at);
+            thread::sleep(due.saturating_duration_since(Instant::now()));
+        }
+        Some(next.input)
+    }
+}
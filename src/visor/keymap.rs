@@ -0,0 +1,225 @@
+use std::{collections::BTreeMap, fs, path::Path, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+use termion::event::Key;
+
+use crate::model::Direction;
+
+use super::UserInput;
+
+/// Everything a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Next,
+    Prev,
+    Confirm,
+    Back,
+    Quit,
+    Help,
+}
+
+impl Action {
+    const ALL: [Action; 6] = [
+        Action::Next,
+        Action::Prev,
+        Action::Confirm,
+        Action::Back,
+        Action::Quit,
+        Action::Help,
+    ];
+
+    pub fn input(self) -> UserInput {
+        match self {
+            Action::Next => UserInput::Direction(Direction::Next),
+            Action::Prev => UserInput::Direction(Direction::Prev),
+            Action::Confirm => UserInput::Confirm,
+            Action::Back => UserInput::Back,
+            Action::Quit => UserInput::Exit,
+            Action::Help => UserInput::Help,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Action::Next => "Move the selection down",
+            Action::Prev => "Move the selection up",
+            Action::Confirm => "Confirm",
+            Action::Back => "Go back",
+            Action::Quit => "Quit",
+            Action::Help => "Show or hide the keys",
+        }
+    }
+}
+
+/// Reads the name of a key, as written in the config file: a single character, or one of
+/// `enter`, `space`, `tab`, `backtab`, `esc`, `backspace`, `up`, `down`, `left` and `right`
+pub fn parse_key(name: &str) -> Result<Key, String> {
+    let key = match name {
+        "enter" => Key::Char('\n'),
+        "space" => Key::Char(' '),
+        "tab" => Key::Char('\t'),
+        "backtab" => Key::BackTab,
+        "esc" => Key::Esc,
+        "backspace" => Key::Backspace,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Key::Char(c),
+                _ => return Err(format!("Unknown key {}", name)),
+            }
+        }
+    };
+    Ok(key)
+}
+
+/// The name of a key, the other way around from [`parse_key`]
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Char('\n') => String::from("enter"),
+        Key::Char(' ') => String::from("space"),
+        Key::Char('\t') => String::from("tab"),
+        Key::Char(c) => c.to_string(),
+        Key::BackTab => String::from("backtab"),
+        Key::Esc => String::from("esc"),
+        Key::Backspace => String::from("backspace"),
+        Key::Up => String::from("up"),
+        Key::Down => String::from("down"),
+        Key::Left => String::from("left"),
+        Key::Right => String::from("right"),
+        key => format!("{:?}", key).to_lowercase(),
+    }
+}
+
+/// Which key does what. Digits are always shortcuts: they pick factory N, or pattern line N, and
+/// 0 picks the common area, or the floor line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<Key>)>,
+}
+
+impl Default for Keymap {
+    /// Vim keys, arrow keys and WASD all work out of the box
+    fn default() -> Self {
+        let keys = |names: &[&str]| -> Vec<Key> {
+            names
+                .iter()
+                .map(|name| parse_key(name).expect("The default keys are valid"))
+                .collect()
+        };
+        Self {
+            bindings: vec![
+                (Action::Next, keys(&["j", "down", "right", "s", "d", "tab"])),
+                (
+                    Action::Prev,
+                    keys(&["k", "up", "left", "w", "a", "backtab"]),
+                ),
+                (Action::Confirm, keys(&["enter", "space"])),
+                (Action::Back, keys(&["backspace", "esc"])),
+                (Action::Quit, keys(&["q"])),
+                (Action::Help, keys(&["?"])),
+            ],
+        }
+    }
+}
+
+impl Keymap {
+    /// The keymap everybody gets without a config file
+    pub fn default_ref() -> &'static Keymap {
+        static DEFAULT: OnceLock<Keymap> = OnceLock::new();
+        DEFAULT.get_or_init(Keymap::default)
+    }
+
+    /// Reads a config file like `{"next": ["j", "down"], "quit": ["q", "esc"]}`. The actions it
+    /// leaves out keep their default keys.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let config: BTreeMap<Action, Vec<String>> =
+            serde_json::from_str(json).map_err(|e| format!("Invalid keymap: {}", e))?;
+        let mut keymap = Self::default();
+        for (action, names) in config {
+            let keys = names
+                .iter()
+                .map(|name| parse_key(name))
+                .collect::<Result<Vec<_>, _>>()?;
+            for (bound, bound_keys) in &mut keymap.bindings {
+                if *bound == action {
+                    *bound_keys = keys.clone();
+                }
+            }
+        }
+        keymap.check()?;
+        Ok(keymap)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read the keymap {}: {}", path.display(), e))?;
+        Self::from_json(&json)
+    }
+
+    // A key can only do one thing, and digits are taken
+    fn check(&self) -> Result<(), String> {
+        let mut seen: Vec<(Key, Action)> = vec![];
+        for (action, keys) in &self.bindings {
+            for &key in keys {
+                if let Key::Char(c) = key {
+                    if c.is_ascii_digit() {
+                        return Err(format!("The digit {} is a shortcut, it cannot be bound", c));
+                    }
+                }
+                if let Some((_, other)) = seen.iter().find(|(seen_key, _)| *seen_key == key) {
+                    return Err(format!(
+                        "The key {} is bound to both {:?} and {:?}",
+                        key_name(key),
+                        other,
+                        action
+                    ));
+                }
+                seen.push((key, *action));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn keys(&self, action: Action) -> &[Key] {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map_or(&[], |(_, keys)| keys)
+    }
+
+    pub fn map(&self, key: Key) -> UserInput {
+        let action = Action::ALL
+            .into_iter()
+            .find(|&action| self.keys(action).contains(&key));
+        match (action, key) {
+            (Some(action), _) => action.input(),
+            (None, Key::Char(c)) => match c.to_digit(10) {
+                Some(digit) => UserInput::Select(digit as usize),
+                None => UserInput::Character(c),
+            },
+            (None, _) => UserInput::Noop,
+        }
+    }
+
+    /// One line per action, with its keys
+    pub fn help(&self) -> Vec<String> {
+        let mut lines: Vec<String> = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys: Vec<_> = self.keys(action).iter().map(|&k| key_name(k)).collect();
+                format!("{:<24}{}", action.describe(), keys.join(", "))
+            })
+            .collect();
+        lines.push(format!(
+            "{:<24}{}",
+            "Pick factory or row N", "1-9, 0 for the common area or the floor line"
+        ));
+        lines
+    }
+}
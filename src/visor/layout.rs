@@ -1,10 +1,93 @@
-use super::{renderer::RootedRenderer, Component, Coords, UserEventHandled};
+use std::cmp::{max, min};
+
+use super::{
+    renderer::{FrameCache, RootedRenderer},
+    Component, Coords, UserEventHandled,
+};
 
 pub type Components<'a> = Vec<Box<dyn Component + 'a>>;
 
 pub struct Layout<'a> {
     direction: Direction,
     components: Components<'a>,
+    /// How much room each component takes along the layout, [`Constraint::Auto`] when left out
+    constraints: Vec<Constraint>,
+    /// Empty columns, or lines, between the components
+    spacing: u16,
+    align: Align,
+    measured: FrameCache<Measured>,
+}
+
+/// How much room a component takes along its layout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Constraint {
+    /// What the component measures
+    #[default]
+    Auto,
+    Fixed(u16),
+    Min(u16),
+    Max(u16),
+    /// A share of the room the layout is stretched to, by weight. Components that fill with the
+    /// same weight are as big as each other.
+    Fill(u16),
+}
+
+impl Constraint {
+    /// The room along the layout for a component that measured `measured`
+    fn apply(self, measured: u16) -> u16 {
+        match self {
+            Constraint::Auto | Constraint::Fill(_) => measured,
+            Constraint::Fixed(size) => size,
+            Constraint::Min(size) => max(measured, size),
+            Constraint::Max(size) => min(measured, size),
+        }
+    }
+
+    /// The most room along the layout the component may use, out of `room`
+    fn limit(self, room: u16) -> u16 {
+        match self {
+            Constraint::Fixed(size) | Constraint::Max(size) => min(room, size),
+            Constraint::Auto | Constraint::Min(_) | Constraint::Fill(_) => room,
+        }
+    }
+
+    fn weight(self) -> u16 {
+        match self {
+            Constraint::Fill(weight) => max(weight, 1),
+            _ => 0,
+        }
+    }
+}
+
+/// Where components go across the layout: up and down in a horizontal layout, left and right in
+/// a vertical one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+    /// As big as the biggest of them
+    Stretch,
+}
+
+/// What the components measured, and which way they go
+#[derive(Debug, Clone, PartialEq)]
+struct Measured {
+    direction: Direction,
+    sizes: Vec<(u16, u16)>,
+}
+
+/// Where the components of a layout go, worked out from their sizes before anything is drawn
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arrangement {
+    pub direction: Direction,
+    /// The root of every component, in the order of the components
+    pub roots: Vec<Coords>,
+    /// The room every component gets
+    pub sizes: Vec<(u16, u16)>,
+    /// The room all of them take together
+    pub size: (u16, u16),
 }
 
 impl<'a> From<Layout<'a>> for Box<dyn Component + 'a> {
@@ -18,48 +101,243 @@ impl<'a> Layout<'a> {
         Self {
             direction,
             components,
+            constraints: vec![],
+            spacing: 0,
+            align: Align::Start,
+            measured: FrameCache::default(),
         }
     }
 
     pub fn horizontal(components: Components<'a>) -> Self {
-        Self {
-            direction: Direction::Horizontal,
-            components,
-        }
+        Self::new(Direction::Horizontal, components)
     }
 
     pub fn vertical(components: Components<'a>) -> Self {
+        Self::new(Direction::Vertical, components)
+    }
+
+    /// Side by side when there is room for it, one under the other otherwise. Stacked components
+    /// take their own room, whatever their constraints say.
+    pub fn fitting(components: Components<'a>) -> Self {
+        Self::new(Direction::Fitting, components)
+    }
+
+    /// The constraint of every component, in order
+    pub fn with_constraints(self, constraints: Vec<Constraint>) -> Self {
         Self {
-            direction: Direction::Vertical,
-            components,
+            constraints,
+            ..self
+        }
+    }
+
+    pub fn with_spacing(self, spacing: u16) -> Self {
+        Self { spacing, ..self }
+    }
+
+    pub fn with_align(self, align: Align) -> Self {
+        Self { align, ..self }
+    }
+
+    fn constraint(&self, direction: Direction, index: usize) -> Constraint {
+        if self.direction == Direction::Fitting && direction == Direction::Vertical {
+            return Constraint::Auto;
+        }
+        self.constraints.get(index).copied().unwrap_or_default()
+    }
+
+    /// The room the spacing takes, between all the components
+    fn gaps(&self) -> u16 {
+        let gaps = self.components.len().saturating_sub(1) as u16;
+        self.spacing.saturating_mul(gaps)
+    }
+
+    /// Measures the components one after the other, each with the room the ones before it left
+    fn measure_along(
+        &self,
+        direction: Direction,
+        renderer: &RootedRenderer,
+        available: (u16, u16),
+    ) -> Vec<(u16, u16)> {
+        let (room, across) = direction.split(available);
+        let mut used: u16 = 0;
+        let mut sizes = vec![];
+        for (i, component) in self.components.iter().enumerate() {
+            let constraint = self.constraint(direction, i);
+            let left = constraint.limit(room.saturating_sub(used));
+            let size = component.measure(renderer, direction.join(left, across));
+            used = used
+                .saturating_add(constraint.apply(direction.split(size).0))
+                .saturating_add(self.spacing);
+            sizes.push(size);
+        }
+        sizes
+    }
+
+    /// Which way the components go with `available` room, and what they measure that way
+    fn measure_components(&self, renderer: &RootedRenderer, available: (u16, u16)) -> Measured {
+        self.measured.get_or_insert_with(renderer, available, || {
+            let direction = match self.direction {
+                Direction::Fitting => {
+                    let sizes = self.measure_along(Direction::Horizontal, renderer, available);
+                    let width: u32 = sizes
+                        .iter()
+                        .enumerate()
+                        .map(|(i, size)| {
+                            u32::from(self.constraint(Direction::Horizontal, i).apply(size.0))
+                        })
+                        .sum();
+                    if width + u32::from(self.gaps()) <= u32::from(available.0) {
+                        return Measured {
+                            direction: Direction::Horizontal,
+                            sizes,
+                        };
+                    }
+                    Direction::Vertical
+                }
+                direction => direction,
+            };
+            Measured {
+                direction,
+                sizes: self.measure_along(direction, renderer, available),
+            }
+        })
+    }
+
+    /// Where the components go with `available` room, when the layout has to fill `min_size`
+    pub fn arrange(
+        &self,
+        renderer: &RootedRenderer,
+        available: (u16, u16),
+        min_size: (u16, u16),
+    ) -> Arrangement {
+        let Measured { direction, sizes } = self.measure_components(renderer, available);
+        let constraints: Vec<Constraint> = (0..sizes.len())
+            .map(|i| self.constraint(direction, i))
+            .collect();
+        let (mut lengths, widths): (Vec<u16>, Vec<u16>) = sizes
+            .iter()
+            .zip(&constraints)
+            .map(|(size, constraint)| {
+                let (length, width) = direction.split(*size);
+                (constraint.apply(length), width)
+            })
+            .unzip();
+
+        // The components that fill get the same room for every unit of weight
+        let weights: Vec<u16> = constraints.iter().map(|c| c.weight()).collect();
+        let unit = lengths
+            .iter()
+            .zip(&weights)
+            .filter(|(_, weight)| **weight > 0)
+            .map(|(length, weight)| length.div_ceil(*weight))
+            .max()
+            .unwrap_or(0);
+        for (length, weight) in lengths.iter_mut().zip(&weights) {
+            if *weight > 0 {
+                *length = unit.saturating_mul(*weight);
+            }
+        }
+
+        // Then they share what is left of the room they have to fill
+        let (min_length, min_width) = direction.split(min_size);
+        let natural = lengths
+            .iter()
+            .fold(self.gaps(), |total, length| total.saturating_add(*length));
+        let total_weight: u32 = weights.iter().map(|w| u32::from(*w)).sum();
+        if min_length > natural && total_weight > 0 {
+            let extra = u32::from(min_length - natural);
+            let mut left = extra;
+            for (length, weight) in lengths.iter_mut().zip(&weights) {
+                let share = extra * u32::from(*weight) / total_weight;
+                *length += share as u16;
+                left -= share;
+            }
+            // What the rounding left goes to the first ones
+            for (length, _) in lengths.iter_mut().zip(&weights).filter(|(_, w)| **w > 0) {
+                if left == 0 {
+                    break;
+                }
+                *length += 1;
+                left -= 1;
+            }
+        }
+
+        let width = widths.iter().copied().fold(min_width, max);
+        let mut offset: u16 = 0;
+        let mut roots = vec![];
+        let mut arranged = vec![];
+        for (length, own_width) in lengths.iter().zip(&widths) {
+            let (across, own_width) = match self.align {
+                Align::Start => (0, *own_width),
+                Align::Center => ((width - own_width) / 2, *own_width),
+                Align::End => (width - own_width, *own_width),
+                Align::Stretch => (0, width),
+            };
+            let (x, y) = direction.join(offset, across);
+            roots.push(Coords(x, y));
+            arranged.push(direction.join(*length, own_width));
+            offset = offset.saturating_add(*length).saturating_add(self.spacing);
+        }
+        let length = lengths
+            .iter()
+            .fold(self.gaps(), |total, length| total.saturating_add(*length));
+        Arrangement {
+            direction,
+            roots,
+            sizes: arranged,
+            size: direction.join(length, width),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     Horizontal,
     Vertical,
+    /// Horizontal if the screen is wide enough, vertical otherwise
+    Fitting,
+}
+
+impl Direction {
+    /// The length along the direction, and the width across it
+    fn split(self, (width, height): (u16, u16)) -> (u16, u16) {
+        match self {
+            Direction::Horizontal => (width, height),
+            Direction::Vertical | Direction::Fitting => (height, width),
+        }
+    }
+
+    /// The size of something `length` long along the direction, and `width` wide across it
+    fn join(self, length: u16, width: u16) -> (u16, u16) {
+        self.split((length, width))
+    }
 }
 
 impl<'a> Component for Layout<'a> {
     fn render(&self, renderer: &mut RootedRenderer) {
-        let mut dims = Coords(0, 0);
-        for component in self.components.iter() {
-            let name = match self.direction {
-                Direction::Horizontal => "layout-horizontal",
-                Direction::Vertical => "layout-vertical",
-            };
-            let node_id = renderer.render_into_layer(name, dims, component.as_ref());
-
-            let just_rendered_area = renderer.get_drawn_area(node_id);
-            let move_root_by: Coords = match self.direction {
-                Direction::Horizontal => (just_rendered_area.0, 0).into(),
-                Direction::Vertical => (0, just_rendered_area.1).into(),
-            };
-            dims = move_root_by + dims;
+        let arrangement = self.arrange(renderer, renderer.available_size(), renderer.min_size());
+        let direction = arrangement.direction;
+        let name = match direction {
+            Direction::Horizontal => "layout-horizontal",
+            Direction::Vertical | Direction::Fitting => "layout-vertical",
+        };
+        let placed = arrangement.roots.into_iter().zip(arrangement.sizes);
+        for (i, (component, (root, size))) in self.components.iter().zip(placed).enumerate() {
+            renderer.push_layer(name, root);
+            // The same room the component was measured with
+            let (room, across) = direction.split(renderer.available_size());
+            let room = self.constraint(direction, i).limit(room);
+            renderer.limit(direction.join(room, across));
+            renderer.set_min_size(size);
+            component.render(renderer);
+            renderer.pop_layer();
         }
     }
 
+    fn measure(&self, renderer: &RootedRenderer, available: (u16, u16)) -> (u16, u16) {
+        self.arrange(renderer, available, (0, 0)).size
+    }
+
     fn handle(&mut self, _event: &super::UserInput) -> UserEventHandled {
         UserEventHandled::Noop
     }
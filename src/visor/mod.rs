@@ -1,16 +1,34 @@
 pub mod backend;
+pub mod events;
+pub mod grid;
+pub mod input;
+pub mod keymap;
 pub mod layout;
 pub mod renderer;
+pub mod style;
+pub mod theme;
 pub mod view;
 
-use self::backend::{DebuggableTerminalBackend, TerminalBackend};
-use self::renderer::RootedRenderer;
-use crate::model::{AppEvent, Direction};
+use self::backend::{DebuggableTerminalBackend, TerminalBackend, UNBOUNDED};
+use self::events::{Event, EventLoop, Flow};
+use self::keymap::Keymap;
+use self::renderer::{HitMap, RootedRenderer};
+use self::style::Style;
+use self::theme::Theme;
+use self::view::TooSmallView;
+use crate::model::{AppEvent, Direction, Pick};
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use std::ops::Add;
+use termion::event::Key;
 
 pub trait Component {
     fn render(&self, writer: &mut RootedRenderer);
+    /// How much room the component takes when it has `available`. By default it is drawn where
+    /// nobody sees it; components that know their size without drawing say so here.
+    fn measure(&self, renderer: &RootedRenderer, available: (u16, u16)) -> (u16, u16) {
+        renderer.draw_off_screen(available, |scratch| self.render(scratch))
+    }
     fn handle(&mut self, _event: &UserInput) -> UserEventHandled {
         UserEventHandled::Noop
     }
@@ -38,7 +56,7 @@ impl From<Coords> for (u16, u16) {
 }
 
 #[derive(Copy, Clone, Debug)]
-pub struct Move(i16, i16);
+pub struct Move(pub i16, pub i16);
 
 impl Default for Coords {
     fn default() -> Self {
@@ -65,6 +83,9 @@ impl Add for Coords {
 pub struct Engine<'a, T: TerminalBackend> {
     backend: T,
     root_component: Box<dyn Component + 'a>,
+    /// What can be clicked on the last frame
+    hit_map: HitMap,
+    theme: Theme,
 }
 
 pub enum UserEventHandled {
@@ -73,22 +94,42 @@ pub enum UserEventHandled {
     Noop,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum UserInput {
     Direction(Direction),
     Character(char),
+    /// A digit shortcut, straight to factory or row N
+    Select(usize),
     Confirm,
     Back,
     Exit,
+    Help,
+    /// A click on the screen, before the engine knows what is there
+    Click(u16, u16),
+    /// Something on the board was clicked
+    Pick(Pick),
     Noop,
 }
 
 impl UserInput {
+    /// What the character does with the default keymap
     pub fn from_char(c: char) -> Self {
-        match c {
-            'q' => Self::Exit,
-            '\n' => Self::Confirm,
-            'j' => Self::Direction(Direction::Next),
-            'k' => Self::Direction(Direction::Prev),
+        Self::from_key(Key::Char(c))
+    }
+
+    /// What the key does with the default keymap
+    pub fn from_key(key: Key) -> Self {
+        Keymap::default_ref().map(key)
+    }
+
+    /// While typing text, keys are just characters
+    pub fn from_text_key(key: Key) -> Self {
+        match key {
+            Key::Char('\n') => Self::Confirm,
+            Key::Char(c) => Self::Character(c),
+            Key::Backspace => Self::Back,
+            Key::Esc => Self::Exit,
             _ => Self::Noop,
         }
     }
@@ -101,6 +142,10 @@ where
     pub fn get_contents(&self) -> String {
         self.backend.get_contents()
     }
+
+    pub fn style_at(&self, coords: Coords) -> Style {
+        self.backend.style_at(coords)
+    }
 }
 
 impl<'a, T> Engine<'a, T>
@@ -111,15 +156,73 @@ where
         Self {
             backend,
             root_component: root_component.into(),
+            hit_map: HitMap::default(),
+            theme: Theme::default(),
+        }
+    }
+
+    pub fn with_theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    pub fn hit_map(&self) -> &HitMap {
+        &self.hit_map
+    }
+
+    /// Turns a click into what was clicked on the last frame, and leaves other inputs alone
+    pub fn resolve(&self, input: UserInput) -> UserInput {
+        match input {
+            UserInput::Click(x, y) => self.hit_map.at(Coords(x, y)).unwrap_or(UserInput::Noop),
+            input => input,
         }
     }
 
+    /// Draws the root component in the room the terminal has, or says that there is not enough
     pub fn render(&mut self) {
+        let size = self.backend.size();
         self.backend.clear();
-        let mut renderer = RootedRenderer::default_with_writer(&mut self.backend);
-        self.root_component.render(&mut renderer);
+        let mut renderer = RootedRenderer::default_with_writer(&mut self.backend)
+            .with_theme(&self.theme)
+            .with_size(size);
+        // Without a size, everything fits
+        let needed = if size == UNBOUNDED {
+            (0, 0)
+        } else {
+            renderer.measure(self.root_component.as_ref())
+        };
+        if needed.0 > size.0 || needed.1 > size.1 {
+            TooSmallView { needed, size }.render(&mut renderer);
+        } else {
+            self.root_component.render(&mut renderer);
+        }
+        self.hit_map = renderer.hit_map();
         self.backend.flush();
 
         //sink.flush().unwrap(); // TODO
     }
+
+    /// Renders, then hands every event to `handle` and renders again, until `handle` says to quit
+    /// or the input is closed. A resized terminal is only rendered again.
+    pub fn run<E: Send + 'static>(
+        &mut self,
+        events: &mut EventLoop<E>,
+        mut handle: impl FnMut(Event<E>) -> Flow,
+    ) {
+        self.render();
+        loop {
+            let event = match events.wait() {
+                Event::Input(input) => Event::Input(self.resolve(input)),
+                Event::Resize => {
+                    self.render();
+                    continue;
+                }
+                Event::Closed => return,
+                event => event,
+            };
+            if handle(event) == Flow::Quit {
+                return;
+            }
+            self.render();
+        }
+    }
 }
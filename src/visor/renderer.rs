@@ -1,14 +1,35 @@
 use indextree::{Arena, NodeId};
-use std::cmp::max;
+use std::{
+    cell::RefCell,
+    cmp::{max, min},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-use super::{backend::TerminalBackend, Component, Coords};
+use super::{
+    backend::{NullBackend, TerminalBackend, UNBOUNDED},
+    style::Span,
+    theme::Theme,
+    Component, Coords, UserInput,
+};
 
 #[derive(Debug, PartialEq)]
 struct Layer {
     name: String,
     root: Coords,
+    /// Where the root is on the screen, so that the cursor never walks up the tree
+    origin: Coords,
     drawn_area: (u16, u16),
+    /// What the layers below this one drew, from its root. Kept up to date as they are popped.
+    children_area: (u16, u16),
     cursor: Coords,
+    /// What a click on the layer means, if anything
+    on_click: Option<UserInput>,
+    /// The room there is from the root of the layer, to the right and down
+    bounds: (u16, u16),
+    /// The room the layer should take at least, when its parent stretches it
+    min_size: (u16, u16),
+    /// Where the layer can draw from its root, once it, or a layer above it, was limited
+    clip: (u16, u16),
 }
 
 impl Layer {
@@ -16,8 +37,14 @@ impl Layer {
         Self {
             name: name.to_string(),
             root: Coords::new(0, 0),
+            origin: Coords::new(0, 0),
             drawn_area: Default::default(),
+            children_area: Default::default(),
             cursor: Coords::default(),
+            on_click: None,
+            bounds: UNBOUNDED,
+            min_size: (0, 0),
+            clip: UNBOUNDED,
         }
     }
 
@@ -26,17 +53,112 @@ impl Layer {
         me.root = root;
         me
     }
+
+    /// What this layer and the ones below it drew, from its root
+    fn total_area(&self) -> (u16, u16) {
+        (
+            max(self.drawn_area.0, self.children_area.0),
+            max(self.drawn_area.1, self.children_area.1),
+        )
+    }
+}
+
+/// Where a clickable layer ended up on the screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hitbox {
+    /// The top left corner, in screen coordinates
+    pub from: Coords,
+    /// The bottom right corner, included
+    pub to: Coords,
+    /// How many layers are above this one
+    pub depth: usize,
+    pub input: UserInput,
+}
+
+impl Hitbox {
+    pub fn contains(&self, Coords(x, y): Coords) -> bool {
+        (self.from.0..=self.to.0).contains(&x) && (self.from.1..=self.to.1).contains(&y)
+    }
+}
+
+/// Every clickable layer of a frame
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HitMap(Vec<Hitbox>);
+
+impl HitMap {
+    pub fn hitboxes(&self) -> &[Hitbox] {
+        &self.0
+    }
+
+    /// What a click at `coords` means: the deepest layer under it wins, then the one drawn last
+    pub fn at(&self, coords: Coords) -> Option<UserInput> {
+        self.0
+            .iter()
+            .filter(|hitbox| hitbox.contains(coords))
+            .max_by_key(|hitbox| hitbox.depth)
+            .map(|hitbox| hitbox.input)
+    }
+}
+
+/// How many renderers there were, so that each frame has a number of its own
+static FRAMES: AtomicU64 = AtomicU64::new(0);
+
+/// Something worked out once per frame, like the size of a component, for the room it had
+pub struct FrameCache<T>(RefCell<Option<Cached<T>>>);
+
+struct Cached<T> {
+    frame: u64,
+    available: (u16, u16),
+    value: T,
+}
+
+impl<T> Default for FrameCache<T> {
+    fn default() -> Self {
+        Self(RefCell::new(None))
+    }
+}
+
+impl<T: Clone> FrameCache<T> {
+    /// What was cached on the frame of `renderer` for `available`, or what `compute` says
+    pub fn get_or_insert_with(
+        &self,
+        renderer: &RootedRenderer,
+        available: (u16, u16),
+        compute: impl FnOnce() -> T,
+    ) -> T {
+        if let Some(cached) = &*self.0.borrow() {
+            if cached.frame == renderer.frame && cached.available == available {
+                return cached.value.clone();
+            }
+        }
+        let value = compute();
+        *self.0.borrow_mut() = Some(Cached {
+            frame: renderer.frame,
+            available,
+            value: value.clone(),
+        });
+        value
+    }
 }
 
 pub struct RootedRenderer<'a> {
     components: Arena<Layer>,
     writer: &'a mut dyn TerminalBackend,
     active_layer_id: NodeId,
+    theme: &'a Theme,
+    /// Tells the frames apart, for the [`FrameCache`]s
+    frame: u64,
 }
 
 impl<'a> RootedRenderer<'a> {
     pub fn push_layer<T: ToString>(&mut self, name: T, root: Coords) -> NodeId {
-        let new_node = Layer::with_name_and_root(name, root);
+        let mut new_node = Layer::with_name_and_root(name, root);
+        let parent = self.active_layer();
+        let (width, height) = parent.bounds;
+        new_node.bounds = (width.saturating_sub(root.0), height.saturating_sub(root.1));
+        let (width, height) = parent.clip;
+        new_node.clip = (width.saturating_sub(root.0), height.saturating_sub(root.1));
+        new_node.origin = parent.origin + root;
         let new_node_id = self.components.new_node(new_node);
         self.active_layer_id
             .append(new_node_id, &mut self.components);
@@ -57,58 +179,142 @@ impl<'a> RootedRenderer<'a> {
         node_id
     }
 
-    fn active_layer(&self) -> &Layer {
-        self.components.get(self.active_layer_id).unwrap().get()
+    /// The columns and lines the active layer has room for
+    pub fn available_size(&self) -> (u16, u16) {
+        self.active_layer().bounds
     }
 
-    fn get_absolute_cursor(&self) -> Coords {
-        let absolute_root = self.active_layer_id.ancestors(&self.components).fold(
-            Coords::new(0, 0),
-            |acc, next| {
-                let node = self.components.get(next).unwrap();
-                acc + node.get().root
-            },
+    /// Keeps `size` at the right and at the bottom of the active layer for its parent, like the
+    /// borders of a panel
+    pub fn reserve(&mut self, size: (u16, u16)) {
+        let bounds = &mut self.get_active_layer_mut().bounds;
+        *bounds = (
+            bounds.0.saturating_sub(size.0),
+            bounds.1.saturating_sub(size.1),
         );
-        absolute_root + self.active_layer().cursor
     }
 
-    pub fn pop_layer(&mut self) {
-        self.active_layer_id = self
+    /// Gives the active layer `size` at most, and cuts off whatever it draws past it
+    pub fn limit(&mut self, size: (u16, u16)) {
+        let layer = self.get_active_layer_mut();
+        let bounds = &mut layer.bounds;
+        *bounds = (min(bounds.0, size.0), min(bounds.1, size.1));
+        let clip = &mut layer.clip;
+        *clip = (min(clip.0, size.0), min(clip.1, size.1));
+    }
+
+    /// The room the active layer should fill, at least. Components that can grow, like panels
+    /// and layouts, take it; the others leave it empty.
+    pub fn min_size(&self) -> (u16, u16) {
+        self.active_layer().min_size
+    }
+
+    /// Asks the active layer to fill `size`, at least
+    pub fn set_min_size(&mut self, size: (u16, u16)) {
+        self.get_active_layer_mut().min_size = size;
+    }
+
+    /// How much room `component` takes where the active layer is
+    pub fn measure(&self, component: &dyn Component) -> (u16, u16) {
+        component.measure(self, self.available_size())
+    }
+
+    /// Draws on a screen of `available` that nobody sees, and says how much of it was used
+    pub fn draw_off_screen(
+        &self,
+        available: (u16, u16),
+        draw: impl FnOnce(&mut RootedRenderer),
+    ) -> (u16, u16) {
+        let mut backend = NullBackend;
+        let mut renderer = RootedRenderer::default_with_writer(&mut backend)
+            .with_theme(self.theme)
+            .with_size(available);
+        renderer.frame = self.frame;
+        draw(&mut renderer);
+        renderer.get_drawn_area_for_active_layer()
+    }
+
+    /// Makes the whole active layer clickable
+    pub fn on_click(&mut self, input: UserInput) {
+        self.get_active_layer_mut().on_click = Some(input);
+    }
+
+    /// Makes `size` cells from `from` clickable, without drawing anything. `from` is relative to
+    /// the active layer, like the cursor.
+    pub fn add_hitbox(&mut self, from: Coords, size: (u16, u16), input: UserInput) {
+        let root = Coords(from.0 - 1, from.1 - 1);
+        let mut layer = Layer::with_name_and_root("hitbox", root);
+        layer.origin = self.active_layer().origin + root;
+        layer.drawn_area = size;
+        layer.on_click = Some(input);
+        let node_id = self.components.new_node(layer);
+        self.active_layer_id.append(node_id, &mut self.components);
+        self.grow_active_layer(root, size);
+    }
+
+    /// Makes room in the active layer for `area`, drawn by a layer below it at `root`
+    fn grow_active_layer(&mut self, root: Coords, area: (u16, u16)) {
+        let children_area = &mut self.get_active_layer_mut().children_area;
+        *children_area = (
+            max(children_area.0, root.0 + area.0),
+            max(children_area.1, root.1 + area.1),
+        );
+    }
+
+    fn absolute_root(&self, node_id: NodeId) -> Coords {
+        self.components.get(node_id).unwrap().get().origin
+    }
+
+    /// Where every clickable layer drawn so far is on the screen
+    pub fn hit_map(&self) -> HitMap {
+        let root = self
             .active_layer_id
             .ancestors(&self.components)
-            .nth(1)
+            .last()
             .unwrap();
-        self.reset_cursor_to_root();
+        let hitboxes = root
+            .descendants(&self.components)
+            .filter_map(|node_id| {
+                let input = self.components.get(node_id).unwrap().get().on_click?;
+                let (width, height) = self.get_drawn_area(node_id);
+                if width == 0 || height == 0 {
+                    return None;
+                }
+                let origin = self.absolute_root(node_id);
+                Some(Hitbox {
+                    from: origin + (1, 1),
+                    to: origin + (width, height),
+                    depth: node_id.ancestors(&self.components).count(),
+                    input,
+                })
+            })
+            .collect();
+        HitMap(hitboxes)
     }
 
-    pub fn get_drawn_area_for_active_layer(&self) -> (u16, u16) {
-        self.get_drawn_area(self.active_layer_id)
+    fn active_layer(&self) -> &Layer {
+        self.components.get(self.active_layer_id).unwrap().get()
     }
 
-    fn calculate_total_area(&self, node_id: NodeId, roots_above: (u16, u16)) -> (u16, u16) {
-        let myself = self.components.get(node_id).unwrap().get();
-        let my_area = (
-            myself.drawn_area.0 + roots_above.0,
-            myself.drawn_area.1 + roots_above.1,
-        );
+    fn get_absolute_cursor(&self) -> Coords {
+        self.absolute_root(self.active_layer_id) + self.active_layer().cursor
+    }
 
-        let result = node_id
-            .children(&self.components)
-            .fold(my_area, |acc, next_id| {
-                let next_node = self.components.get(next_id).unwrap().get();
-                let next_roll = (
-                    next_node.root.0 + roots_above.0,
-                    next_node.root.1 + roots_above.1,
-                );
-                let next_area = self.calculate_total_area(next_id, next_roll);
-                (max(acc.0, next_area.0), max(acc.1, next_area.1))
-            });
+    pub fn pop_layer(&mut self) {
+        let popped = self.active_layer();
+        let (root, area) = (popped.root, popped.total_area());
+        self.active_layer_id = self.components[self.active_layer_id].parent().unwrap();
+        self.grow_active_layer(root, area);
+        self.reset_cursor_to_root();
+    }
 
-        result
+    pub fn get_drawn_area_for_active_layer(&self) -> (u16, u16) {
+        self.get_drawn_area(self.active_layer_id)
     }
 
+    /// What the layer, and every layer below it, drew from its root
     pub fn get_drawn_area(&self, node_id: NodeId) -> (u16, u16) {
-        self.calculate_total_area(node_id, (0, 0))
+        self.components.get(node_id).unwrap().get().total_area()
     }
 
     pub fn default_with_writer(writer: &'a mut dyn TerminalBackend) -> Self {
@@ -119,14 +325,37 @@ impl<'a> RootedRenderer<'a> {
             writer,
             components: nodes,
             active_layer_id: root,
+            theme: Theme::default_ref(),
+            frame: FRAMES.fetch_add(1, Ordering::Relaxed),
         }
     }
 
-    pub fn write(&mut self, s: &str) {
+    pub fn with_theme(self, theme: &'a Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    /// Gives the components a screen of `size`, instead of all the room they want
+    pub fn with_size(mut self, size: (u16, u16)) -> Self {
+        self.get_active_layer_mut().bounds = size;
+        self
+    }
+
+    /// How components should draw themselves
+    pub fn theme(&self) -> &'a Theme {
+        self.theme
+    }
+
+    /// Writes plain text, or a styled [`Span`]. What goes past the room the active layer was
+    /// limited to is cut off.
+    pub fn write<'s, S: Into<Span<'s>>>(&mut self, span: S) {
+        let span = span.into();
+        let s: &str = &span.text;
         if s.is_empty() {
             return;
         }
         let active_node = self.get_active_layer_mut();
+        // The drawn area keeps what did not fit, so that layouts can tell it is too small
+        let visible = clip(s, active_node.cursor, active_node.clip);
         let Coords(x, y) = &mut active_node.cursor;
         let lines_drawn = s.lines().count() as u16 - 1;
         let longest_line = s
@@ -148,7 +377,9 @@ impl<'a> RootedRenderer<'a> {
             *x += new_x;
         }
 
-        self.writer.write(s);
+        if !visible.is_empty() {
+            self.writer.write(&visible, span.style);
+        }
     }
 
     fn get_active_layer_mut(&mut self) -> &mut Layer {
@@ -181,6 +412,17 @@ impl<'a> RootedRenderer<'a> {
     }
 }
 
+/// The part of `text` written at `cursor` that falls within `size`
+fn clip(text: &str, Coords(x, y): Coords, (width, height): (u16, u16)) -> String {
+    let columns = (usize::from(width) + 1).saturating_sub(x.into());
+    let lines = (usize::from(height) + 1).saturating_sub(y.into());
+    text.split('\n')
+        .take(lines)
+        .map(|line| line.chars().take(columns).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use crate::visor::{backend::TestBackend, renderer::RootedRenderer, Coords};
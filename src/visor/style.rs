@@ -0,0 +1,151 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use termion::color;
+
+/// The colors of the terminal palette, or any other one for terminals that take them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Gray,
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn fg(self) -> String {
+        match self {
+            Color::Black => color::Fg(color::Black).to_string(),
+            Color::Red => color::Fg(color::Red).to_string(),
+            Color::Green => color::Fg(color::Green).to_string(),
+            Color::Yellow => color::Fg(color::Yellow).to_string(),
+            Color::Blue => color::Fg(color::Blue).to_string(),
+            Color::Magenta => color::Fg(color::Magenta).to_string(),
+            Color::Cyan => color::Fg(color::Cyan).to_string(),
+            Color::White => color::Fg(color::White).to_string(),
+            Color::Gray => color::Fg(color::LightBlack).to_string(),
+            Color::Rgb(r, g, b) => color::Fg(color::Rgb(r, g, b)).to_string(),
+        }
+    }
+
+    fn bg(self) -> String {
+        match self {
+            Color::Black => color::Bg(color::Black).to_string(),
+            Color::Red => color::Bg(color::Red).to_string(),
+            Color::Green => color::Bg(color::Green).to_string(),
+            Color::Yellow => color::Bg(color::Yellow).to_string(),
+            Color::Blue => color::Bg(color::Blue).to_string(),
+            Color::Magenta => color::Bg(color::Magenta).to_string(),
+            Color::Cyan => color::Bg(color::Cyan).to_string(),
+            Color::White => color::Bg(color::White).to_string(),
+            Color::Gray => color::Bg(color::LightBlack).to_string(),
+            Color::Rgb(r, g, b) => color::Bg(color::Rgb(r, g, b)).to_string(),
+        }
+    }
+}
+
+/// How text looks. The default is whatever the terminal does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub reverse: bool,
+}
+
+impl Style {
+    pub fn fg(self, color: Color) -> Self {
+        Self {
+            fg: Some(color),
+            ..self
+        }
+    }
+
+    pub fn bg(self, color: Color) -> Self {
+        Self {
+            bg: Some(color),
+            ..self
+        }
+    }
+
+    pub fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+
+    pub fn reverse(self) -> Self {
+        Self {
+            reverse: true,
+            ..self
+        }
+    }
+
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The escape codes that switch the terminal to this style
+    pub fn ansi(&self) -> String {
+        let mut codes = String::new();
+        if let Some(fg) = self.fg {
+            codes += &fg.fg();
+        }
+        if let Some(bg) = self.bg {
+            codes += &bg.bg();
+        }
+        if self.bold {
+            codes += termion::style::Bold.as_ref();
+        }
+        if self.reverse {
+            codes += termion::style::Invert.as_ref();
+        }
+        codes
+    }
+}
+
+/// Some text, all in one style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span<'a> {
+    pub text: Cow<'a, str>,
+    pub style: Style,
+}
+
+impl<'a> Span<'a> {
+    pub fn styled<T: Into<Cow<'a, str>>>(text: T, style: Style) -> Self {
+        Self {
+            text: text.into(),
+            style,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Span<'a> {
+    fn from(text: &'a str) -> Self {
+        Self::styled(text, Style::default())
+    }
+}
+
+impl<'a> From<&'a String> for Span<'a> {
+    fn from(text: &'a String) -> Self {
+        Self::styled(text.as_str(), Style::default())
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for Span<'a> {
+    fn from(text: Cow<'a, str>) -> Self {
+        Self::styled(text, Style::default())
+    }
+}
+
+impl From<String> for Span<'_> {
+    fn from(text: String) -> Self {
+        Self::styled(text, Style::default())
+    }
+}
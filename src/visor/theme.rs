@@ -0,0 +1,325 @@
+use std::{fs, path::Path, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::Tile;
+
+use super::style::{Color, Span, Style};
+
+/// One character on the screen, and how it looks. Glyphs take one column, or the layout breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Glyph {
+    pub glyph: char,
+    #[serde(default)]
+    pub style: Style,
+}
+
+impl Glyph {
+    pub fn new(glyph: char, style: Style) -> Self {
+        Self { glyph, style }
+    }
+
+    pub fn span(self) -> Span<'static> {
+        Span::styled(self.glyph.to_string(), self.style)
+    }
+}
+
+/// How each tile is shown
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileGlyphs {
+    pub blue: Glyph,
+    pub green: Glyph,
+    pub red: Glyph,
+    pub white: Glyph,
+    pub yellow: Glyph,
+    pub first_player: Glyph,
+}
+
+impl Default for TileGlyphs {
+    fn default() -> Self {
+        let plain = Style::default();
+        Self {
+            blue: Glyph::new('B', plain.fg(Color::Blue)),
+            green: Glyph::new('G', plain.fg(Color::Green)),
+            red: Glyph::new('R', plain.fg(Color::Red)),
+            white: Glyph::new('W', plain.fg(Color::White)),
+            yellow: Glyph::new('Y', plain.fg(Color::Yellow)),
+            first_player: Glyph::new('1', plain.bold()),
+        }
+    }
+}
+
+/// What shows the selection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Markers {
+    /// Before the selected factory, or the common area
+    pub source: String,
+    /// Before the selected pattern line
+    pub row: String,
+    /// After the selected floor line
+    pub floor: String,
+    /// Around the selected tiles
+    pub open: char,
+    pub close: char,
+}
+
+impl Default for Markers {
+    fn default() -> Self {
+        Self {
+            source: String::from("--> "),
+            row: String::from("-> "),
+            floor: String::from(" <-"),
+            open: '|',
+            close: '|',
+        }
+    }
+}
+
+/// The frame of the panels
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Borders {
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+    pub horizontal: char,
+    pub vertical: char,
+    /// Around the name of a panel
+    pub title_open: char,
+    pub title_close: char,
+}
+
+impl Default for Borders {
+    fn default() -> Self {
+        Self {
+            top_left: '┌',
+            top_right: '┐',
+            bottom_left: '└',
+            bottom_right: '┘',
+            horizontal: '─',
+            vertical: '│',
+            title_open: '|',
+            title_close: '|',
+        }
+    }
+}
+
+impl Borders {
+    /// Only ASCII, for terminals without box drawing characters
+    pub fn ascii() -> Self {
+        Self {
+            top_left: '+',
+            top_right: '+',
+            bottom_left: '+',
+            bottom_right: '+',
+            horizontal: '-',
+            vertical: '|',
+            title_open: '|',
+            title_close: '|',
+        }
+    }
+}
+
+/// `c`, or `fallback` when `c` is not ASCII
+fn ascii_or(c: char, fallback: char) -> char {
+    if c.is_ascii() {
+        c
+    } else {
+        fallback
+    }
+}
+
+/// How the board is drawn. A theme file only needs what it changes, the rest is the default.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub tiles: TileGlyphs,
+    /// A free slot of a pattern line or of the floor line
+    pub empty_slot: Glyph,
+    /// A tile on the wall, in the style of the tile
+    pub wall_filled: char,
+    /// The tiles a wall still waits for
+    pub wall_hint: Style,
+    pub markers: Markers,
+    pub borders: Borders,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            tiles: TileGlyphs::default(),
+            empty_slot: Glyph::new('☐', Style::default()),
+            wall_filled: 'X',
+            wall_hint: Style::default().fg(Color::Gray),
+            markers: Markers::default(),
+            borders: Borders::default(),
+        }
+    }
+}
+
+impl Theme {
+    pub const PRESETS: [&'static str; 4] = ["default", "high-contrast", "colorblind", "ascii"];
+
+    /// The theme everybody gets without asking for another one
+    pub fn default_ref() -> &'static Theme {
+        static DEFAULT: OnceLock<Theme> = OnceLock::new();
+        DEFAULT.get_or_init(Theme::default)
+    }
+
+    /// Colored backgrounds, bold letters and heavy borders, for dim screens and tired eyes
+    pub fn high_contrast() -> Self {
+        let tile = |glyph, bg, fg| Glyph::new(glyph, Style::default().bg(bg).fg(fg).bold());
+        Self {
+            tiles: TileGlyphs {
+                blue: tile('B', Color::Blue, Color::White),
+                green: tile('G', Color::Green, Color::Black),
+                red: tile('R', Color::Red, Color::White),
+                white: tile('W', Color::White, Color::Black),
+                yellow: tile('Y', Color::Yellow, Color::Black),
+                first_player: Glyph::new('1', Style::default().bold().reverse()),
+            },
+            empty_slot: Glyph::new('·', Style::default().bold()),
+            wall_filled: 'X',
+            wall_hint: Style::default(),
+            markers: Markers {
+                source: String::from("==> "),
+                row: String::from("=> "),
+                floor: String::from(" <="),
+                open: '[',
+                close: ']',
+            },
+            borders: Borders {
+                top_left: '┏',
+                top_right: '┓',
+                bottom_left: '┗',
+                bottom_right: '┛',
+                horizontal: '━',
+                vertical: '┃',
+                title_open: '┫',
+                title_close: '┣',
+            },
+        }
+    }
+
+    /// The Okabe-Ito colors, which stay apart for every kind of color blindness, and a shape for
+    /// every tile so that color is never the only clue
+    pub fn colorblind() -> Self {
+        let tile = |glyph, (r, g, b)| Glyph::new(glyph, Style::default().fg(Color::Rgb(r, g, b)));
+        Self {
+            tiles: TileGlyphs {
+                blue: tile('●', (0, 114, 178)),
+                green: tile('▲', (0, 158, 115)),
+                red: tile('■', (213, 94, 0)),
+                white: tile('○', (255, 255, 255)),
+                yellow: tile('◆', (240, 228, 66)),
+                first_player: Glyph::new('1', Style::default().bold()),
+            },
+            ..Self::default()
+        }
+    }
+
+    /// The default theme, in plain ASCII
+    pub fn ascii() -> Self {
+        Self::default().to_ascii()
+    }
+
+    /// The same theme, with everything that is not ASCII replaced: letters for the tiles, `.` for
+    /// the empty slots and `+-|` for the borders. The styles stay.
+    pub fn to_ascii(self) -> Self {
+        let letters = TileGlyphs::default();
+        let tile = |glyph: Glyph, letter: Glyph| {
+            Glyph::new(ascii_or(glyph.glyph, letter.glyph), glyph.style)
+        };
+        let marker = |marker: String, fallback: String| {
+            if marker.is_ascii() {
+                marker
+            } else {
+                fallback
+            }
+        };
+        let markers = Markers::default();
+        let ascii = Borders::ascii();
+        let borders = self.borders;
+        Self {
+            tiles: TileGlyphs {
+                blue: tile(self.tiles.blue, letters.blue),
+                green: tile(self.tiles.green, letters.green),
+                red: tile(self.tiles.red, letters.red),
+                white: tile(self.tiles.white, letters.white),
+                yellow: tile(self.tiles.yellow, letters.yellow),
+                first_player: tile(self.tiles.first_player, letters.first_player),
+            },
+            empty_slot: Glyph::new(ascii_or(self.empty_slot.glyph, '.'), self.empty_slot.style),
+            wall_filled: ascii_or(self.wall_filled, 'X'),
+            wall_hint: self.wall_hint,
+            markers: Markers {
+                source: marker(self.markers.source, markers.source),
+                row: marker(self.markers.row, markers.row),
+                floor: marker(self.markers.floor, markers.floor),
+                open: ascii_or(self.markers.open, markers.open),
+                close: ascii_or(self.markers.close, markers.close),
+            },
+            borders: Borders {
+                top_left: ascii_or(borders.top_left, ascii.top_left),
+                top_right: ascii_or(borders.top_right, ascii.top_right),
+                bottom_left: ascii_or(borders.bottom_left, ascii.bottom_left),
+                bottom_right: ascii_or(borders.bottom_right, ascii.bottom_right),
+                horizontal: ascii_or(borders.horizontal, ascii.horizontal),
+                vertical: ascii_or(borders.vertical, ascii.vertical),
+                title_open: ascii_or(borders.title_open, ascii.title_open),
+                title_close: ascii_or(borders.title_close, ascii.title_close),
+            },
+        }
+    }
+
+    pub fn preset(name: &str) -> Result<Self, String> {
+        match name {
+            "default" => Ok(Self::default()),
+            "high-contrast" => Ok(Self::high_contrast()),
+            "colorblind" => Ok(Self::colorblind()),
+            "ascii" => Ok(Self::ascii()),
+            _ => Err(format!(
+                "Unknown theme {}, expected {}",
+                name,
+                Self::PRESETS.join(", ")
+            )),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid theme: {}", e))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read the theme {}: {}", path.display(), e))?;
+        Self::from_json(&json)
+    }
+
+    pub fn tile(&self, tile: Tile) -> Glyph {
+        match tile {
+            Tile::Blue => self.tiles.blue,
+            Tile::Green => self.tiles.green,
+            Tile::Red => self.tiles.red,
+            Tile::White => self.tiles.white,
+            Tile::Yellow => self.tiles.yellow,
+            Tile::FirstPlayer => self.tiles.first_player,
+        }
+    }
+}
+
+/// Whether the locale can show more than ASCII. Like the C library, the first of `LC_ALL`,
+/// `LC_CTYPE` and `LANG` that is set decides, and without any of them the locale is plain `C`.
+pub fn utf8_locale<F: Fn(&str) -> Option<String>>(var: F) -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .find_map(|name| var(name).filter(|value| !value.is_empty()))
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
@@ -1,6 +1,10 @@
-use crate::visor::{renderer::RootedRenderer, Coords};
+use crate::visor::{
+    renderer::{FrameCache, RootedRenderer},
+    theme::Borders,
+    Coords,
+};
 use derive_builder::Builder;
-use std::fmt::Debug;
+use std::{cmp::max, fmt::Debug};
 
 use super::Component;
 
@@ -44,6 +48,19 @@ impl Component for TextView {
             i += 1;
         }
     }
+
+    fn measure(&self, _renderer: &RootedRenderer, _available: (u16, u16)) -> (u16, u16) {
+        // Empty lines at the end take no room, like when they are drawn
+        let height = self
+            .contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .last()
+            .map_or(0, |(last, _)| last + 1);
+        let width = self.contents.lines().map(|line| line.chars().count()).max();
+        (width.unwrap_or(0) as u16, height as u16)
+    }
 }
 
 #[derive(Builder)]
@@ -54,6 +71,8 @@ pub struct Panel<'a> {
     #[builder(default)]
     padding: u16,
     component: Box<dyn Component + 'a>,
+    #[builder(setter(skip))]
+    size: FrameCache<(u16, u16)>,
 }
 
 impl<'a> From<Panel<'a>> for Box<dyn Component + 'a> {
@@ -67,36 +86,60 @@ impl Component for Panel<'_> {
         self.component.handle(e)
     }
 
-    fn render<'a>(&'a self, writer: &'a mut RootedRenderer) {
-        let (vertical_padding, horizontal_padding) = (self.padding, self.padding);
-
-        let subroot = Coords(1 + horizontal_padding, 1 + vertical_padding); // +1 for the border
-        let node_id = writer.render_into_layer("panel-content", subroot, self.component.as_ref());
-        let (content_w, content_h) = writer.get_drawn_area(node_id);
-        let (mut full_w, full_h) = (
-            content_w + 2 + horizontal_padding * 2,
-            content_h + 2 + vertical_padding * 2,
-        );
-
-        full_w = self.draw_header(writer, full_w);
-
+    fn render(&self, writer: &mut RootedRenderer) {
+        let (natural_w, natural_h) = self.measure(writer, writer.available_size());
+        let (min_w, min_h) = writer.min_size();
+        let (full_w, full_h) = (max(natural_w, min_w), max(natural_h, min_h));
+
+        let subroot = self.content_root();
+        writer.push_layer("panel-content", subroot);
+        // The right and the bottom border, with their padding
+        writer.reserve(subroot.into());
+        writer.set_min_size((full_w - subroot.0 * 2, full_h - subroot.1 * 2));
+        self.component.render(writer);
+        writer.pop_layer();
+
+        let borders = &writer.theme().borders;
+        writer.write(self.header(borders, full_w));
         for i in 2..full_h {
             writer.set_cursor_to(Coords(1, i));
-            writer.write("│");
+            writer.write(borders.vertical.to_string());
             writer.set_cursor_to(Coords(full_w, i));
-            writer.write("│");
+            writer.write(borders.vertical.to_string());
         }
 
         writer.set_cursor_to(Coords(1, full_h));
-        writer.write("└");
-        writer.write(&"─".repeat((full_w - 2).into()));
-        writer.write("┘");
+        writer.write(borders.bottom_left.to_string());
+        writer.write(borders.horizontal.to_string().repeat((full_w - 2).into()));
+        writer.write(borders.bottom_right.to_string());
+    }
+
+    fn measure(&self, renderer: &RootedRenderer, available: (u16, u16)) -> (u16, u16) {
+        self.size.get_or_insert_with(renderer, available, || {
+            let Coords(left, top) = self.content_root();
+            let room = (
+                available.0.saturating_sub(left * 2),
+                available.1.saturating_sub(top * 2),
+            );
+            let (content_w, content_h) = self.component.measure(renderer, room);
+            let (full_w, full_h) = (content_w + left * 2, content_h + top * 2);
+            let header = self.header(&renderer.theme().borders, full_w);
+            (header.chars().count() as u16, full_h)
+        })
     }
 }
 
 impl Panel<'_> {
-    fn draw_header(&self, writer: &mut RootedRenderer, total_width: u16) -> u16 {
-        let mut to_write = String::from("┌");
+    /// Where the content starts, after the border and the padding
+    fn content_root(&self) -> Coords {
+        Coords(1 + self.padding, 1 + self.padding)
+    }
+
+    /// The top border, with the name in it. It is wider than `total_width` when the name needs
+    /// more room.
+    fn header(&self, borders: &Borders, total_width: u16) -> String {
+        let horizontal = borders.horizontal.to_string();
+        let mut to_write = String::from(borders.top_left);
         match &self.name {
             Some(name) => {
                 // (total_length - length of title - two || characters - two spaces - two corners) / 2
@@ -105,10 +148,12 @@ impl Panel<'_> {
                 let dash_length = (total_width_i - 2 - 4 - name_len) / 2;
                 let actual = dash_length.try_into().unwrap_or(0);
                 to_write.push_str(&format!(
-                    "{}| {} |{}",
-                    "─".repeat(actual),
+                    "{}{} {} {}{}",
+                    horizontal.repeat(actual),
+                    borders.title_open,
                     name,
-                    "─".repeat(actual)
+                    borders.title_close,
+                    horizontal.repeat(actual)
                 ));
                 /* If the name of the panel has even characters, and the width of the box is odd (or vice versa, they don't
                  * match), we have to add an extra -, otherwise the top line won't line up with the bottom.
@@ -116,21 +161,36 @@ impl Panel<'_> {
                  * I'm going to add the extra - on the right side.
                  */
                 if total_width as usize % 2 != name.len() % 2 {
-                    to_write.push('─');
+                    to_write.push(borders.horizontal);
                 }
-                //actual_panel_width
             }
             None => {
                 let draw_amount = match total_width {
                     0..=2 => 0,
                     n => n - 2,
                 } as usize;
-                to_write.push_str(&"─".repeat(draw_amount));
-                //total_width
+                to_write.push_str(&horizontal.repeat(draw_amount));
             }
         };
-        to_write.push('┐');
-        writer.write(&to_write);
-        to_write.chars().count() as u16
+        to_write.push(borders.top_right);
+        to_write
+    }
+}
+
+/// Shown instead of a screen that does not fit in the terminal
+pub struct TooSmallView {
+    /// The columns and lines the screen needs
+    pub needed: (u16, u16),
+    /// The columns and lines the terminal has
+    pub size: (u16, u16),
+}
+
+impl Component for TooSmallView {
+    fn render(&self, writer: &mut RootedRenderer) {
+        TextView::new(format!(
+            "Terminal too small: {}x{}\nMake it at least {}x{}",
+            self.size.0, self.size.1, self.needed.0, self.needed.1
+        ))
+        .render(writer);
     }
 }
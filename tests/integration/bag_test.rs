@@ -4,9 +4,50 @@ use crate::util::eq_lists;
 
 #[test]
 pub fn test_drawing_reshuffles_if_not_enough_tiles_are_in_bag() {
-    let mut bag = Bag::new(vec![Tile::Yellow, Tile::Red, Tile::Red], vec![Tile::Blue]);
+    let mut bag = Bag::new(
+        vec![Tile::Yellow, Tile::Red, Tile::Red],
+        vec![Tile::Blue],
+        0,
+    );
     let mut factory = Factory::new_empty();
     bag.fill_factory(&mut factory);
     let tiles = factory.get_tiles().unwrap();
     eq_lists(tiles, &[Tile::Yellow, Tile::Red, Tile::Red, Tile::Blue]);
 }
+
+#[test]
+pub fn test_factories_stay_empty_when_the_tiles_run_out() {
+    let mut bag = Bag::new(vec![Tile::Yellow, Tile::Red], vec![Tile::Blue], 0);
+    let mut factory = Factory::new_empty();
+    bag.fill_factory(&mut factory);
+    assert!(factory.is_empty());
+
+    // The tiles are still there for the next round
+    bag.discard(&[Tile::White]);
+    bag.fill_factory(&mut factory);
+    let tiles = factory.get_tiles().unwrap();
+    eq_lists(tiles, &[Tile::Yellow, Tile::Red, Tile::Blue, Tile::White]);
+}
+
+#[test]
+pub fn test_reshuffles_follow_the_seed() {
+    let deal = |seed| {
+        let discards = [
+            Tile::Yellow,
+            Tile::Red,
+            Tile::Blue,
+            Tile::White,
+            Tile::Green,
+        ];
+        let mut bag = Bag::new(vec![], discards.repeat(4), seed);
+        let mut factories = vec![];
+        for _ in 0..5 {
+            let mut factory = Factory::new_empty();
+            bag.fill_factory(&mut factory);
+            factories.push(factory.get_tiles().unwrap().to_vec());
+        }
+        factories
+    };
+    assert_eq!(deal(7), deal(7));
+    assert_ne!(deal(7), deal(8));
+}
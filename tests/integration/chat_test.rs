@@ -0,0 +1,99 @@
+use std::{net::SocketAddr, thread, time::Duration};
+
+use azulrs::{
+    model::rules::Rules,
+    net::{
+        chat::{CHAT_BURST, MAX_CHAT_LENGTH},
+        client,
+        protocol::{ChatMessage, ClientMessage, GameEvent},
+        server::Server,
+    },
+};
+use pretty_assertions::assert_eq;
+
+use crate::net_helpers::{start_server, ScriptedClient};
+
+fn say(client: &mut ScriptedClient, text: &str) {
+    client.send(&ClientMessage::Chat { text: text.into() });
+}
+
+fn expect_chat(client: &mut ScriptedClient, from: &str, text: &str) {
+    client.expect_event(GameEvent::Chat(ChatMessage {
+        from: from.into(),
+        text: text.into(),
+    }));
+}
+
+#[test]
+fn test_chat_reaches_everybody_at_the_table() {
+    let (addr, _) = start_server::<2>();
+    let mut alice = ScriptedClient::join(addr, "Alice");
+    let mut bob = ScriptedClient::join(addr, "Bob");
+    let mut carol = ScriptedClient::spectate(addr, "Carol");
+    alice.expect_event(GameEvent::SpectatorsChanged { count: 1 });
+
+    say(&mut alice, "  good luck ");
+    expect_chat(&mut bob, "Alice", "good luck");
+    expect_chat(&mut carol, "Alice", "good luck");
+
+    say(&mut carol, "have fun");
+    expect_chat(&mut alice, "Carol", "have fun");
+}
+
+#[test]
+fn test_chat_messages_are_checked() {
+    let (addr, _) = start_server::<2>();
+    let mut alice = ScriptedClient::join(addr, "Alice");
+
+    say(&mut alice, " ");
+    assert_eq!(alice.receive_error(), "Chat messages cannot be empty");
+    say(&mut alice, &"a".repeat(MAX_CHAT_LENGTH + 1));
+    assert_eq!(
+        alice.receive_error(),
+        "Chat messages can be at most 200 characters"
+    );
+
+    let mut mallory = ScriptedClient::connect(addr);
+    say(&mut mallory, "hello?");
+    assert_eq!(mallory.receive_error(), "You have not joined a table");
+}
+
+#[test]
+fn test_chat_is_rate_limited() {
+    let (addr, _) = start_server::<2>();
+    let mut alice = ScriptedClient::join(addr, "Alice");
+    for i in 0..=CHAT_BURST {
+        say(&mut alice, &format!("spam {}", i));
+    }
+    assert_eq!(alice.receive_error(), "You are sending messages too fast");
+}
+
+fn start_lobby_without_grace_period() -> SocketAddr {
+    let server = Server::bind("127.0.0.1:0")
+        .unwrap()
+        .with_grace_period(Duration::ZERO);
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run().unwrap());
+    addr
+}
+
+#[test]
+fn test_chat_is_archived_with_the_game() {
+    let addr = start_lobby_without_grace_period();
+    client::create_table(addr, 2, Rules::default(), None).unwrap();
+    let mut alice = ScriptedClient::join(addr, "Alice");
+    let mut bob = ScriptedClient::join(addr, "Bob");
+    say(&mut bob, "gotta go");
+    expect_chat(&mut alice, "Bob", "gotta go");
+
+    drop(bob);
+    assert!(alice.is_closed());
+    let archive = client::list_archive(addr).unwrap();
+    assert_eq!(
+        archive[0].chat,
+        [ChatMessage {
+            from: "Bob".into(),
+            text: "gotta go".into()
+        }]
+    );
+}
@@ -0,0 +1,200 @@
+use std::{
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use azulrs::{
+    model::TileSource,
+    net::{
+        client::{Connection, ConnectionStatus, NetworkEvent, RemoteGame},
+        protocol::ServerMessage,
+    },
+    visor::{backend::TestBackend, Engine, UserInput},
+};
+
+use crate::net_helpers::{start_server, start_server_with_grace_period, ScriptedClient};
+
+struct TestClient {
+    remote: RemoteGame<2>,
+    events: Receiver<NetworkEvent>,
+}
+
+impl TestClient {
+    fn join(addr: std::net::SocketAddr, name: &str) -> Self {
+        let connection = Connection::open(addr, name, None).unwrap();
+        let (sender, events) = mpsc::channel();
+        let mut remote = RemoteGame::new(connection, sender).unwrap();
+        remote.handle_input(UserInput::Confirm);
+        Self { remote, events }
+    }
+
+    /// Handles network events until the condition is true
+    #[track_caller]
+    fn wait_for(&mut self, condition: impl Fn(&RemoteGame<2>) -> bool) {
+        while !condition(&self.remote) {
+            let event = self
+                .events
+                .recv_timeout(Duration::from_secs(5))
+                .expect("Timed out waiting for the server");
+            self.remote.handle_network_event(event);
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut engine = Engine::new(
+            TestBackend::default(),
+            Box::new(self.remote.view()) as Box<_>,
+        );
+        engine.render();
+        engine.get_contents()
+    }
+}
+
+fn start_game() -> (TestClient, TestClient) {
+    let (addr, _) = start_server::<2>();
+    let mut alice = TestClient::join(addr, "Alice");
+    let mut bob = TestClient::join(addr, "Bob");
+    alice.wait_for(|r| r.status.borrow().game_started);
+    bob.wait_for(|r| r.status.borrow().game_started);
+    (alice, bob)
+}
+
+#[test]
+fn test_client_shows_whose_turn_it_is() {
+    let (alice, bob) = start_game();
+    assert!(alice.remote.is_my_turn());
+    assert!(!bob.remote.is_my_turn());
+    assert!(alice.render().contains("Your turn"));
+    assert!(bob.render().contains("Waiting for Alice"));
+}
+
+#[test]
+fn test_inputs_are_sent_as_moves() {
+    let (mut alice, mut bob) = start_game();
+    for _ in 0..3 {
+        alice.remote.handle_input(UserInput::Confirm);
+    }
+    assert!(alice.remote.status.borrow().pending_move.is_some());
+    assert!(alice.render().contains("Move sent, waiting for the server"));
+
+    alice.wait_for(|r| r.status.borrow().pending_move.is_none());
+    bob.wait_for(|r| r.is_my_turn());
+    assert!(!alice.remote.is_my_turn());
+    assert!(bob.render().contains("Your turn"));
+}
+
+#[test]
+fn test_only_a_refused_move_stops_waiting_for_the_server() {
+    let (mut alice, _bob) = start_game();
+    for _ in 0..3 {
+        alice.remote.handle_input(UserInput::Confirm);
+    }
+    let mv = alice.remote.status.borrow().pending_move.unwrap();
+
+    let mut other = mv;
+    other.source = TileSource::Factory(42.into());
+    for message in [
+        ServerMessage::Error {
+            message: "You are sending messages too fast".into(),
+        },
+        ServerMessage::MoveRefused {
+            mv: other,
+            message: "It is not your turn".into(),
+        },
+    ] {
+        alice
+            .remote
+            .handle_network_event(NetworkEvent::Message(message));
+        assert_eq!(alice.remote.status.borrow().pending_move, Some(mv));
+    }
+
+    let message = "You cannot put these tiles there".to_string();
+    let refused = ServerMessage::MoveRefused { mv, message };
+    alice
+        .remote
+        .handle_network_event(NetworkEvent::Message(refused));
+    let status = alice.remote.status.borrow();
+    assert_eq!(status.pending_move, None);
+    assert_eq!(
+        status.last_error.as_deref(),
+        Some("You cannot put these tiles there")
+    );
+}
+
+#[test]
+fn test_inputs_are_ignored_when_its_not_our_turn() {
+    let (_alice, mut bob) = start_game();
+    let hash = bob.remote.game.borrow().zobrist_hash();
+    for _ in 0..3 {
+        bob.remote.handle_input(UserInput::Confirm);
+    }
+    assert!(bob.remote.status.borrow().pending_move.is_none());
+    assert!(bob.remote.game.borrow().selected_move().is_none());
+    assert_eq!(bob.remote.game.borrow().zobrist_hash(), hash);
+}
+
+#[test]
+fn test_client_notices_lost_connection() {
+    // Without a grace period, the server gives up as soon as Bob leaves
+    let (addr, _) = start_server_with_grace_period::<2>(Duration::ZERO);
+    let mut alice = TestClient::join(addr, "Alice");
+    let bob = ScriptedClient::join(addr, "Bob");
+    alice.wait_for(|r| r.status.borrow().game_started);
+
+    drop(bob);
+    alice.wait_for(|r| r.status.borrow().connection == ConnectionStatus::Disconnected);
+    assert!(alice.render().contains("Lost connection"));
+}
+
+#[test]
+fn test_client_shows_when_the_game_is_paused() {
+    let (addr, _) = start_server::<2>();
+    let mut alice = TestClient::join(addr, "Alice");
+    let bob = ScriptedClient::join(addr, "Bob");
+    alice.wait_for(|r| r.status.borrow().game_started);
+
+    drop(bob);
+    alice.wait_for(|r| !r.status.borrow().away.is_empty());
+    assert!(!alice.remote.is_my_turn());
+    assert!(alice.render().contains("Paused until Bob reconnects"));
+}
+
+#[test]
+fn test_typing_a_chat_message() {
+    let (mut alice, mut bob) = start_game();
+    assert!(alice.render().contains("Press t to chat"));
+
+    alice.remote.handle_input(UserInput::Character('t'));
+    assert!(alice.remote.is_typing());
+    for input in [
+        UserInput::Character('h'),
+        UserInput::Character('i'),
+        UserInput::Character('q'),
+        UserInput::Back,
+        // Does not play a move while typing
+        UserInput::Character('!'),
+    ] {
+        assert!(!alice.remote.handle_input(input));
+    }
+    assert!(alice.render().contains("> hi!_"));
+    assert!(alice.remote.game.borrow().selected_move().is_none());
+
+    alice.remote.handle_input(UserInput::Confirm);
+    assert!(!alice.remote.is_typing());
+    assert!(alice.remote.status.borrow().pending_move.is_none());
+    bob.wait_for(|r| !r.chat.borrow().messages.is_empty());
+    alice.wait_for(|r| !r.chat.borrow().messages.is_empty());
+    assert!(bob.render().contains("Alice: hi!"));
+    assert!(alice.render().contains("Press t to chat"));
+}
+
+#[test]
+fn test_leaving_the_chat_discards_the_message() {
+    let (mut alice, _bob) = start_game();
+    alice.remote.handle_input(UserInput::Character('t'));
+    alice.remote.handle_input(UserInput::Character('x'));
+    // Leaves the chat, not the game
+    assert!(!alice.remote.handle_input(UserInput::Exit));
+    assert!(!alice.remote.is_typing());
+    assert!(alice.remote.chat.borrow().messages.is_empty());
+}
@@ -0,0 +1,136 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use azulrs::{
+    model::{
+        bag::Bag,
+        buildingarea::Destination,
+        clock::{Clocks, TimeControl, Timeout, TimeoutPolicy},
+        player::Player,
+        view::GameView,
+        Game,
+    },
+    visor::{backend::TestBackend, Engine},
+};
+use test_case::test_case;
+
+fn new_game() -> Game<2> {
+    let players = [
+        Player::default_with_name("Alice".into()),
+        Player::default_with_name("Bob".into()),
+    ];
+    Game::for_players_with_bag(players, Bag::seeded(1))
+}
+
+fn control(on_timeout: TimeoutPolicy) -> TimeControl {
+    TimeControl {
+        initial: Duration::from_secs(60),
+        increment: Duration::from_secs(2),
+        on_timeout,
+    }
+}
+
+#[test_case("5+3", 300, 3; "with an increment")]
+#[test_case("1", 60, 0; "without an increment")]
+fn test_time_controls_are_parsed(s: &str, initial: u64, increment: u64) {
+    let control: TimeControl = s.parse().unwrap();
+    assert_eq!(control.initial, Duration::from_secs(initial));
+    assert_eq!(control.increment, Duration::from_secs(increment));
+    assert_eq!(control.on_timeout, TimeoutPolicy::Forfeit);
+}
+
+#[test]
+fn test_invalid_time_controls_are_refused() {
+    assert_eq!(
+        "0+3".parse::<TimeControl>(),
+        Err(String::from(
+            "Invalid time control 0+3, expected minutes+seconds"
+        ))
+    );
+    assert!("five".parse::<TimeControl>().is_err());
+    assert_eq!("floor".parse(), Ok(TimeoutPolicy::ToFloor));
+    assert!("later".parse::<TimeoutPolicy>().is_err());
+}
+
+#[test]
+fn test_only_the_current_player_clock_runs() {
+    let mut game = new_game();
+    let start = Instant::now();
+    let mut clocks = Clocks::start(control(TimeoutPolicy::Forfeit), &game, start);
+
+    let later = start + Duration::from_secs(10);
+    assert_eq!(clocks.remaining(0, later), Duration::from_secs(50));
+    assert_eq!(clocks.remaining(1, later), Duration::from_secs(60));
+    assert_eq!(clocks.readings(later), ["0:50", "1:00"]);
+
+    let mv = game.legal_moves()[0];
+    game.apply_move(0, mv).unwrap();
+    clocks.update(&game, later);
+    let even_later = later + Duration::from_secs(5);
+    assert_eq!(clocks.remaining(0, even_later), Duration::from_secs(52));
+    assert_eq!(clocks.remaining(1, even_later), Duration::from_secs(55));
+    // Nothing happened in between, so nothing changes
+    clocks.update(&game, even_later);
+    assert_eq!(clocks.remaining(0, even_later), Duration::from_secs(52));
+}
+
+#[test]
+fn test_running_out_of_time_forfeits() {
+    let mut game = new_game();
+    let start = Instant::now();
+    let mut clocks = Clocks::start(control(TimeoutPolicy::Forfeit), &game, start);
+    assert_eq!(
+        clocks.tick(&mut game, start + Duration::from_secs(59)),
+        None
+    );
+
+    let timeout = start + Duration::from_secs(60);
+    assert_eq!(
+        clocks.tick(&mut game, timeout),
+        Some(Timeout::Forfeit { seat: 0 })
+    );
+    assert_eq!(clocks.flagged(), Some(0));
+    assert_eq!(clocks.readings(timeout), ["out of time", "1:00"]);
+    assert_eq!(clocks.tick(&mut game, timeout), None);
+}
+
+#[test_case(TimeoutPolicy::FirstLegalMove; "the first legal move")]
+#[test_case(TimeoutPolicy::ToFloor; "to the floor")]
+fn test_running_out_of_time_plays_a_move(policy: TimeoutPolicy) {
+    let mut game = new_game();
+    let first_move = game.legal_moves()[0];
+    let start = Instant::now();
+    let mut clocks = Clocks::start(control(policy), &game, start);
+
+    let timeout = start + Duration::from_secs(61);
+    let Some(Timeout::Played { seat: 0, mv }) = clocks.tick(&mut game, timeout) else {
+        panic!("A move should have been played");
+    };
+    assert_eq!((mv.source, mv.tile), (first_move.source, first_move.tile));
+    if policy == TimeoutPolicy::ToFloor {
+        assert_eq!(mv.destination, Destination::FloorLine);
+    } else {
+        assert_eq!(mv, first_move);
+    }
+    assert_eq!(game.get_current_player_id(), 1);
+    assert_eq!(clocks.flagged(), None);
+    assert_eq!(clocks.readings(timeout), ["0:02", "1:00"]);
+}
+
+#[test]
+fn test_clocks_are_shown_next_to_the_players() {
+    let game = new_game();
+    let clocks = Clocks::start(control(TimeoutPolicy::Forfeit), &game, Instant::now());
+    let view = GameView {
+        game: Rc::new(RefCell::new(game)),
+        clocks: Some(Rc::new(RefCell::new(clocks))),
+    };
+    let mut engine = Engine::new(TestBackend::default(), Box::new(view) as Box<_>);
+    engine.render();
+    let contents = engine.get_contents();
+    assert!(contents.contains("Alice 1:00"), "{}", contents);
+    assert!(contents.contains("Bob 1:00"), "{}", contents);
+}
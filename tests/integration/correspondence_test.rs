@@ -0,0 +1,212 @@
+use std::fs;
+
+use azulrs::{
+    model::{bag::Bag, player::Player, rules::Rules, Game},
+    net::correspondence::{CorrespondenceTurn, SeenGames, TurnFile},
+    visor::UserInput,
+};
+use pretty_assertions::assert_eq;
+
+fn new_file() -> TurnFile {
+    let players = vec![String::from("Alice"), String::from("Bob")];
+    TurnFile::new(players, Rules::default(), 7, 42).unwrap()
+}
+
+/// Plays the first legal move of the current player, through the file
+fn play_first_move(file: &mut TurnFile) {
+    let mut game = file.replay::<2>().unwrap();
+    let mv = game.legal_moves()[0];
+    file.play(&mut game, mv).unwrap();
+}
+
+/// The file after a trip by mail
+fn mail(file: &TurnFile) -> TurnFile {
+    TurnFile::from_json(&file.to_json()).unwrap()
+}
+
+#[test]
+fn test_moves_replay_to_the_same_game() {
+    let mut file = new_file();
+    for _ in 0..3 {
+        play_first_move(&mut file);
+        file = mail(&file);
+    }
+
+    let players = [
+        Player::default_with_name("Alice".into()),
+        Player::default_with_name("Bob".into()),
+    ];
+    let mut game = Game::with_rules(players, Bag::seeded(7), Rules::default());
+    for record in &file.moves {
+        game.apply_move(record.seat, record.mv).unwrap();
+    }
+    let replayed = file.replay::<2>().unwrap();
+    assert_eq!(replayed.zobrist_hash(), game.zobrist_hash());
+    assert_eq!(file.moves.last().unwrap().hash, game.zobrist_hash());
+    assert_eq!(
+        file.moves.iter().map(|r| r.seat).collect::<Vec<_>>(),
+        [0, 1, 0]
+    );
+}
+
+#[test]
+fn test_tampering_is_detected() {
+    let mut file = new_file();
+    play_first_move(&mut file);
+    play_first_move(&mut file);
+
+    let mut changed_move = file.clone();
+    changed_move.moves[0].mv = file.replay::<2>().unwrap().legal_moves()[1];
+    assert_eq!(
+        changed_move.replay::<2>().err().unwrap(),
+        "Move 1 does not follow the move before it"
+    );
+
+    let mut swapped = file.clone();
+    swapped.moves.swap(0, 1);
+    assert_eq!(
+        swapped.replay::<2>().err().unwrap(),
+        "Move 1 does not follow the move before it"
+    );
+
+    let mut other_seed = file.clone();
+    other_seed.seed = 8;
+    assert_eq!(
+        other_seed.replay::<2>().err().unwrap(),
+        "Move 1 does not follow the move before it"
+    );
+
+    let mut dropped = file.clone();
+    dropped.moves.remove(0);
+    assert_eq!(
+        dropped.replay::<2>().err().unwrap(),
+        "Move 1 does not follow the move before it"
+    );
+}
+
+#[test]
+fn test_old_and_rewritten_files_are_refused() {
+    let mut file = new_file();
+    play_first_move(&mut file);
+    let sent_by_alice = mail(&file);
+    play_first_move(&mut file);
+    let sent_by_bob = mail(&file);
+
+    // Alice saw her own move, and gets the move of Bob
+    let seen = sent_by_alice.seen();
+    assert_eq!(sent_by_bob.check_follows(&seen), Ok(()));
+    // Her own file coming back is fine, an older one is not
+    assert_eq!(sent_by_alice.check_follows(&seen), Ok(()));
+    assert_eq!(
+        new_file().check_follows(&seen),
+        Err(String::from(
+            "This file is older than the last one you saw: it has 0 moves, you saw 1"
+        ))
+    );
+
+    // Bob plays another first move, and rebuilds the chain to hide it
+    let mut rewritten = new_file();
+    let mut game = rewritten.replay::<2>().unwrap();
+    let mv = game.legal_moves()[1];
+    rewritten.play(&mut game, mv).unwrap();
+    play_first_move(&mut rewritten);
+    assert!(rewritten.replay::<2>().is_ok());
+    assert_eq!(
+        rewritten.check_follows(&seen),
+        Err(String::from(
+            "This file does not continue the game you saw, its history was changed"
+        ))
+    );
+
+    let other = TurnFile::new(file.players.clone(), Rules::default(), 7, 43).unwrap();
+    assert_eq!(
+        other.check_follows(&seen),
+        Err(String::from("This file is for another game"))
+    );
+}
+
+#[test]
+fn test_only_the_current_player_takes_a_turn() {
+    let mut file = new_file();
+    assert_eq!(
+        CorrespondenceTurn::<2>::new(file.clone(), "Bob").err(),
+        Some(String::from("It is the turn of Alice, not Bob"))
+    );
+
+    let mut turn = CorrespondenceTurn::<2>::new(file.clone(), "Alice").unwrap();
+    // Picks the first factory and its first tile, then plays them on the first pattern line
+    for _ in 0..2 {
+        assert!(!turn.handle_input(UserInput::Confirm));
+    }
+    assert!(turn.handle_input(UserInput::Confirm));
+    assert!(turn.has_played());
+    assert_eq!(turn.file.moves.len(), 1);
+    assert!(turn.file.replay::<2>().is_ok());
+
+    play_first_move(&mut file);
+    assert!(CorrespondenceTurn::<2>::new(mail(&file), "Bob").is_ok());
+}
+
+#[test]
+fn test_invalid_files_are_refused() {
+    assert!(TurnFile::from_json("{}")
+        .err()
+        .unwrap()
+        .starts_with("This is not a turn file"));
+    let json = new_file()
+        .to_json()
+        .replace("\"version\": 1", "\"version\": 9");
+    assert_eq!(
+        TurnFile::from_json(&json).err().unwrap(),
+        "The turn file is version 9, this azulrs reads version 1"
+    );
+    let names = vec![String::from("Alice"), String::from("Alice")];
+    assert_eq!(
+        TurnFile::new(names, Rules::default(), 0, 0).err().unwrap(),
+        "Every player needs a different name"
+    );
+
+    // Files that do not come from `new` are checked the same way
+    let json = new_file()
+        .to_json()
+        .replace("\"factories\": 4", "\"factories\": 50");
+    assert_eq!(
+        TurnFile::from_json(&json).err().unwrap(),
+        "A game for 2 players needs between 1 and 5 factories, not 50"
+    );
+    let json = new_file().to_json().replace("\"Bob\"", "\"Alice\"");
+    assert_eq!(
+        TurnFile::from_json(&json).err().unwrap(),
+        "Every player needs a different name"
+    );
+    let json = new_file()
+        .to_json()
+        .replace("\"Bob\"", "\"Bob\", \"C\", \"D\", \"E\"");
+    assert_eq!(
+        TurnFile::from_json(&json).err().unwrap(),
+        "A game is for 2 to 4 players, not 5"
+    );
+}
+
+#[test]
+fn test_seen_games_are_kept() {
+    let dir = std::env::temp_dir().join(format!("azulrs-seen-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let seen = SeenGames::open(&dir).unwrap();
+    let mut file = new_file();
+    assert_eq!(seen.get(file.game, "Alice").unwrap(), None);
+
+    play_first_move(&mut file);
+    seen.save("Alice", &file.seen()).unwrap();
+    assert_eq!(seen.get(file.game, "Alice").unwrap(), Some(file.seen()));
+    assert_eq!(seen.get(file.game, "Bob").unwrap(), None);
+
+    // Whatever the name, it stays in the directory
+    seen.save("../escaped", &file.seen()).unwrap();
+    assert_eq!(
+        seen.get(file.game, "../escaped").unwrap(),
+        Some(file.seen())
+    );
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,215 @@
+use std::{
+    cell::RefCell,
+    net::{SocketAddr, UdpSocket},
+    rc::Rc,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use azulrs::{
+    model::{rules::Rules, Direction},
+    net::{
+        discovery::{
+            Announcer, Discovered, Discovery, FindGames, FindGamesChoice, ANNOUNCEMENT_LIFETIME,
+        },
+        protocol::{Announcement, TableInfo, PROTOCOL_VERSION},
+        view::FindGamesView,
+    },
+    visor::{backend::TestBackend, Engine, UserInput},
+};
+use pretty_assertions::assert_eq;
+
+use crate::net_helpers::{start_server, ScriptedClient};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn listen_on_loopback() -> Discovery {
+    Discovery::bind("127.0.0.1:0").unwrap()
+}
+
+fn table(id: usize, players: usize, seats: &[&str], started: bool) -> TableInfo {
+    TableInfo {
+        id,
+        players,
+        seats: seats.iter().map(|s| s.to_string()).collect(),
+        rules: Rules::default(),
+        started,
+        spectators: 0,
+    }
+}
+
+fn discovered(port: u16, name: &str, version: u32, tables: Vec<TableInfo>) -> Discovered {
+    Discovered {
+        address: SocketAddr::from(([192, 168, 1, 5], port)),
+        announcement: Announcement {
+            version,
+            name: name.to_owned(),
+            port,
+            tables,
+        },
+    }
+}
+
+fn render(games: &Rc<RefCell<FindGames>>) -> String {
+    let mut engine = Engine::new(
+        TestBackend::default(),
+        Box::new(FindGamesView::new(games.clone())) as Box<_>,
+    );
+    engine.render();
+    engine.get_contents()
+}
+
+#[test]
+fn test_server_announces_its_tables() {
+    let (addr, _) = start_server::<3>();
+    let _alice = ScriptedClient::sit_at(addr, "Alice", None);
+    let discovery = listen_on_loopback();
+    let announcer = Announcer::new("Office", addr, discovery.local_addr().unwrap()).unwrap();
+
+    announcer.announce().unwrap();
+    let found = discovery.receive(TIMEOUT).unwrap().unwrap();
+    assert_eq!(found.address, addr);
+    assert_eq!(found.announcement.name, "Office");
+    assert_eq!(found.announcement.version, PROTOCOL_VERSION);
+    assert_eq!(found.announcement.tables.len(), 1);
+    let table = &found.announcement.tables[0];
+    assert_eq!((table.players, table.seats.len()), (3, 1));
+    assert!(table.has_free_seat());
+}
+
+#[test]
+fn test_stray_packets_are_ignored() {
+    let (addr, _) = start_server::<2>();
+    let discovery = listen_on_loopback();
+    let target = discovery.local_addr().unwrap();
+    let stray = UdpSocket::bind("127.0.0.1:0").unwrap();
+    stray.send_to(b"hello?", target).unwrap();
+
+    Announcer::new("Office", addr, target)
+        .unwrap()
+        .announce()
+        .unwrap();
+    let found = discovery.receive(TIMEOUT).unwrap().unwrap();
+    assert_eq!(found.announcement.name, "Office");
+    assert_eq!(discovery.receive(Duration::from_millis(50)).unwrap(), None);
+}
+
+#[test]
+fn test_announcements_keep_coming() {
+    let (addr, _) = start_server::<2>();
+    let discovery = listen_on_loopback();
+    let announcer = Announcer::new("Office", addr, discovery.local_addr().unwrap())
+        .unwrap()
+        .with_interval(Duration::from_millis(10));
+    thread::spawn(move || announcer.run());
+
+    let (sender, receiver) = mpsc::channel::<Discovered>();
+    discovery.listen(sender);
+    for _ in 0..3 {
+        let found = receiver.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(found.address, addr);
+    }
+}
+
+#[test]
+fn test_find_games_lists_every_table() {
+    let games = Rc::new(RefCell::new(FindGames::default()));
+    let now = Instant::now();
+    assert!(render(&games).contains("Looking for games on the local network..."));
+
+    games.borrow_mut().update(
+        discovered(
+            4242,
+            "Office",
+            PROTOCOL_VERSION,
+            vec![
+                table(0, 2, &["Alice"], false),
+                table(1, 2, &["Bob", "Carol"], true),
+            ],
+        ),
+        now,
+    );
+    games.borrow_mut().update(
+        discovered(
+            4000,
+            "Attic",
+            PROTOCOL_VERSION - 1,
+            vec![table(3, 4, &[], false)],
+        ),
+        now,
+    );
+    let screen = render(&games);
+    assert!(screen.contains("> Attic (192.168.1.5:4000) table 3: 0/4 players, incompatible"));
+    assert!(screen.contains("  Office (192.168.1.5:4242) table 0: 1/2 players, open"));
+    assert!(screen.contains("  Office (192.168.1.5:4242) table 1: 2/2 players, playing"));
+}
+
+#[test]
+fn test_find_games_joins_open_tables_only() {
+    let mut games = FindGames::default();
+    games.update(
+        discovered(
+            4242,
+            "Office",
+            PROTOCOL_VERSION,
+            vec![
+                table(0, 2, &["Alice", "Bob"], true),
+                table(5, 2, &["Alice"], false),
+            ],
+        ),
+        Instant::now(),
+    );
+
+    assert_eq!(games.handle_input(UserInput::Confirm), None);
+    assert_eq!(
+        games.last_error.as_deref(),
+        Some("There is no free seat at this table")
+    );
+    games.handle_input(UserInput::Direction(Direction::Next));
+    assert_eq!(
+        games.handle_input(UserInput::Confirm),
+        Some(FindGamesChoice::Join(
+            SocketAddr::from(([192, 168, 1, 5], 4242)),
+            5
+        ))
+    );
+    // Wraps around
+    games.handle_input(UserInput::Direction(Direction::Next));
+    assert_eq!(games.selected, 0);
+    assert_eq!(
+        games.handle_input(UserInput::Exit),
+        Some(FindGamesChoice::Quit)
+    );
+}
+
+#[test]
+fn test_silent_servers_are_forgotten() {
+    let mut games = FindGames::default();
+    let start = Instant::now();
+    games.update(
+        discovered(
+            4242,
+            "Office",
+            PROTOCOL_VERSION,
+            vec![table(0, 2, &[], false)],
+        ),
+        start,
+    );
+    games.update(
+        discovered(
+            4000,
+            "Attic",
+            PROTOCOL_VERSION,
+            vec![table(0, 2, &[], false)],
+        ),
+        start + ANNOUNCEMENT_LIFETIME,
+    );
+    games.handle_input(UserInput::Direction(Direction::Next));
+
+    games.forget_stale(start + ANNOUNCEMENT_LIFETIME);
+    let tables = games.tables();
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].server, "Attic");
+    assert_eq!(games.selected, 0);
+}
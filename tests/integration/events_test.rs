@@ -0,0 +1,160 @@
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use azulrs::visor::{
+    backend::TestBackend,
+    events::{Event, EventLoop, Flow},
+    input::ScriptedInput,
+    keymap::Keymap,
+    renderer::RootedRenderer,
+    Component, Engine, UserInput,
+};
+use pretty_assertions::assert_eq;
+use termion::event::Key;
+
+/// Shows how many times it was rendered
+struct RenderCount(Rc<Cell<u32>>);
+
+impl Component for RenderCount {
+    fn render(&self, writer: &mut RootedRenderer) {
+        self.0.set(self.0.get() + 1);
+        writer.write(self.0.get().to_string());
+    }
+}
+
+#[test]
+fn test_keys_arrive_in_order() {
+    let mut events = EventLoop::<()>::new();
+    events.forward_keys(&b"jk\n"[..]);
+    assert_eq!(events.wait(), Event::Key(Key::Char('j')));
+    assert_eq!(events.wait(), Event::Key(Key::Char('k')));
+    assert_eq!(events.wait(), Event::Key(Key::Char('\n')));
+    assert_eq!(events.wait(), Event::Closed);
+    // Nothing else can come after the end of the keys
+    assert_eq!(events.wait(), Event::Closed);
+}
+
+#[test]
+fn test_external_events_are_injected() {
+    let mut events = EventLoop::new();
+    let sender = events.sender();
+    std::thread::spawn(move || {
+        sender.send("hello").unwrap();
+        sender.send("world").unwrap();
+    });
+    assert_eq!(events.wait(), Event::External("hello"));
+    assert_eq!(events.wait(), Event::External("world"));
+
+    let start = Instant::now();
+    events.send_after(Duration::from_millis(20), "later");
+    assert_eq!(events.wait(), Event::External("later"));
+    assert!(start.elapsed() >= Duration::from_millis(20));
+}
+
+#[test]
+fn test_ticks_come_at_the_interval() {
+    let mut events = EventLoop::<()>::new();
+    let start = Instant::now();
+    events.set_tick(Some(Duration::from_millis(10)));
+    for _ in 0..3 {
+        assert_eq!(events.wait(), Event::Tick);
+    }
+    assert!(start.elapsed() >= Duration::from_millis(30));
+
+    // Other events still get through between the ticks
+    events.set_tick(Some(Duration::from_secs(60)));
+    events.sender().send(()).unwrap();
+    assert_eq!(events.wait(), Event::External(()));
+}
+
+#[test]
+fn test_run_renders_after_every_event_until_quit() {
+    let renders = Rc::new(Cell::new(0));
+    let mut engine = Engine::new(
+        TestBackend::default(),
+        Box::new(RenderCount(renders.clone())) as Box<_>,
+    );
+    let mut events = EventLoop::new();
+    // The keys stay open, so the loop only stops when the handler says so
+    let (keys, mut typing) = io::pipe().unwrap();
+    typing.write_all(b"ab").unwrap();
+    events.forward_keys(keys);
+    events.sender().send(10).unwrap();
+
+    let mut total = 0;
+    let mut handled = 0;
+    engine.run(&mut events, |event| {
+        handled += 1;
+        match event {
+            Event::Key(_) | Event::Input(_) => total += 1,
+            Event::External(n) => total += n,
+            Event::Tick | Event::Resize | Event::Closed => {}
+        }
+        if handled == 3 {
+            Flow::Quit
+        } else {
+            Flow::Continue
+        }
+    });
+
+    assert_eq!(total, 12);
+    // Once at the start, and after every event but the last one
+    assert_eq!(engine.get_contents(), "3");
+}
+
+#[test]
+fn test_handlers_schedule_events_without_blocking_the_loop() {
+    let mut engine = Engine::new(
+        TestBackend::default(),
+        Box::new(RenderCount(Rc::default())) as Box<_>,
+    );
+    let mut events = EventLoop::new();
+    events.set_tick(Some(Duration::from_millis(5)));
+    let timer = events.timer();
+    events.send_after(Duration::from_millis(20), 1);
+
+    let mut attempts = vec![];
+    let mut ticks = 0;
+    engine.run(&mut events, |event| {
+        match event {
+            Event::External(attempt) => {
+                attempts.push(attempt);
+                if attempt == 3 {
+                    return Flow::Quit;
+                }
+                timer.send_after(Duration::from_millis(20), attempt + 1);
+            }
+            Event::Tick => ticks += 1,
+            Event::Key(_) | Event::Input(_) | Event::Resize | Event::Closed => {}
+        }
+        Flow::Continue
+    });
+
+    assert_eq!(attempts, [1, 2, 3]);
+    // The loop kept going while it waited for the next attempt
+    assert!(ticks >= 3);
+}
+
+#[test]
+fn test_run_stops_when_the_input_runs_dry() {
+    let mut engine = Engine::new(
+        TestBackend::default(),
+        Box::new(RenderCount(Rc::default())) as Box<_>,
+    );
+    let mut events = EventLoop::<()>::new();
+    events.forward_input(ScriptedInput::new([UserInput::Back, UserInput::Confirm]));
+
+    let mut inputs = vec![];
+    engine.run(&mut events, |event| {
+        inputs.extend(event.input(&Keymap::default()));
+        Flow::Continue
+    });
+    assert_eq!(inputs, [UserInput::Back, UserInput::Confirm]);
+
+    // The next screen does not wait for keys that will never come either
+    engine.run(&mut events, |_| Flow::Continue);
+}
@@ -1,5 +1,8 @@
 use crate::util::eq_lists;
-use azulrs::model::{buildingarea::BuildingArea, CommonArea, Factory, Tile};
+use azulrs::model::{
+    buildingarea::{BuildingArea, Destination},
+    CommonArea, Factory, Tile,
+};
 
 #[test]
 fn test_picking_tiles_from_factory() {
@@ -8,8 +11,13 @@ fn test_picking_tiles_from_factory() {
     let mut factory = Factory::new([Tile::Yellow, Tile::Yellow, Tile::Green, Tile::Red]);
     let mut common = CommonArea::default();
 
-    bg.pick_factory(&mut factory, &mut common, 4, Tile::Yellow)
-        .unwrap();
+    bg.pick_factory(
+        &mut factory,
+        &mut common,
+        Destination::PatternLine(4),
+        Tile::Yellow,
+    )
+    .unwrap();
 
     assert!(factory.is_empty());
     eq_lists(
@@ -0,0 +1,106 @@
+use azulrs::model::{
+    bag::Bag, buildingarea::Destination, player::Player, rules::Rules, snapshot::GameSnapshot,
+    Game, Move, Tile, TileSource, TurnOutcome,
+};
+use serde_json::{json, Value};
+use test_case::test_case;
+
+fn new_game() -> Game<2> {
+    let tiles = [Tile::Yellow, Tile::Red, Tile::Blue, Tile::Green].repeat(25);
+    let players = [
+        Player::default_with_name("Alice".into()),
+        Player::default_with_name("Bob".into()),
+    ];
+    Game::for_players_with_bag(players, Bag::new(tiles, vec![], 0))
+}
+
+#[test]
+fn test_tiles_can_go_straight_to_the_floorline() {
+    let mut game = new_game();
+    let mv = Move {
+        source: TileSource::Factory(0.into()),
+        tile: Tile::Red,
+        destination: Destination::FloorLine,
+    };
+    assert!(game.legal_moves().contains(&mv));
+
+    let outcome = game.apply_move(0, mv).unwrap();
+    assert_eq!(outcome, TurnOutcome::NextPlayer);
+    let floorline = game.get_players()[0].get_buildingarea().get_floorline();
+    assert_eq!(floorline.calculate_minus_points(), 1);
+    assert_eq!(game.get_current_player_id(), 1);
+}
+
+#[test]
+fn test_illegal_moves_leave_the_game_untouched() {
+    let mut game = new_game();
+    let hash = game.zobrist_hash();
+    let mv = Move {
+        source: TileSource::Factory(0.into()),
+        tile: Tile::White,
+        destination: Destination::PatternLine(0),
+    };
+    assert!(game.apply_move(0, mv).is_err());
+    assert_eq!(game.zobrist_hash(), hash);
+    assert_eq!(game.get_current_player_id(), 0);
+}
+
+/// A snapshot of a new game, with one value changed
+fn snapshot_with(pointer: &str, value: Value) -> GameSnapshot {
+    let mut snapshot = serde_json::to_value(new_game().snapshot()).unwrap();
+    *snapshot.pointer_mut(pointer).unwrap() = value;
+    serde_json::from_value(snapshot).unwrap()
+}
+
+#[test]
+fn test_snapshots_bring_the_game_back() {
+    let game = new_game();
+    let restored = Game::<2>::from_snapshot(game.snapshot()).unwrap();
+    assert_eq!(restored.compute_zobrist_hash(), game.compute_zobrist_hash());
+}
+
+#[test_case("/factories", json!(vec![["B", "G", "R", "Y"]; 10]), "10 factories" ; "too many factories")]
+#[test_case("/common_area", json!(vec!["B"; 101]), "101 tiles in the common area" ; "too many tiles in the common area")]
+#[test_case("/players/0/building_area/in_progress/1", json!({"taken": {"tile": "B", "length": 2, "taken": 3}}), "pattern line 2" ; "too many tiles in a pattern line")]
+#[test_case("/players/0/building_area/in_progress/4", json!({"free": {"length": 9}}), "pattern line 5" ; "pattern line too long")]
+#[test_case("/players/1/building_area/in_progress/0", json!({"taken": {"tile": "1", "length": 1, "taken": 1}}), "pattern line 1 of Bob" ; "first player tile in a pattern line")]
+#[test_case("/players/0/building_area/wall/slots/0/0", json!({"free": "R"}), "out of place" ; "wall out of place")]
+fn test_snapshots_out_of_range_are_refused(pointer: &str, value: Value, expected: &str) {
+    let error = Game::<2>::from_snapshot(snapshot_with(pointer, value))
+        .err()
+        .unwrap();
+    assert!(error.contains(expected), "{}", error);
+}
+
+/// Plays the first legal move until the game is over, and returns how many moves it took
+fn play_to_the_end<const N: usize>(factories: usize, seed: u64) -> usize {
+    let players = std::array::from_fn(|i| Player::default_with_name(format!("Player {}", i)));
+    let mut game = Game::<N>::with_rules(players, Bag::seeded(seed), Rules { factories });
+    let mut moves = 0;
+    while !game.is_over() {
+        let mv = game.legal_moves()[0];
+        game.apply_move(game.get_current_player_id(), mv).unwrap();
+        moves += 1;
+        assert!(moves < 10_000, "The game never ends");
+    }
+    moves
+}
+
+#[test_case(0 ; "first seed")]
+#[test_case(1 ; "second seed")]
+#[test_case(2 ; "third seed")]
+fn test_games_with_the_most_factories_run_out_of_tiles_without_panicking(seed: u64) {
+    play_to_the_end::<2>(5, seed);
+    play_to_the_end::<3>(7, seed);
+    play_to_the_end::<4>(9, seed);
+}
+
+#[test_case(2, 5, true ; "five factories for two players")]
+#[test_case(2, 6, false ; "six factories for two players")]
+#[test_case(3, 7, true ; "seven factories for three players")]
+#[test_case(3, 8, false ; "eight factories for three players")]
+#[test_case(4, 9, true ; "nine factories for four players")]
+#[test_case(4, 0, false ; "no factories")]
+fn test_the_factories_depend_on_the_players(players: usize, factories: usize, allowed: bool) {
+    assert_eq!(Rules { factories }.check(players).is_ok(), allowed);
+}
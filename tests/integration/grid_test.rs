@@ -0,0 +1,210 @@
+use azulrs::visor::{
+    backend::TestBackend,
+    grid::Grid,
+    layout::Layout,
+    renderer::RootedRenderer,
+    theme::Theme,
+    view::{PanelBuilder, TextView},
+    Component, Engine,
+};
+use std::{cell::Cell, rc::Rc};
+
+use crate::helpers::{assert_dimensions, expect_component, expect_component_in, to_textviews};
+use test_case::test_case;
+
+fn panel(text: &str) -> Box<dyn Component> {
+    PanelBuilder::default()
+        .component(Box::new(TextView::from(text)))
+        .build()
+        .unwrap()
+        .into()
+}
+
+fn table() -> Grid<'static> {
+    Grid::from_rows(vec![
+        to_textviews(["Alice", "12", "x"]),
+        to_textviews(["Bob", "7", "xx"]),
+        to_textviews(["Charlotte", "103", "x"]),
+    ])
+}
+
+#[test]
+fn test_columns_are_as_wide_as_their_widest_cell() {
+    expect_component(
+        table().with_spacing(1, 0),
+        "
+Alice     12  x
+Bob       7   xx
+Charlotte 103 x"
+            .trim(),
+    );
+}
+
+#[test]
+fn test_rows_are_as_high_as_their_highest_cell() {
+    let grid = Grid::from_rows(vec![
+        to_textviews(["One", "Two\nlines"]),
+        to_textviews(["Three\nmore\nlines", "Four"]),
+    ])
+    .with_spacing(1, 1);
+    expect_component(
+        grid,
+        "
+One   Two
+      lines
+
+Three Four
+more
+lines"
+            .trim(),
+    );
+}
+
+#[test_case(0, 0, (8, 2))]
+#[test_case(1, 0, (10, 2))]
+#[test_case(0, 1, (8, 3))]
+#[test_case(2, 3, (12, 5))]
+fn test_grid_dimensions(columns: u16, rows: u16, expected: (u16, u16)) {
+    let grid = Grid::from_rows(vec![
+        to_textviews(["Hi", "you", "all"]),
+        to_textviews(["a", "b", "c"]),
+    ])
+    .with_spacing(columns, rows);
+    assert_dimensions(&grid, expected);
+}
+
+#[test]
+fn test_empty_grids_take_no_room() {
+    assert_dimensions(&Grid::new(), (0, 0));
+}
+
+#[test]
+fn test_cells_can_be_left_empty() {
+    let grid = Grid::new()
+        .cell(0, 0, TextView::from("a"))
+        .cell(1, 2, TextView::from("c"))
+        .with_spacing(1, 0);
+    expect_component(grid, "a\n   c");
+}
+
+#[test]
+fn test_panels_fill_their_cells() {
+    let grid = Grid::from_rows(vec![
+        vec![panel("Hi"), panel("Hello")],
+        vec![panel("Greetings"), panel("Yo")],
+    ]);
+    expect_component(
+        grid,
+        "
+┌─────────┐┌─────┐
+│Hi       ││Hello│
+└─────────┘└─────┘
+┌─────────┐┌─────┐
+│Greetings││Yo   │
+└─────────┘└─────┘"
+            .trim(),
+    );
+}
+
+#[test]
+fn test_cells_can_span_columns() {
+    let grid = Grid::new()
+        .spanning(0, 0, (1, 2), panel("Scoreboard table"))
+        .cell(1, 0, panel("Alice"))
+        .cell(1, 1, panel("Bob"));
+    expect_component(
+        grid,
+        "
+┌────────────────┐
+│Scoreboard table│
+└────────────────┘
+┌────────┐┌──────┐
+│Alice   ││Bob   │
+└────────┘└──────┘"
+            .trim(),
+    );
+}
+
+#[test]
+fn test_wide_cells_share_what_they_need_between_their_columns() {
+    let grid = Grid::new()
+        .spanning(0, 0, (1, 2), TextView::from("0123456789"))
+        .cell(1, 0, TextView::from("a"))
+        .cell(1, 1, TextView::from("b"))
+        .with_spacing(2, 0);
+    // The two columns need 6 more on top of the spacing, 3 each
+    expect_component(grid, "0123456789\na     b");
+}
+
+#[test]
+fn test_cells_can_span_rows() {
+    // The first row takes the line the two rows miss
+    let grid = Grid::new()
+        .spanning(0, 0, (2, 1), panel("Wall"))
+        .cell(0, 1, TextView::from("one"))
+        .cell(1, 1, TextView::from("two"));
+    expect_component(
+        grid,
+        "
+┌────┐one
+│Wall│
+└────┘two"
+            .trim(),
+    );
+
+    let tall = Grid::new()
+        .spanning(0, 0, (2, 1), TextView::from("1\n2\n3\n4"))
+        .cell(0, 1, TextView::from("a"))
+        .cell(1, 1, TextView::from("b"));
+    // The rows need 4 lines between them, 2 each
+    expect_component(tall, "1a\n2\n3b\n4");
+}
+
+#[test]
+fn test_grids_go_in_layouts() {
+    let layout = Layout::vertical(vec![
+        TextView::from("Scores").into(),
+        table().with_spacing(1, 0).into(),
+    ]);
+    expect_component(
+        layout,
+        "
+Scores
+Alice     12  x
+Bob       7   xx
+Charlotte 103 x"
+            .trim(),
+    );
+}
+
+#[test]
+fn test_grids_draw_with_the_theme() {
+    let grid = Grid::from_rows(vec![vec![panel("a"), panel("bc")]]);
+    expect_component_in(Theme::ascii(), grid, "+-++--+\n|a||bc|\n+-++--+");
+}
+
+/// Counts how many times it is measured
+struct Counted(Rc<Cell<usize>>);
+
+impl Component for Counted {
+    fn render(&self, writer: &mut RootedRenderer) {
+        writer.write("c");
+    }
+
+    fn measure(&self, _renderer: &RootedRenderer, _available: (u16, u16)) -> (u16, u16) {
+        self.0.set(self.0.get() + 1);
+        (1, 1)
+    }
+}
+
+#[test]
+fn test_cells_are_measured_once_per_frame() {
+    let count = Rc::new(Cell::new(0));
+    let grid = Grid::new()
+        .cell(0, 0, Box::new(Counted(count.clone())) as Box<dyn Component>)
+        .cell(0, 1, TextView::from("x"));
+    let mut engine = Engine::new(TestBackend::with_size(10, 10), grid);
+    engine.render();
+    assert_eq!(engine.get_contents(), "cx");
+    assert_eq!(count.get(), 1);
+}
@@ -1,5 +1,6 @@
 use azulrs::visor::{
-    backend::TestBackend, renderer::RootedRenderer, view::TextView, Component, Coords, Engine,
+    backend::TestBackend, renderer::RootedRenderer, theme::Theme, view::TextView, Component,
+    Coords, Engine,
 };
 
 pub fn to_textviews<const N: usize>(data: [&str; N]) -> Vec<Box<dyn Component>> {
@@ -11,8 +12,17 @@ pub fn to_textviews<const N: usize>(data: [&str; N]) -> Vec<Box<dyn Component>>
 
 #[track_caller]
 pub fn expect_component<'a, T: Into<Box<dyn Component + 'a>>>(component: T, expected: &str) {
+    expect_component_in(Theme::default(), component, expected);
+}
+
+#[track_caller]
+pub fn expect_component_in<'a, T: Into<Box<dyn Component + 'a>>>(
+    theme: Theme,
+    component: T,
+    expected: &str,
+) {
     let backend = TestBackend::default();
-    let mut engine = Engine::new(backend, component);
+    let mut engine = Engine::new(backend, component).with_theme(theme);
     engine.render();
     let result = engine.get_contents();
     if result != expected {
@@ -0,0 +1,299 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use azulrs::{
+    model::{bag::Bag, player::Player, rules::Rules, Game, Move},
+    net::{
+        http::{ApiError, CreateGame, GameCreated, GameState, HttpApi, PostMove},
+        protocol::GameEvent,
+    },
+};
+use pretty_assertions::assert_eq;
+use serde::{de::DeserializeOwned, Serialize};
+
+fn start_api() -> SocketAddr {
+    let api = HttpApi::bind("127.0.0.1:0").unwrap();
+    let addr = api.local_addr().unwrap();
+    thread::spawn(move || api.run().unwrap());
+    addr
+}
+
+fn send_request(addr: SocketAddr, method: &str, path: &str, body: &str) -> TcpStream {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+    stream
+}
+
+/// Returns the status and the body
+fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut response = String::new();
+    send_request(addr, method, path, body)
+        .read_to_string(&mut response)
+        .unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, body.to_owned())
+}
+
+fn get<T: DeserializeOwned>(addr: SocketAddr, path: &str) -> T {
+    let (status, body) = request(addr, "GET", path, "");
+    assert_eq!(status, 200, "{}", body);
+    serde_json::from_str(&body).unwrap()
+}
+
+fn post<T: Serialize>(addr: SocketAddr, path: &str, body: &T) -> (u16, String) {
+    request(addr, "POST", path, &serde_json::to_string(body).unwrap())
+}
+
+fn error(body: &str) -> String {
+    serde_json::from_str::<ApiError>(body).unwrap().error
+}
+
+fn create_game(addr: SocketAddr, seed: u64) -> usize {
+    let create = CreateGame {
+        players: vec![String::from("Alice"), String::from("Bob")],
+        rules: Rules::default(),
+        seed: Some(seed),
+    };
+    let (status, body) = post(addr, "/games", &create);
+    assert_eq!(status, 201, "{}", body);
+    serde_json::from_str::<GameCreated>(&body).unwrap().id
+}
+
+fn local_game(seed: u64) -> Game<2> {
+    let players = [
+        Player::default_with_name("Alice".into()),
+        Player::default_with_name("Bob".into()),
+    ];
+    Game::with_rules(players, Bag::seeded(seed), Rules::default())
+}
+
+#[test]
+fn test_game_state_is_served() {
+    let addr = start_api();
+    assert_eq!(create_game(addr, 3), 0);
+    assert_eq!(create_game(addr, 3), 1);
+
+    let state: GameState = get(addr, "/games/1");
+    let game = local_game(3);
+    assert_eq!(state.id, 1);
+    assert_eq!(state.hash, game.zobrist_hash());
+    assert_eq!(state.scores, [0, 0]);
+    assert_eq!(state.game.players[1].get_name(), "Bob");
+    assert_eq!(state.game.factories.len(), Rules::default().factories);
+    let moves: Vec<Move> = get(addr, "/games/1/moves");
+    assert_eq!(moves, game.legal_moves());
+}
+
+#[test]
+fn test_moves_are_checked_and_played() {
+    let addr = start_api();
+    let id = create_game(addr, 5);
+    let mut game = local_game(5);
+    let mv = game.legal_moves()[0];
+
+    let (status, body) = post(addr, "/games/0/moves", &PostMove { seat: 1, mv });
+    assert_eq!(
+        (status, error(&body).as_str()),
+        (400, "It is not the turn of seat 1")
+    );
+
+    let (status, body) = post(
+        addr,
+        &format!("/games/{}/moves", id),
+        &PostMove { seat: 0, mv },
+    );
+    assert_eq!(status, 200, "{}", body);
+    game.apply_move(0, mv).unwrap();
+    let state: GameState = serde_json::from_str(&body).unwrap();
+    assert_eq!(state.hash, game.zobrist_hash());
+    assert_eq!(state.game.current_player_id, 1);
+
+    // The factory is empty by now
+    let (status, body) = post(addr, "/games/0/moves", &PostMove { seat: 1, mv });
+    assert_eq!(status, 400);
+    assert!(error(&body).starts_with("You cannot pick from"), "{}", body);
+}
+
+#[test]
+fn test_invalid_requests_are_refused() {
+    let addr = start_api();
+    let (status, body) = request(addr, "GET", "/games/4", "");
+    assert_eq!((status, error(&body).as_str()), (404, "There is no game 4"));
+    let (status, _) = request(addr, "GET", "/nothing", "");
+    assert_eq!(status, 404);
+    let (status, _) = request(addr, "DELETE", "/games", "");
+    assert_eq!(status, 405);
+    let (status, body) = request(addr, "POST", "/games", "{\"players\":");
+    assert_eq!(status, 400);
+    assert!(error(&body).starts_with("Invalid request"), "{}", body);
+
+    let five = CreateGame {
+        players: ["A", "B", "C", "D", "E"].map(String::from).to_vec(),
+        rules: Rules::default(),
+        seed: None,
+    };
+    let (status, body) = post(addr, "/games", &five);
+    assert_eq!(
+        (status, error(&body).as_str()),
+        (400, "A game is for 2 to 4 players, not 5")
+    );
+}
+
+#[test]
+fn test_malformed_requests_are_refused() {
+    let addr = start_api();
+    let raw = |request: String| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        // The server may answer before it has read everything
+        let _ = stream.write_all(request.as_bytes());
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (
+            head.split_whitespace().nth(1).unwrap().to_owned(),
+            error(body),
+        )
+    };
+
+    let bad_length = "POST /games HTTP/1.1\r\nContent-Length: lots\r\n\r\n".to_owned();
+    assert_eq!(
+        raw(bad_length),
+        (String::from("400"), String::from("Invalid Content-Length"))
+    );
+    let long_header = format!(
+        "GET /games/0 HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+        "a".repeat(10_000)
+    );
+    assert_eq!(
+        raw(long_header),
+        (
+            String::from("400"),
+            String::from("The request has a line that is too long")
+        )
+    );
+    let many_headers = format!(
+        "GET /games/0 HTTP/1.1\r\n{}\r\n",
+        "X-Some: thing\r\n".repeat(100)
+    );
+    assert_eq!(
+        raw(many_headers),
+        (
+            String::from("400"),
+            String::from("The request has too many headers")
+        )
+    );
+}
+
+#[test]
+fn test_player_names_are_checked() {
+    let addr = start_api();
+    let create = CreateGame {
+        players: vec![String::from("Alice"), String::from(" ")],
+        rules: Rules::default(),
+        seed: None,
+    };
+    let (status, body) = post(addr, "/games", &create);
+    assert_eq!(
+        (status, error(&body).as_str()),
+        (400, "Names cannot be empty")
+    );
+
+    let create = CreateGame {
+        players: vec![String::from(" Alice\u{7}"), String::from("Bob")],
+        rules: Rules::default(),
+        seed: None,
+    };
+    let (status, body) = post(addr, "/games", &create);
+    assert_eq!(status, 201, "{}", body);
+    let state: GameState = get(addr, "/games/0");
+    assert_eq!(state.game.players[0].get_name(), "Alice");
+}
+
+#[test]
+fn test_events_are_streamed() {
+    let addr = start_api();
+    let id = create_game(addr, 9);
+    let mut events = BufReader::new(send_request(addr, "GET", "/games/0/events", ""));
+    // Once the headers are there, we are subscribed
+    let mut headers = vec![];
+    loop {
+        let mut line = String::new();
+        events.read_line(&mut line).unwrap();
+        if line == "\r\n" {
+            break;
+        }
+        headers.push(line);
+    }
+    assert_eq!(headers[0], "HTTP/1.1 200 OK\r\n");
+    assert!(headers.contains(&String::from("Content-Type: text/event-stream\r\n")));
+
+    let mv = local_game(9).legal_moves()[0];
+    let (status, _) = post(
+        addr,
+        &format!("/games/{}/moves", id),
+        &PostMove { seat: 0, mv },
+    );
+    assert_eq!(status, 200);
+    let mut line = String::new();
+    events.read_line(&mut line).unwrap();
+    let event: GameEvent = serde_json::from_str(line.strip_prefix("data: ").unwrap()).unwrap();
+    assert_eq!(event, GameEvent::MovePlayed { seat: 0, mv });
+}
+
+#[test]
+fn test_event_streams_end_with_the_game() {
+    let addr = start_api();
+    let id = create_game(addr, 11);
+    let mut events = send_request(addr, "GET", &format!("/games/{}/events", id), "");
+    let mut subscribed = [0; 1];
+    events.read_exact(&mut subscribed).unwrap();
+
+    let mut state: GameState = get(addr, "/games/0");
+    loop {
+        let moves: Vec<Move> = get(addr, "/games/0/moves");
+        let Some(&mv) = moves.first() else { break };
+        let seat = state.game.current_player_id;
+        let (status, body) = post(addr, "/games/0/moves", &PostMove { seat, mv });
+        assert_eq!(status, 200, "{}", body);
+        state = serde_json::from_str(&body).unwrap();
+    }
+
+    // The stream ends with the end of the game
+    let mut rest = String::new();
+    events.read_to_string(&mut rest).unwrap();
+    let last = rest.trim_end().lines().last().unwrap();
+    let event: GameEvent = serde_json::from_str(last.strip_prefix("data: ").unwrap()).unwrap();
+    assert_eq!(
+        event,
+        GameEvent::GameOver {
+            scores: state.scores
+        }
+    );
+
+    // Nothing is left to follow in a game that is over
+    let mut late = String::new();
+    send_request(addr, "GET", "/games/0/events", "")
+        .read_to_string(&mut late)
+        .unwrap();
+    assert!(late.starts_with("HTTP/1.1 200 OK"), "{}", late);
+    assert!(late.ends_with("\r\n\r\n"), "{}", late);
+}
@@ -0,0 +1,115 @@
+use azulrs::{
+    model::{
+        bag::Bag,
+        local::{local_players, replay, LocalGame},
+        Direction, Game,
+    },
+    visor::{
+        backend::{TestBackend, UNBOUNDED},
+        input::{InputSource, Recorder, Recording, ReplayInput, ScriptedInput, TerminalInput},
+        Engine, UserInput,
+    },
+};
+use pretty_assertions::assert_eq;
+
+fn drain(mut source: impl InputSource) -> Vec<UserInput> {
+    let mut inputs = vec![];
+    while let Some(input) = source.next_input() {
+        inputs.push(input);
+    }
+    inputs
+}
+
+// Room for the whole game
+const SIZE: (u16, u16) = (200, 60);
+
+/// Plays `source` on a fresh game, and returns the game and the last screen
+fn play(seed: u64, source: &mut dyn InputSource) -> (LocalGame<2>, String) {
+    let game = Game::for_players_with_bag(local_players(), Bag::seeded(seed));
+    let mut local = LocalGame::new(game);
+    let backend = TestBackend::with_size(SIZE.0, SIZE.1);
+    let mut engine = Engine::new(backend, Box::new(local.view()) as Box<_>);
+    local.play(&mut engine, source);
+    let screen = engine.get_contents();
+    (local, screen)
+}
+
+fn record(seed: u64, size: (u16, u16), keys: &str) -> Recording {
+    let mut file = vec![];
+    let recorder = Recorder::new(ScriptedInput::from_keys(keys), &mut file, seed, size).unwrap();
+    assert_eq!(drain(recorder).len(), keys.len());
+    Recording::read(std::str::from_utf8(&file).unwrap()).unwrap()
+}
+
+#[test]
+fn test_terminal_and_scripted_inputs_agree() {
+    let expected = [
+        UserInput::Direction(Direction::Next),
+        UserInput::Direction(Direction::Prev),
+        UserInput::Confirm,
+        UserInput::Exit,
+    ];
+    assert_eq!(drain(TerminalInput::new(&b"jk\nq"[..])), expected);
+    assert_eq!(drain(ScriptedInput::from_keys("jk\nq")), expected);
+    assert_eq!(drain(TerminalInput::new(&b"\x1b"[..])), [UserInput::Back]);
+}
+
+#[test]
+fn test_the_play_loop_runs_headless() {
+    // The first factory, its first tile, then the first pattern line
+    let (local, _) = play(3, &mut ScriptedInput::from_keys("\n\n\n"));
+    assert_eq!(local.game.borrow().get_current_player_id(), 1);
+
+    // Nothing after q is played
+    let (local, _) = play(3, &mut ScriptedInput::from_keys("q\n\n\n"));
+    assert_eq!(local.game.borrow().get_current_player_id(), 0);
+}
+
+#[test]
+fn test_recordings_keep_the_seed_and_the_inputs() {
+    let recording = record(42, (80, 24), "j\n\x7f");
+    assert_eq!(recording.seed, 42);
+    assert_eq!(recording.size, (80, 24));
+    assert_eq!(
+        recording
+            .inputs
+            .iter()
+            .map(|timed| timed.input)
+            .collect::<Vec<_>>(),
+        [
+            UserInput::Direction(Direction::Next),
+            UserInput::Confirm,
+            UserInput::Character('\x7f'),
+        ]
+    );
+    assert!(recording.inputs.windows(2).all(|w| w[0].at <= w[1].at));
+    assert_eq!(drain(ReplayInput::new(&recording)).len(), 3);
+}
+
+#[test]
+fn test_a_replay_shows_the_same_final_screen() {
+    let keys = "j\n\njj\n\n\n";
+    let (_, live) = play(7, &mut ScriptedInput::from_keys(keys));
+    let recording = record(7, SIZE, keys);
+    assert_eq!(replay(&recording), live);
+    // Another seed deals other tiles
+    assert_ne!(replay(&record(8, SIZE, keys)), live);
+    // And the screen is as small as the one the game was recorded on
+    assert!(replay(&record(7, (30, 10), keys)).starts_with("Terminal too small: 30x10"));
+}
+
+#[test]
+fn test_invalid_recordings_are_refused() {
+    assert_eq!(
+        Recording::read(""),
+        Err(String::from("The recording is empty"))
+    );
+    assert!(Recording::read("{\"seed\":1}\n{\"at\":0}")
+        .unwrap_err()
+        .starts_with("Line 2 of the recording is invalid"));
+    // Recordings from before the size was kept play back without bounds
+    assert_eq!(Recording::read("{\"seed\":1}").unwrap().size, UNBOUNDED);
+    assert!(Recording::read("[]")
+        .unwrap_err()
+        .starts_with("The recording has no seed"));
+}
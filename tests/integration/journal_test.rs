@@ -0,0 +1,235 @@
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use azulrs::{
+    model::{rules::Rules, Game},
+    net::{
+        client,
+        journal::{read_entries, Journal, JournalEntry},
+        protocol::{ChatMessage, ClientMessage, GameEvent, RecordedMove},
+        server::Server,
+    },
+};
+use pretty_assertions::assert_eq;
+
+use crate::net_helpers::ScriptedClient;
+
+fn journal_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("azulrs-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// A server that keeps its tables in `dir`. The old servers of a test keep running, but once
+/// their clients are gone they have nothing left to write.
+fn start_server(dir: &Path) -> SocketAddr {
+    start_server_with_grace_period(dir, Duration::from_secs(600))
+}
+
+fn start_server_with_grace_period(dir: &Path, grace_period: Duration) -> SocketAddr {
+    let server = Server::bind("127.0.0.1:0")
+        .unwrap()
+        .with_grace_period(grace_period)
+        .with_journal(dir);
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run().unwrap());
+    addr
+}
+
+fn created() -> JournalEntry {
+    JournalEntry::Created {
+        players: 2,
+        rules: Rules::default(),
+        seed: 7,
+    }
+}
+
+#[test]
+fn test_tables_survive_a_restart() {
+    let dir = journal_dir("restart");
+    let addr = start_server(&dir);
+    let table = client::create_table(addr, 2, Rules::default(), Some(7)).unwrap();
+    let mut alice = ScriptedClient::sit_at(addr, "Alice", Some(table));
+    let mut bob = ScriptedClient::sit_at(addr, "Bob", Some(table));
+    let (_, alice_token) = alice.receive_welcome();
+    let (_, bob_token) = bob.receive_welcome();
+    alice.send(&ClientMessage::Ready);
+    bob.send(&ClientMessage::Ready);
+    let game: Game<2> = alice.receive_state();
+    alice.play(game.legal_moves()[0]);
+    let played: Game<2> = alice.receive_state();
+    alice.send(&ClientMessage::Chat {
+        text: String::from("brb"),
+    });
+    alice.expect_event(GameEvent::Chat(ChatMessage {
+        from: String::from("Alice"),
+        text: String::from("brb"),
+    }));
+    drop((alice, bob));
+
+    let addr = start_server(&dir);
+    let tables = client::list_tables(addr).unwrap();
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].seats, ["Alice", "Bob"]);
+    assert!(tables[0].started);
+
+    let mut alice = ScriptedClient::resume(addr, &alice_token);
+    let restored: Game<2> = alice.receive_state();
+    assert_eq!(restored.zobrist_hash(), played.zobrist_hash());
+    alice.expect_event(GameEvent::PlayerDisconnected { seat: 1 });
+
+    let mut bob = ScriptedClient::resume(addr, &bob_token);
+    let _: Game<2> = bob.receive_state();
+    alice.expect_event(GameEvent::PlayerReconnected { seat: 1 });
+    let mv = restored.legal_moves()[0];
+    bob.play(mv);
+    let _: Game<2> = bob.receive_state();
+
+    // The game goes on in the same journal
+    let entries = Journal::open(&dir).unwrap().load().unwrap();
+    assert_eq!(entries.len(), 1);
+    let entries = &entries[0].1.as_ref().unwrap().entries;
+    assert_eq!(entries.len(), 5);
+    assert_eq!(entries[4], JournalEntry::Move(RecordedMove { seat: 1, mv }));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_finished_tables_survive_a_restart() {
+    let dir = journal_dir("archive");
+    // Without a grace period the table is given up as soon as a player leaves
+    let addr = start_server_with_grace_period(&dir, Duration::ZERO);
+    let table = client::create_table(addr, 2, Rules::default(), Some(7)).unwrap();
+    let mut alice = ScriptedClient::sit_at(addr, "Alice", Some(table));
+    let mut bob = ScriptedClient::sit_at(addr, "Bob", Some(table));
+    alice.send(&ClientMessage::Ready);
+    bob.send(&ClientMessage::Ready);
+    let game: Game<2> = alice.receive_state();
+    alice.play(game.legal_moves()[0]);
+    let _: Game<2> = alice.receive_state();
+    drop(bob);
+    assert!(alice.is_closed());
+    let archive = client::list_archive(addr).unwrap();
+    assert_eq!(archive.len(), 1);
+    assert!(!Journal::open(&dir).unwrap().path(table).exists());
+
+    let addr = start_server(&dir);
+    assert!(client::list_tables(addr).unwrap().is_empty());
+    assert_eq!(client::list_archive(addr).unwrap(), archive);
+    // The id of the finished table is not given out again
+    let next = client::create_table(addr, 2, Rules::default(), None).unwrap();
+    assert_eq!(next, table + 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_damaged_journals_are_reported() {
+    let dir = journal_dir("damaged");
+    let journal = Journal::open(&dir).unwrap();
+    journal.create(0, &created()).unwrap();
+    journal.create(1, &created()).unwrap();
+    journal.create(2, &created()).unwrap();
+    journal.create(3, &created()).unwrap();
+
+    let truncated = fs::read_to_string(journal.path(1)).unwrap();
+    fs::write(journal.path(1), &truncated[..truncated.len() - 5]).unwrap();
+    let tampered = fs::read_to_string(journal.path(2)).unwrap();
+    fs::write(
+        journal.path(2),
+        tampered.replace("\"seed\":7", "\"seed\":8"),
+    )
+    .unwrap();
+    fs::write(journal.path(3), "{not json\n").unwrap();
+
+    let loaded = journal.load().unwrap();
+    let problems: Vec<_> = loaded
+        .iter()
+        .map(|(table, entries)| (*table, entries.as_ref().err().cloned()))
+        .collect();
+    assert_eq!(problems[0], (0, None));
+    assert_eq!(
+        problems[1],
+        (1, Some(String::from("The journal is truncated at line 1")))
+    );
+    assert_eq!(
+        problems[2],
+        (
+            2,
+            Some(String::from(
+                "Line 1 of the journal does not match its checksum"
+            ))
+        )
+    );
+    assert!(problems[3]
+        .1
+        .as_ref()
+        .unwrap()
+        .starts_with("Line 1 of the journal is corrupt"));
+
+    // Only the healthy table comes back, and new tables do not overwrite the broken ones
+    let addr = start_server(&dir);
+    let tables = client::list_tables(addr).unwrap();
+    assert_eq!(tables.iter().map(|t| t.id).collect::<Vec<_>>(), [0]);
+    let table = client::create_table(addr, 2, Rules::default(), None).unwrap();
+    assert_eq!(table, 4);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_a_cut_off_last_entry_only_loses_that_entry() {
+    let dir = journal_dir("cut-off");
+    let journal = Journal::open(&dir).unwrap();
+    let mut file = journal.create(0, &created()).unwrap();
+    let hi = JournalEntry::Chat(ChatMessage {
+        from: String::from("Alice"),
+        text: String::from("hi"),
+    });
+    file.append(&hi).unwrap();
+    file.append(&hi).unwrap();
+    drop(file);
+    let contents = fs::read_to_string(journal.path(0)).unwrap();
+    fs::write(journal.path(0), &contents[..contents.len() - 5]).unwrap();
+
+    let loaded = journal.load().unwrap();
+    let read = loaded[0].1.as_ref().unwrap();
+    assert_eq!(read.entries, [created(), hi.clone()]);
+    assert_eq!(
+        read.cut_off.as_deref(),
+        Some("The last entry of the journal, on line 3, was cut off")
+    );
+
+    // The next entry starts on a line of its own
+    journal.reopen(0).unwrap().append(&hi).unwrap();
+    let loaded = journal.load().unwrap();
+    let read = loaded[0].1.as_ref().unwrap();
+    assert_eq!(read.entries, [created(), hi.clone(), hi]);
+    assert_eq!(read.cut_off, None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_journal_must_start_with_the_table() {
+    assert_eq!(read_entries("").unwrap_err(), "The journal is empty");
+    let dir = journal_dir("start");
+    let journal = Journal::open(&dir).unwrap();
+    let mut file = journal.create(0, &created()).unwrap();
+    file.append(&JournalEntry::Chat(ChatMessage {
+        from: String::from("Alice"),
+        text: String::from("hi"),
+    }))
+    .unwrap();
+    let contents = fs::read_to_string(journal.path(0)).unwrap();
+    assert_eq!(read_entries(&contents).unwrap().entries.len(), 2);
+
+    let chat_only = contents.lines().nth(1).unwrap().to_owned() + "\n";
+    assert_eq!(
+        read_entries(&chat_only).unwrap_err(),
+        "The journal does not start with the table"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
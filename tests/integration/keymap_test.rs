@@ -0,0 +1,154 @@
+use std::time::Instant;
+
+use azulrs::{
+    model::{
+        bag::Bag,
+        buildingarea::Destination,
+        local::{local_players, LocalGame},
+        Direction, Game, TileSource,
+    },
+    visor::{
+        backend::TestBackend,
+        keymap::{parse_key, Action, Keymap},
+        Engine, UserInput,
+    },
+};
+use pretty_assertions::assert_eq;
+use termion::event::Key;
+
+fn game() -> Game<2> {
+    Game::for_players_with_bag(local_players(), Bag::seeded(5))
+}
+
+#[test]
+fn test_arrows_vim_keys_and_wasd_all_move() {
+    let keymap = Keymap::default();
+    let next = UserInput::Direction(Direction::Next);
+    let prev = UserInput::Direction(Direction::Prev);
+    for key in [
+        Key::Down,
+        Key::Right,
+        Key::Char('j'),
+        Key::Char('s'),
+        Key::Char('d'),
+    ] {
+        assert_eq!(keymap.map(key), next);
+    }
+    for key in [
+        Key::Up,
+        Key::Left,
+        Key::Char('k'),
+        Key::Char('w'),
+        Key::Char('a'),
+    ] {
+        assert_eq!(keymap.map(key), prev);
+    }
+    assert_eq!(keymap.map(Key::Char('\n')), UserInput::Confirm);
+    assert_eq!(keymap.map(Key::Char('?')), UserInput::Help);
+    assert_eq!(keymap.map(Key::Char('3')), UserInput::Select(3));
+    assert_eq!(keymap.map(Key::Char('x')), UserInput::Character('x'));
+    assert_eq!(keymap.map(Key::F(1)), UserInput::Noop);
+}
+
+#[test]
+fn test_a_config_file_replaces_the_keys_of_an_action() {
+    let keymap = Keymap::from_json(r#"{"quit": ["x", "esc"], "back": ["backspace"]}"#).unwrap();
+    assert_eq!(keymap.map(Key::Char('x')), UserInput::Exit);
+    assert_eq!(keymap.map(Key::Esc), UserInput::Exit);
+    assert_eq!(keymap.map(Key::Char('q')), UserInput::Character('q'));
+    // The actions left out keep their keys
+    assert_eq!(
+        keymap.map(Key::Char('j')),
+        UserInput::Direction(Direction::Next)
+    );
+    assert_eq!(keymap.keys(Action::Back), [Key::Backspace]);
+}
+
+#[test]
+fn test_invalid_configs_are_refused() {
+    assert_eq!(
+        Keymap::from_json(r#"{"quit": ["ctrl-q"]}"#),
+        Err(String::from("Unknown key ctrl-q"))
+    );
+    assert_eq!(
+        Keymap::from_json(r#"{"quit": ["j"]}"#),
+        Err(String::from("The key j is bound to both Next and Quit"))
+    );
+    assert_eq!(
+        Keymap::from_json(r#"{"help": ["1"]}"#),
+        Err(String::from(
+            "The digit 1 is a shortcut, it cannot be bound"
+        ))
+    );
+    assert!(Keymap::from_json(r#"{"jump": ["x"]}"#)
+        .unwrap_err()
+        .starts_with("Invalid keymap"));
+    assert_eq!(parse_key("backtab"), Ok(Key::BackTab));
+}
+
+#[test]
+fn test_the_help_follows_the_keymap() {
+    let help = Keymap::from_json(r#"{"quit": ["x"]}"#).unwrap().help();
+    assert_eq!(help.len(), 7);
+    assert_eq!(help[0], "Move the selection down j, down, right, s, d, tab");
+    assert_eq!(help[4], "Quit                    x");
+}
+
+#[test]
+fn test_digits_jump_to_a_factory_and_a_row() {
+    let mut game = game();
+    game.handle(UserInput::Select(3));
+    game.handle(UserInput::Confirm);
+    game.handle(UserInput::Confirm);
+    let mv = game.selected_move().unwrap();
+    assert_eq!(mv.source, TileSource::Factory(2.into()));
+
+    game.handle(UserInput::Select(2));
+    assert_eq!(
+        game.selected_move().unwrap().destination,
+        Destination::PatternLine(1)
+    );
+    game.handle(UserInput::Select(0));
+    assert_eq!(
+        game.selected_move().unwrap().destination,
+        Destination::FloorLine
+    );
+    // There is no sixth pattern line, nothing changes
+    game.handle(UserInput::Select(6));
+    assert_eq!(
+        game.selected_move().unwrap().destination,
+        Destination::FloorLine
+    );
+}
+
+#[test]
+fn test_digits_skip_empty_sources() {
+    let mut game = game();
+    // The common area has no tiles yet, and there is no ninth factory
+    game.handle(UserInput::Select(0));
+    game.handle(UserInput::Select(9));
+    game.handle(UserInput::Confirm);
+    game.handle(UserInput::Confirm);
+    assert_eq!(
+        game.selected_move().unwrap().source,
+        TileSource::Factory(0.into())
+    );
+}
+
+#[test]
+fn test_the_help_screen_toggles() {
+    let mut local = LocalGame::new(game());
+    let mut engine = Engine::new(TestBackend::default(), Box::new(local.view()) as Box<_>);
+    engine.render();
+    assert!(!engine.get_contents().contains("Keys"));
+
+    local.handle_input(UserInput::Help, Instant::now());
+    engine.render();
+    let screen = engine.get_contents();
+    assert!(screen.contains("Keys"));
+    assert!(screen.contains("Move the selection down"));
+
+    local.handle_input(UserInput::Help, Instant::now());
+    engine.render();
+    assert!(!engine.get_contents().contains("Keys"));
+}
@@ -1,7 +1,11 @@
 use azulrs::visor::{
-    backend::TestBackend, layout::Layout, renderer::RootedRenderer, view::TextView, Component,
-    Coords,
+    backend::{DebuggableTerminalBackend, TestBackend},
+    layout::{Align, Arrangement, Constraint, Direction, Layout},
+    renderer::RootedRenderer,
+    view::{PanelBuilder, TextView},
+    Component, Coords, Engine,
 };
+use std::{cell::Cell, rc::Rc};
 
 use crate::helpers::{assert_dimensions, expect_component, to_textviews};
 use test_case::test_case;
@@ -89,3 +93,177 @@ h1h2
 
     expect_component(layout, expected);
 }
+
+/// Counts how often it is measured
+struct MeasureCount(Rc<Cell<usize>>);
+
+impl Component for MeasureCount {
+    fn render(&self, writer: &mut RootedRenderer) {
+        writer.write("x");
+    }
+
+    fn measure(&self, _renderer: &RootedRenderer, _available: (u16, u16)) -> (u16, u16) {
+        self.0.set(self.0.get() + 1);
+        (1, 1)
+    }
+}
+
+#[test]
+fn test_layouts_are_arranged_before_drawing() {
+    let layout = Layout::horizontal(to_textviews(["Hello", "Bye\nWorld", "!"]));
+    let mut backend = TestBackend::default();
+    let writer = RootedRenderer::default_with_writer(&mut backend);
+    let arrangement = layout.arrange(&writer, (80, 24), (0, 0));
+    assert_eq!(
+        arrangement,
+        Arrangement {
+            direction: Direction::Horizontal,
+            roots: vec![Coords::new(0, 0), Coords::new(5, 0), Coords::new(10, 0)],
+            sizes: vec![(5, 1), (5, 2), (1, 1)],
+            size: (11, 2),
+        }
+    );
+    assert_eq!(backend.get_contents(), "");
+}
+
+#[test]
+fn test_measuring_matches_drawing() {
+    let panel = PanelBuilder::default()
+        .name("A long name")
+        .padding(1)
+        .component(Box::new(Layout::vertical(vec![
+            Box::new(TextView::from("v1\n\nv3")),
+            Box::new(Layout::horizontal(to_textviews(["h1", "h2"]))),
+        ])))
+        .build()
+        .unwrap();
+    let mut backend = TestBackend::default();
+    let writer = RootedRenderer::default_with_writer(&mut backend);
+    assert_eq!(writer.measure(&panel), (18, 8));
+    assert_dimensions(&panel, (18, 8));
+}
+
+#[test_case(1)]
+#[test_case(8)]
+fn test_sizes_are_measured_once_per_frame(depth: usize) {
+    let count = Rc::new(Cell::new(0));
+    let mut component: Box<dyn Component> = Box::new(MeasureCount(count.clone()));
+    for _ in 0..depth {
+        component = Box::new(Layout::fitting(vec![component]));
+    }
+    let mut engine = Engine::new(TestBackend::with_size(80, 24), component);
+    engine.render();
+    assert_eq!(engine.get_contents(), "x");
+    assert_eq!(count.get(), 1);
+
+    engine.render();
+    assert_eq!(count.get(), 2);
+}
+
+fn panel(text: &str) -> Box<dyn Component> {
+    let panel = PanelBuilder::default()
+        .component(Box::new(TextView::from(text)))
+        .build()
+        .unwrap();
+    Box::new(panel)
+}
+
+#[test_case(Layout::horizontal, "a b c", (5, 1))]
+#[test_case(Layout::vertical, "a\n\nb\n\nc", (1, 5))]
+fn test_spacing(
+    layout: fn(Vec<Box<dyn Component>>) -> Layout<'static>,
+    expected: &str,
+    dimensions: (u16, u16),
+) {
+    let layout = layout(to_textviews(["a", "b", "c"])).with_spacing(1);
+    assert_dimensions(&layout, dimensions);
+    expect_component(layout, expected);
+}
+
+#[test]
+fn test_constraints() {
+    let layout = Layout::horizontal(to_textviews(["ab", "cd"]))
+        .with_constraints(vec![Constraint::Min(6), Constraint::Auto]);
+    expect_component(layout, "ab    cd");
+
+    let layout = Layout::horizontal(to_textviews(["Hello", "World", "!"])).with_constraints(vec![
+        Constraint::Max(3),
+        Constraint::Fixed(7),
+        Constraint::Max(3),
+    ]);
+    let mut backend = TestBackend::default();
+    let writer = RootedRenderer::default_with_writer(&mut backend);
+    let arrangement = layout.arrange(&writer, (80, 24), (0, 0));
+    assert_eq!(arrangement.sizes, vec![(3, 1), (7, 1), (1, 1)]);
+    assert_eq!(arrangement.roots[2], Coords::new(10, 0));
+    assert_eq!(arrangement.size, (11, 1));
+}
+
+#[test]
+fn test_constraints_clip_what_does_not_fit() {
+    let layout =
+        Layout::horizontal(to_textviews(["Hello"])).with_constraints(vec![Constraint::Max(3)]);
+    let panel = PanelBuilder::default()
+        .component(Box::new(layout))
+        .build()
+        .unwrap();
+    let expected = r#"
+┌───┐
+│Hel│
+└───┘"#;
+    expect_component(panel, expected.trim());
+}
+
+#[test]
+fn test_filling_components_share_the_room() {
+    let row = Layout::horizontal(vec![panel("a"), panel("bbb")])
+        .with_constraints(vec![Constraint::Fill(1), Constraint::Fill(1)]);
+    // Without room to fill, they are as big as each other
+    expect_component(row, "┌───┐┌───┐\n│a  ││bbb│\n└───┘└───┘");
+
+    let row = Layout::horizontal(vec![panel("a"), panel("bbb")])
+        .with_constraints(vec![Constraint::Fill(1), Constraint::Fill(1)]);
+    let layout = Layout::vertical(vec![
+        Box::new(row),
+        Box::new(TextView::from("0123456789012")),
+    ])
+    .with_align(Align::Stretch);
+    let expected = r#"
+┌─────┐┌────┐
+│a    ││bbb │
+└─────┘└────┘
+0123456789012"#;
+    expect_component(layout, expected.trim());
+
+    let row = Layout::horizontal(vec![panel("a"), panel("b")])
+        .with_constraints(vec![Constraint::Fill(2), Constraint::Fill(1)]);
+    let layout = Layout::vertical(vec![Box::new(row), Box::new(TextView::from("0123456789"))])
+        .with_align(Align::Stretch);
+    let expected = r#"
+┌─────┐┌─┐
+│a    ││b│
+└─────┘└─┘
+0123456789"#;
+    expect_component(layout, expected.trim());
+}
+
+#[test_case(Align::Start, "Hello\nab")]
+#[test_case(Align::Center, "Hello\n ab")]
+#[test_case(Align::End, "Hello\n   ab")]
+fn test_alignment(align: Align, expected: &str) {
+    let layout = Layout::vertical(to_textviews(["Hello", "ab"])).with_align(align);
+    expect_component(layout, expected);
+}
+
+#[test]
+fn test_stretched_panels() {
+    let layout =
+        Layout::horizontal(vec![panel("Hello\nWorld\n!"), panel("Hi")]).with_align(Align::Stretch);
+    let expected = r#"
+┌─────┐┌──┐
+│Hello││Hi│
+│World││  │
+│!    ││  │
+└─────┘└──┘"#;
+    expect_component(layout, expected.trim());
+}
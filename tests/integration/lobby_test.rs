@@ -0,0 +1,193 @@
+use std::{net::SocketAddr, thread, time::Duration};
+
+use azulrs::{
+    model::{bag::Bag, player::Player, rules::Rules, Game},
+    net::{
+        client,
+        protocol::{ClientMessage, GameEvent},
+        server::Server,
+    },
+};
+use pretty_assertions::assert_eq;
+
+use crate::net_helpers::ScriptedClient;
+
+fn start_lobby(grace_period: Duration) -> SocketAddr {
+    let server = Server::bind("127.0.0.1:0")
+        .unwrap()
+        .with_grace_period(grace_period);
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run().unwrap());
+    addr
+}
+
+fn start_table(addr: SocketAddr, table: usize) -> [ScriptedClient; 2] {
+    let mut alice = ScriptedClient::sit_at(addr, "Alice", Some(table));
+    let mut bob = ScriptedClient::sit_at(addr, "Bob", Some(table));
+    alice.send(&ClientMessage::Ready);
+    bob.send(&ClientMessage::Ready);
+    [alice, bob]
+}
+
+#[test]
+fn test_tables_are_listed() {
+    let addr = start_lobby(Duration::from_secs(60));
+    let rules = Rules { factories: 7 };
+    let table = client::create_table(addr, 3, rules, None).unwrap();
+    let _alice = ScriptedClient::sit_at(addr, "Alice", Some(table));
+
+    let tables = client::list_tables(addr).unwrap();
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].id, table);
+    assert_eq!(tables[0].players, 3);
+    assert_eq!(tables[0].seats, ["Alice"]);
+    assert_eq!(tables[0].rules, rules);
+    assert!(tables[0].has_free_seat());
+}
+
+#[test]
+fn test_invalid_tables_are_rejected() {
+    let addr = start_lobby(Duration::from_secs(60));
+    let error = client::create_table(addr, 5, Rules::default(), None).unwrap_err();
+    assert_eq!(error.to_string(), "A game is for 2 to 4 players, not 5");
+    let error = client::create_table(addr, 2, Rules { factories: 0 }, None).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "A game for 2 players needs between 1 and 5 factories, not 0"
+    );
+    assert!(client::list_tables(addr).unwrap().is_empty());
+}
+
+#[test]
+fn test_joining_needs_a_table() {
+    let addr = start_lobby(Duration::from_secs(60));
+    let mut alice = ScriptedClient::join_table(addr, "Alice", None);
+    assert_eq!(alice.receive_error(), "There is no open table");
+    let mut alice = ScriptedClient::join_table(addr, "Alice", Some(9));
+    assert_eq!(alice.receive_error(), "There is no table 9");
+    alice.send(&ClientMessage::Ready);
+    assert_eq!(alice.receive_error(), "You have not joined a table");
+}
+
+#[test]
+fn test_game_starts_when_everyone_is_ready() {
+    let addr = start_lobby(Duration::from_secs(60));
+    let table = client::create_table(addr, 2, Rules::default(), None).unwrap();
+    let mut alice = ScriptedClient::sit_at(addr, "Alice", Some(table));
+    let mut bob = ScriptedClient::sit_at(addr, "Bob", Some(table));
+
+    alice.send(&ClientMessage::Ready);
+    bob.expect_event(GameEvent::PlayerReady { seat: 0 });
+    let game: Game<2> = Game::for_players([
+        Player::default_with_name("Alice".into()),
+        Player::default_with_name("Bob".into()),
+    ]);
+    let mv = game.legal_moves()[0];
+    alice.play(mv);
+    assert_eq!(alice.receive_refusal(mv), "The game has not started yet");
+
+    bob.send(&ClientMessage::Ready);
+    alice.expect_event(GameEvent::GameStarted);
+    let game: Game<2> = alice.receive_state();
+    assert_eq!(game.get_current_player_id(), 0);
+}
+
+#[test]
+fn test_players_keep_their_seats_when_somebody_leaves() {
+    let addr = start_lobby(Duration::from_secs(60));
+    let table = client::create_table(addr, 3, Rules { factories: 7 }, None).unwrap();
+    let alice = ScriptedClient::sit_at(addr, "Alice", Some(table));
+    let mut bob = ScriptedClient::sit_at(addr, "Bob", Some(table));
+    drop(alice);
+    bob.expect_event(GameEvent::PlayerLeft { seat: 0 });
+
+    let mut carol = ScriptedClient::sit_at(addr, "Carol", Some(table));
+    assert_eq!(carol.receive_welcome().0, 0);
+    let mut dave = ScriptedClient::sit_at(addr, "Dave", Some(table));
+    assert_eq!(dave.receive_welcome().0, 2);
+
+    for client in [&mut bob, &mut carol, &mut dave] {
+        client.send(&ClientMessage::Ready);
+    }
+    let game: Game<3> = bob.receive_state();
+    let names: Vec<_> = game.get_players().iter().map(Player::get_name).collect();
+    assert_eq!(names, ["Carol", "Bob", "Dave"]);
+}
+
+#[test]
+fn test_tables_are_independent() {
+    let addr = start_lobby(Duration::from_secs(60));
+    let first = client::create_table(addr, 2, Rules::default(), None).unwrap();
+    let second = client::create_table(addr, 2, Rules { factories: 5 }, None).unwrap();
+    let [mut alice, mut bob] = start_table(addr, first);
+    let [mut carol, _dave] = start_table(addr, second);
+
+    let mut eve = ScriptedClient::join_table(addr, "Eve", Some(first));
+    assert_eq!(eve.receive_error(), "The table is full");
+
+    let game: Game<2> = alice.receive_state();
+    let other: Game<2> = carol.receive_state();
+    assert_eq!(game.get_factories().len(), 4);
+    assert_eq!(other.get_factories().len(), 5);
+
+    alice.play(game.legal_moves()[0]);
+    let _: Game<2> = bob.receive_state();
+    let game: Game<2> = bob.receive_state();
+    assert_eq!(game.get_current_player_id(), 1);
+
+    // Carol's table is still at the first move
+    carol.play(other.legal_moves()[0]);
+    let other: Game<2> = carol.receive_state();
+    assert_eq!(other.get_current_player_id(), 1);
+}
+
+#[test]
+fn test_same_seed_deals_the_same_game() {
+    let addr = start_lobby(Duration::from_secs(60));
+    let first = client::create_table(addr, 2, Rules::default(), Some(42)).unwrap();
+    let second = client::create_table(addr, 2, Rules::default(), Some(42)).unwrap();
+    let [mut alice, _bob] = start_table(addr, first);
+    let [mut carol, _dave] = start_table(addr, second);
+
+    let game: Game<2> = alice.receive_state();
+    let other: Game<2> = carol.receive_state();
+    assert_eq!(game.zobrist_hash(), other.zobrist_hash());
+}
+
+#[test]
+fn test_finished_tables_are_archived() {
+    // Without a grace period the table is given up as soon as a player leaves
+    let addr = start_lobby(Duration::ZERO);
+    let rules = Rules { factories: 5 };
+    let table = client::create_table(addr, 2, rules, Some(7)).unwrap();
+    let [mut alice, bob] = start_table(addr, table);
+    let game: Game<2> = alice.receive_state();
+    let mv = game.legal_moves()[0];
+    alice.play(mv);
+    let played: Game<2> = alice.receive_state();
+
+    drop(bob);
+    assert!(alice.is_closed());
+    assert!(client::list_tables(addr).unwrap().is_empty());
+
+    let archive = client::list_archive(addr).unwrap();
+    assert_eq!(archive.len(), 1);
+    let archived = &archive[0];
+    assert_eq!(archived.id, table);
+    assert_eq!(archived.players, ["Alice", "Bob"]);
+    assert_eq!(archived.seed, 7);
+    assert!(archived.abandoned);
+    assert_eq!(archived.moves.len(), 1);
+    assert_eq!((archived.moves[0].seat, archived.moves[0].mv), (0, mv));
+
+    // The seed and the moves are enough to play the game again
+    let players = [
+        Player::default_with_name("Alice".into()),
+        Player::default_with_name("Bob".into()),
+    ];
+    let mut replayed = Game::with_rules(players, Bag::seeded(archived.seed), archived.rules);
+    for recorded in &archived.moves {
+        replayed.apply_move(recorded.seat, recorded.mv).unwrap();
+    }
+    assert_eq!(replayed.zobrist_hash(), played.zobrist_hash());
+}
@@ -1,8 +1,32 @@
 mod bag_test;
+mod chat_test;
+mod client_test;
+mod clock_test;
+mod correspondence_test;
+mod discovery_test;
+mod events_test;
 mod factory_test;
+mod game_test;
+mod grid_test;
 mod helpers;
+mod http_test;
+mod input_test;
+mod journal_test;
+mod keymap_test;
 mod layout_test;
+mod lobby_test;
 mod model_views;
+mod mouse_test;
+mod net_helpers;
 mod panel_test;
+mod peer_test;
+mod reconnect_test;
 mod renderer;
+mod resize_test;
+mod server_test;
+mod spectator_test;
+mod style_test;
+mod telnet_test;
+mod theme_test;
 mod util;
+mod zobrist_test;
@@ -0,0 +1,160 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use azulrs::{
+    model::{
+        bag::Bag, buildingarea::Destination, local::local_players, view::GameView, Game, Pick,
+        TileSource,
+    },
+    visor::{
+        backend::TestBackend,
+        input::{InputSource, TerminalInput},
+        layout::Layout,
+        renderer::RootedRenderer,
+        view::PanelBuilder,
+        Component, Coords, Engine, UserInput,
+    },
+};
+use pretty_assertions::assert_eq;
+
+/// Some text, and what a click on it means
+struct Button(&'static str, UserInput);
+
+impl Component for Button {
+    fn render(&self, writer: &mut RootedRenderer) {
+        writer.write(self.0);
+        writer.on_click(self.1);
+    }
+}
+
+/// Clicks where the screen shows `target`, and returns what the click meant
+fn click(engine: &mut Engine<TestBackend>, target: UserInput) -> UserInput {
+    engine.render();
+    let hitbox = engine
+        .hit_map()
+        .hitboxes()
+        .iter()
+        .find(|hitbox| hitbox.input == target)
+        .unwrap_or_else(|| panic!("{:?} cannot be clicked", target));
+    let (x, y) = hitbox.from.into();
+    engine.resolve(UserInput::Click(x, y))
+}
+
+#[test]
+fn test_layers_know_where_they_are_on_screen() {
+    let panel = PanelBuilder::default()
+        .component(Box::new(Layout::vertical(vec![
+            Box::new(Button("ok", UserInput::Confirm)),
+            Box::new(Button("cancel", UserInput::Back)),
+        ])))
+        .build()
+        .unwrap();
+    let mut engine = Engine::new(TestBackend::default(), Box::new(panel) as Box<_>);
+    engine.render();
+    let boxes: Vec<_> = engine
+        .hit_map()
+        .hitboxes()
+        .iter()
+        .map(|hitbox| (hitbox.from.into(), hitbox.to.into(), hitbox.input))
+        .collect::<Vec<((u16, u16), (u16, u16), UserInput)>>();
+    assert_eq!(
+        boxes,
+        [
+            ((2, 2), (3, 2), UserInput::Confirm),
+            ((2, 3), (7, 3), UserInput::Back),
+        ]
+    );
+    assert_eq!(engine.resolve(UserInput::Click(3, 2)), UserInput::Confirm);
+    assert_eq!(engine.resolve(UserInput::Click(7, 3)), UserInput::Back);
+    // The border, and past the end of "ok"
+    assert_eq!(engine.resolve(UserInput::Click(1, 1)), UserInput::Noop);
+    assert_eq!(engine.resolve(UserInput::Click(4, 2)), UserInput::Noop);
+    // Other inputs go through
+    assert_eq!(engine.resolve(UserInput::Exit), UserInput::Exit);
+}
+
+#[test]
+fn test_the_deepest_layer_wins() {
+    let outer = Layout::vertical(vec![Box::new(Layout::horizontal(vec![Box::new(Button(
+        "inner",
+        UserInput::Confirm,
+    ))]))]);
+    struct Outer<'a>(Layout<'a>);
+    impl Component for Outer<'_> {
+        fn render(&self, writer: &mut RootedRenderer) {
+            writer.write("outer ");
+            writer.on_click(UserInput::Back);
+            writer.render_into_layer("inner", Coords::new(6, 0), &self.0);
+            writer.add_hitbox(Coords::new(8, 1), (1, 1), UserInput::Exit);
+        }
+    }
+    let mut engine = Engine::new(TestBackend::default(), Box::new(Outer(outer)) as Box<_>);
+    engine.render();
+    assert_eq!(engine.get_contents(), "outer inner");
+    assert_eq!(engine.resolve(UserInput::Click(1, 1)), UserInput::Back);
+    assert_eq!(engine.resolve(UserInput::Click(7, 1)), UserInput::Confirm);
+    // The hitbox is shallower than the button drawn over it
+    assert_eq!(engine.resolve(UserInput::Click(8, 1)), UserInput::Confirm);
+}
+
+#[test]
+fn test_clicks_play_a_move() {
+    let game = Rc::new(RefCell::new(Game::for_players_with_bag(
+        local_players(),
+        Bag::seeded(11),
+    )));
+    let view = GameView {
+        game: game.clone(),
+        clocks: None,
+    };
+    let mut engine = Engine::new(TestBackend::default(), Box::new(view) as Box<_>);
+    let source = TileSource::Factory(2.into());
+    let tile = game.borrow().get_factories()[2].get_tiles().unwrap()[0];
+    let mut play = |target: UserInput| {
+        let input = click(&mut engine, target);
+        assert_eq!(input, target);
+        game.borrow_mut().handle(input);
+    };
+
+    // The first click on a tile selects it, the second takes it
+    play(UserInput::Pick(Pick::Tile(source, tile)));
+    assert_eq!(game.borrow().selected_move(), None);
+    play(UserInput::Pick(Pick::Tile(source, tile)));
+    let mv = game.borrow().selected_move().unwrap();
+    assert_eq!((mv.source, mv.tile), (source, tile));
+
+    // The first click on a row selects it, the second puts the tiles there
+    let row = Destination::PatternLine(3);
+    play(UserInput::Pick(Pick::Destination(row)));
+    assert_eq!(game.borrow().selected_move().unwrap().destination, row);
+    play(UserInput::Confirm);
+    assert_eq!(game.borrow().get_current_player_id(), 1);
+    assert_eq!(game.borrow().count_tiles_in(source, tile), 0);
+}
+
+#[test]
+fn test_only_the_rows_of_the_current_player_can_be_clicked() {
+    let game = Game::for_players_with_bag(local_players(), Bag::seeded(11));
+    let view = GameView {
+        game: Rc::new(RefCell::new(game)),
+        clocks: None,
+    };
+    let mut engine = Engine::new(TestBackend::default(), Box::new(view) as Box<_>);
+    engine.render();
+    let destinations = engine
+        .hit_map()
+        .hitboxes()
+        .iter()
+        .filter(|hitbox| matches!(hitbox.input, UserInput::Pick(Pick::Destination(_))))
+        .count();
+    // Five pattern lines and the floor line
+    assert_eq!(destinations, 6);
+}
+
+#[test]
+fn test_the_terminal_reports_clicks() {
+    // A left click at column 5, row 3, then a release that is ignored
+    let mut input = TerminalInput::new(&b"\x1b[<0;5;3M\x1b[<0;5;3mj"[..]);
+    assert_eq!(input.next_input(), Some(UserInput::Click(5, 3)));
+    assert_eq!(input.next_input(), Some(UserInput::from_char('j')));
+}
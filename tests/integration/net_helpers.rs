@@ -0,0 +1,198 @@
+use std::{
+    io::{BufReader, Write},
+    net::{SocketAddr, TcpStream},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use azulrs::{
+    model::{rules::Rules, Game, Move},
+    net::{
+        client,
+        protocol::{
+            read_message, write_message, ClientMessage, GameEvent, ServerMessage, TableId,
+            PROTOCOL_VERSION,
+        },
+        server::{Server, DEFAULT_GRACE_PERIOD},
+    },
+};
+
+pub fn start_server<const N: usize>() -> (SocketAddr, JoinHandle<()>) {
+    start_server_with_grace_period::<N>(DEFAULT_GRACE_PERIOD)
+}
+
+pub fn start_server_with_grace_period<const N: usize>(
+    grace_period: Duration,
+) -> (SocketAddr, JoinHandle<()>) {
+    let server = Server::bind("127.0.0.1:0")
+        .unwrap()
+        .with_grace_period(grace_period);
+    let addr = server.local_addr().unwrap();
+    let handle = thread::spawn(move || server.run().unwrap());
+    client::create_table(addr, N, Rules::default(), None).unwrap();
+    (addr, handle)
+}
+
+/// A client that is driven by the test, one message at a time
+pub struct ScriptedClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Kept until the test asks for it, see [`ScriptedClient::sit_at`]
+    welcome: Option<(usize, String)>,
+}
+
+impl ScriptedClient {
+    pub fn connect(addr: SocketAddr) -> Self {
+        let writer = TcpStream::connect(addr).unwrap();
+        writer
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let reader = BufReader::new(writer.try_clone().unwrap());
+        Self {
+            reader,
+            writer,
+            welcome: None,
+        }
+    }
+
+    /// Joins any open table, and is ready to start right away
+    pub fn join(addr: SocketAddr, name: &str) -> Self {
+        let mut client = Self::sit_at(addr, name, None);
+        client.send(&ClientMessage::Ready);
+        client
+    }
+
+    /// Joins the table and waits for the seat, so clients that join one after the other get
+    /// their seats in that order
+    pub fn sit_at(addr: SocketAddr, name: &str, table: Option<TableId>) -> Self {
+        let mut client = Self::join_table(addr, name, table);
+        client.welcome = Some(client.receive_welcome());
+        client
+    }
+
+    pub fn join_table(addr: SocketAddr, name: &str, table: Option<TableId>) -> Self {
+        let mut client = Self::connect(addr);
+        client.send(&ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            name: name.into(),
+            table,
+        });
+        client
+    }
+
+    pub fn spectate(addr: SocketAddr, name: &str) -> Self {
+        let mut client = Self::connect(addr);
+        client.send(&ClientMessage::Spectate {
+            version: PROTOCOL_VERSION,
+            name: name.into(),
+            table: None,
+        });
+        client
+    }
+
+    pub fn resume(addr: SocketAddr, token: &str) -> Self {
+        let mut client = Self::connect(addr);
+        client.send(&ClientMessage::Resume {
+            version: PROTOCOL_VERSION,
+            token: token.into(),
+        });
+        client
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) {
+        write_message(&mut self.writer, message).unwrap();
+    }
+
+    pub fn send_raw(&mut self, line: &str) {
+        self.writer.write_all(line.as_bytes()).unwrap();
+    }
+
+    pub fn play(&mut self, mv: Move) {
+        self.send(&ClientMessage::Move(mv));
+    }
+
+    #[track_caller]
+    pub fn receive(&mut self) -> ServerMessage {
+        read_message(&mut self.reader)
+            .unwrap()
+            .expect("The server closed the connection")
+    }
+
+    /// Skips messages until one matches
+    #[track_caller]
+    pub fn receive_until<T>(&mut self, mut matcher: impl FnMut(ServerMessage) -> Option<T>) -> T {
+        loop {
+            if let Some(found) = matcher(self.receive()) {
+                return found;
+            }
+        }
+    }
+
+    #[track_caller]
+    pub fn receive_state<const N: usize>(&mut self) -> Game<N> {
+        self.receive_until(|message| match message {
+            ServerMessage::State { game, .. } => Some(Game::from_snapshot(game).unwrap()),
+            _ => None,
+        })
+    }
+
+    /// Returns the seat and the session token
+    #[track_caller]
+    pub fn receive_welcome(&mut self) -> (usize, String) {
+        if let Some(welcome) = self.welcome.take() {
+            return welcome;
+        }
+        self.receive_until(|message| match message {
+            ServerMessage::Welcome { seat, token, .. } => Some((seat, token)),
+            _ => None,
+        })
+    }
+
+    #[track_caller]
+    pub fn receive_event(&mut self) -> GameEvent {
+        self.receive_until(|message| match message {
+            ServerMessage::Event { event } => Some(event),
+            _ => None,
+        })
+    }
+
+    /// Skips events until the expected one
+    #[track_caller]
+    pub fn expect_event(&mut self, expected: GameEvent) {
+        while self.receive_event() != expected {}
+    }
+
+    #[track_caller]
+    pub fn receive_error(&mut self) -> String {
+        self.receive_until(|message| match message {
+            ServerMessage::Error { message } => Some(message),
+            _ => None,
+        })
+    }
+
+    /// The reason the server gives for not playing `mv`
+    #[track_caller]
+    pub fn receive_refusal(&mut self, mv: Move) -> String {
+        self.receive_until(|message| match message {
+            ServerMessage::MoveRefused {
+                mv: refused,
+                message,
+            } => {
+                assert_eq!(refused, mv);
+                Some(message)
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns true if the server closed the connection
+    pub fn is_closed(&mut self) -> bool {
+        loop {
+            match read_message::<_, ServerMessage>(&mut self.reader) {
+                Ok(Some(_)) => continue,
+                Ok(None) => return true,
+                Err(_) => return false,
+            }
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use azulrs::visor::{
     layout::Layout,
-    view::{PanelBuilder, TextView},
+    theme::Theme,
+    view::{Panel, PanelBuilder, TextView},
     Component,
 };
 
-use crate::helpers::{assert_dimensions, expect_component};
+use crate::helpers::{assert_dimensions, expect_component_in};
 use test_case::test_case;
 
 fn textview_in_panel(text: &str) -> Box<dyn Component> {
@@ -15,6 +16,13 @@ fn textview_in_panel(text: &str) -> Box<dyn Component> {
     Box::new(panel) as Box<_>
 }
 
+/// Renders what `panel` builds with box drawing characters, then in ASCII
+#[track_caller]
+fn expect_in_both_modes(panel: impl Fn() -> Panel<'static>, unicode: &str, ascii: &str) {
+    expect_component_in(Theme::default(), panel(), unicode.trim());
+    expect_component_in(Theme::ascii(), panel(), ascii.trim());
+}
+
 #[test_case(
     r#"Hello
 Hello
@@ -25,6 +33,13 @@ Hello"#,
 │Hello│
 │Hello│
 └─────┘
+"#,
+    r#"
++-----+
+|Hello|
+|Hello|
+|Hello|
++-----+
 "#,
     0,
     (7, 5)
@@ -37,6 +52,13 @@ Hello"#,
 │ Hello │
 │       │
 └───────┘
+"#,
+    r#"
++-------+
+|       |
+| Hello |
+|       |
++-------+
 "#,
     1,
 (9, 5)
@@ -44,17 +66,19 @@ Hello"#,
 fn test_panel_with_border(
     text: &str,
     expected_output: &str,
+    expected_ascii: &str,
     padding: u16,
     expected_dimensions: (u16, u16),
 ) {
-    let hello = TextView::new(String::from(text));
-    let panel = PanelBuilder::default()
-        .component(Box::new(hello))
-        .padding(padding)
-        .build()
-        .unwrap();
-    assert_dimensions(&panel, expected_dimensions);
-    expect_component(panel, expected_output.trim());
+    let panel = || {
+        PanelBuilder::default()
+            .component(Box::new(TextView::new(String::from(text))))
+            .padding(padding)
+            .build()
+            .unwrap()
+    };
+    assert_dimensions(&panel(), expected_dimensions);
+    expect_in_both_modes(panel, expected_output, expected_ascii);
 }
 
 #[test_case(
@@ -64,6 +88,11 @@ fn test_panel_with_border(
 ┌| Very long title |┐
 │Hello              │
 └───────────────────┘
+",
+    "
++| Very long title |+
+|Hello              |
++-------------------+
 "
 )]
 #[test_case(
@@ -73,6 +102,11 @@ fn test_panel_with_border(
 ┌───| x |───┐
 │Hello world│
 └───────────┘
+",
+    "
++---| x |---+
+|Hello world|
++-----------+
 "
 )]
 #[test_case(
@@ -82,73 +116,99 @@ fn test_panel_with_border(
 ┌──| xx |───┐
 │Hello world│
 └───────────┘
+",
+    "
++--| xx |---+
+|Hello world|
++-----------+
 "
 )]
-fn test_panel_title(title: &str, content: &str, expected: &str) {
-    let hello = TextView::new(String::from(content));
-    let panel = PanelBuilder::default()
-        .component(Box::new(hello))
-        .name(title)
-        .build()
-        .unwrap();
-    expect_component(panel, expected.trim());
+fn test_panel_title(title: &str, content: &str, expected: &str, expected_ascii: &str) {
+    let panel = || {
+        PanelBuilder::default()
+            .component(Box::new(TextView::new(String::from(content))))
+            .name(title)
+            .build()
+            .unwrap()
+    };
+    expect_in_both_modes(panel, expected, expected_ascii);
 }
 
 #[test]
 fn test_two_panels_horizontally() {
-    let hellos = ["Hello", "Hello"]
-        .into_iter()
-        .map(textview_in_panel)
-        .collect();
-    let panel = PanelBuilder::default()
-        .component(Box::new(Layout::horizontal(hellos)))
-        .build()
-        .unwrap();
+    let panel = || {
+        let hellos = ["Hello", "Hello"]
+            .into_iter()
+            .map(textview_in_panel)
+            .collect();
+        PanelBuilder::default()
+            .component(Box::new(Layout::horizontal(hellos)))
+            .build()
+            .unwrap()
+    };
     let expected = r#"
 ┌──────────────┐
 │┌─────┐┌─────┐│
 ││Hello││Hello││
 │└─────┘└─────┘│
-└──────────────┘"#
-        .trim_start();
-    expect_component(panel, expected);
+└──────────────┘"#;
+    let expected_ascii = r#"
++--------------+
+|+-----++-----+|
+||Hello||Hello||
+|+-----++-----+|
++--------------+"#;
+    expect_in_both_modes(panel, expected, expected_ascii);
 }
 
 #[test]
 fn test_panel_in_layout_in_panel() {
-    let hellos = ["Hello"].into_iter().map(textview_in_panel).collect();
-    let panel = PanelBuilder::default()
-        .component(Box::new(Layout::horizontal(hellos)))
-        .build()
-        .unwrap();
+    let panel = || {
+        let hellos = ["Hello"].into_iter().map(textview_in_panel).collect();
+        PanelBuilder::default()
+            .component(Box::new(Layout::horizontal(hellos)))
+            .build()
+            .unwrap()
+    };
     let expected = r#"
 ┌───────┐
 │┌─────┐│
 ││Hello││
 │└─────┘│
-└───────┘"#
-        .trim_start();
-    expect_component(panel, expected);
+└───────┘"#;
+    let expected_ascii = r#"
++-------+
+|+-----+|
+||Hello||
+|+-----+|
++-------+"#;
+    expect_in_both_modes(panel, expected, expected_ascii);
 }
 
 #[test]
 fn test_panel_in_panel() {
-    let hello = PanelBuilder::default()
-        .component(Box::new(TextView::from("Hello")) as Box<_>)
-        .name("i")
-        .build()
-        .unwrap();
-
-    let panel = PanelBuilder::default()
-        .component(Box::new(hello))
-        .build()
-        .unwrap();
+    let panel = || {
+        let hello = PanelBuilder::default()
+            .component(Box::new(TextView::from("Hello")) as Box<_>)
+            .name("i")
+            .build()
+            .unwrap();
+        PanelBuilder::default()
+            .component(Box::new(hello))
+            .build()
+            .unwrap()
+    };
     let expected = r#"
 ┌───────┐
 │┌| i |┐│
 ││Hello││
 │└─────┘│
-└───────┘"#
-        .trim_start();
-    expect_component(panel, expected);
+└───────┘"#;
+    let expected_ascii = r#"
++-------+
+|+| i |+|
+||Hello||
+|+-----+|
++-------+"#;
+    expect_in_both_modes(panel, expected, expected_ascii);
 }
@@ -0,0 +1,249 @@
+use std::{
+    io::BufReader,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use azulrs::{
+    model::zobrist::ZobristHash,
+    net::{
+        peer::{combine_seeds, commitment, Mesh, PeerEvent, PeerGame, PeerMessage},
+        protocol::{read_message, write_message, PROTOCOL_VERSION},
+    },
+};
+use pretty_assertions::assert_eq;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn listen() -> (TcpListener, SocketAddr) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    (listener, addr)
+}
+
+/// Connects and agrees on the bag in the background, that needs the other peers to take part
+fn spawn_peer(
+    listener: TcpListener,
+    name: &'static str,
+    players: usize,
+    addresses: Vec<SocketAddr>,
+    seed: u64,
+) -> JoinHandle<std::io::Result<(Mesh, u64)>> {
+    thread::spawn(move || {
+        let mut mesh = Mesh::connect(&listener, name, players, &addresses)?;
+        let seed = mesh.agree_on_seed(seed)?;
+        Ok((mesh, seed))
+    })
+}
+
+fn start<const N: usize>(mesh: Mesh, seed: u64) -> (PeerGame<N>, Receiver<PeerEvent>) {
+    let (sender, receiver) = mpsc::channel();
+    (PeerGame::new(mesh, seed, sender).unwrap(), receiver)
+}
+
+fn handle_next<const N: usize>(game: &mut PeerGame<N>, events: &Receiver<PeerEvent>) {
+    game.handle_peer_event(events.recv_timeout(TIMEOUT).unwrap());
+}
+
+/// A peer driven by the test, that can cheat
+struct ScriptedPeer {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl ScriptedPeer {
+    fn connect(addr: SocketAddr, name: &str, players: usize) -> Self {
+        let writer = TcpStream::connect(addr).unwrap();
+        writer.set_read_timeout(Some(TIMEOUT)).unwrap();
+        let reader = BufReader::new(writer.try_clone().unwrap());
+        let mut peer = Self { reader, writer };
+        peer.send(&PeerMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: name.to_owned(),
+            players,
+        });
+        assert!(matches!(peer.receive(), PeerMessage::Hello { .. }));
+        peer
+    }
+
+    fn send(&mut self, message: &PeerMessage) {
+        write_message(&mut self.writer, message).unwrap();
+    }
+
+    fn receive(&mut self) -> PeerMessage {
+        read_message(&mut self.reader).unwrap().unwrap()
+    }
+
+    /// Commits to `seed` but reveals `revealed`
+    fn exchange_seeds(&mut self, seed: u64, revealed: u64) -> u64 {
+        self.send(&PeerMessage::Commit {
+            commitment: commitment(seed, "nonce"),
+        });
+        assert!(matches!(self.receive(), PeerMessage::Commit { .. }));
+        self.send(&PeerMessage::Reveal {
+            seed: revealed,
+            nonce: String::from("nonce"),
+        });
+        match self.receive() {
+            PeerMessage::Reveal { seed, .. } => seed,
+            other => panic!("Expected a seed, got {:?}", other),
+        }
+    }
+}
+
+/// Alice, played by the code under test, against Mallory, played by the test
+fn alice_against_mallory() -> (PeerGame<2>, Receiver<PeerEvent>, ScriptedPeer) {
+    let (listener, addr) = listen();
+    let alice = spawn_peer(listener, "Alice", 2, vec![], 1);
+    let mut mallory = ScriptedPeer::connect(addr, "Mallory", 2);
+    mallory.exchange_seeds(2, 2);
+    let (mesh, seed) = alice.join().unwrap().unwrap();
+    assert_eq!(seed, combine_seeds(&[1, 2]));
+    let (game, events) = start::<2>(mesh, seed);
+    (game, events, mallory)
+}
+
+#[test]
+fn test_peers_agree_on_the_bag() {
+    let (alice_listener, alice_addr) = listen();
+    let (bob_listener, bob_addr) = listen();
+    let (carol_listener, _) = listen();
+    let alice = spawn_peer(alice_listener, "Alice", 3, vec![], 11);
+    let bob = spawn_peer(bob_listener, "Bob", 3, vec![alice_addr], 22);
+    let carol = spawn_peer(carol_listener, "Carol", 3, vec![alice_addr, bob_addr], 33);
+
+    let (alice, alice_seed) = alice.join().unwrap().unwrap();
+    let (bob, bob_seed) = bob.join().unwrap().unwrap();
+    let (carol, carol_seed) = carol.join().unwrap().unwrap();
+    // Seats go by name, whoever connected first
+    assert_eq!((alice.seat(), bob.seat(), carol.seat()), (0, 1, 2));
+    // The seeds are combined in seat order
+    assert_eq!(alice_seed, combine_seeds(&[11, 22, 33]));
+    assert_eq!(bob_seed, alice_seed);
+    assert_eq!(carol_seed, alice_seed);
+}
+
+#[test]
+fn test_peers_check_each_move() {
+    let (alice_listener, alice_addr) = listen();
+    let (bob_listener, _) = listen();
+    let alice = spawn_peer(alice_listener, "Alice", 2, vec![], 1);
+    let bob = spawn_peer(bob_listener, "Bob", 2, vec![alice_addr], 2);
+    let (alice, seed) = alice.join().unwrap().unwrap();
+    let (bob, _) = bob.join().unwrap().unwrap();
+    let (mut alice, alice_events) = start::<2>(alice, seed);
+    let (mut bob, bob_events) = start::<2>(bob, seed);
+    assert_eq!(
+        alice.game.borrow().zobrist_hash(),
+        bob.game.borrow().zobrist_hash()
+    );
+
+    let mv = alice.game.borrow().legal_moves()[0];
+    assert_eq!(bob.play(mv).unwrap_err(), "It is not your turn");
+    alice.play(mv).unwrap();
+    // The move, then Alice's hash
+    handle_next(&mut bob, &bob_events);
+    handle_next(&mut bob, &bob_events);
+    handle_next(&mut alice, &alice_events);
+
+    for peer in [&alice, &bob] {
+        let status = peer.status.borrow();
+        assert_eq!(status.fault, None);
+        assert_eq!(status.turn, 1);
+    }
+    assert_eq!(
+        alice.game.borrow().zobrist_hash(),
+        bob.game.borrow().zobrist_hash()
+    );
+    assert!(bob.is_my_turn());
+}
+
+#[test]
+fn test_seed_must_match_the_commitment() {
+    let (listener, addr) = listen();
+    let alice = spawn_peer(listener, "Alice", 2, vec![], 1);
+    let mut mallory = ScriptedPeer::connect(addr, "Mallory", 2);
+    // Mallory waits for Alice's seed, and picks another one
+    mallory.exchange_seeds(2, 3);
+    let error = alice.join().unwrap().err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Mallory revealed a seed that does not match their commitment"
+    );
+}
+
+#[test]
+fn test_names_must_differ() {
+    let (listener, addr) = listen();
+    let alice = spawn_peer(listener, "Alice", 2, vec![], 1);
+    let _impostor = ScriptedPeer::connect(addr, "Alice", 2);
+    let error = alice.join().unwrap().err().unwrap();
+    assert_eq!(error.to_string(), "Every peer needs a different name");
+}
+
+#[test]
+fn test_desync_is_caught() {
+    let (mut alice, events, mut mallory) = alice_against_mallory();
+    let mv = alice.game.borrow().legal_moves()[0];
+    alice.play(mv).unwrap();
+    assert_eq!(mallory.receive(), PeerMessage::Move(mv));
+    assert!(matches!(
+        mallory.receive(),
+        PeerMessage::StateHash { turn: 1, .. }
+    ));
+
+    mallory.send(&PeerMessage::StateHash {
+        turn: 1,
+        hash: ZobristHash::new(42),
+    });
+    handle_next(&mut alice, &events);
+    assert_eq!(
+        alice.status.borrow().fault.as_deref(),
+        Some("Mallory disagrees about the game after move 1")
+    );
+    assert!(!alice.is_my_turn());
+}
+
+#[test]
+fn test_hashes_for_far_away_moves_are_caught() {
+    let (mut alice, events, mut mallory) = alice_against_mallory();
+    mallory.send(&PeerMessage::StateHash {
+        turn: 1000,
+        hash: ZobristHash::new(42),
+    });
+    handle_next(&mut alice, &events);
+    assert_eq!(
+        alice.status.borrow().fault.as_deref(),
+        Some("Mallory sent the game after move 1000, during move 0")
+    );
+}
+
+#[test]
+fn test_moves_out_of_turn_are_caught() {
+    let (mut alice, events, mut mallory) = alice_against_mallory();
+    let mv = alice.game.borrow().legal_moves()[0];
+    mallory.send(&PeerMessage::Move(mv));
+    handle_next(&mut alice, &events);
+    assert_eq!(
+        alice.status.borrow().fault.as_deref(),
+        Some("Mallory played out of turn")
+    );
+}
+
+#[test]
+fn test_illegal_moves_are_caught() {
+    let (mut alice, events, mut mallory) = alice_against_mallory();
+    let mv = alice.game.borrow().legal_moves()[0];
+    alice.play(mv).unwrap();
+    // Take the same tiles again, they are gone by now
+    mallory.send(&PeerMessage::Move(mv));
+    handle_next(&mut alice, &events);
+    let fault = alice.status.borrow().fault.clone().unwrap();
+    assert!(
+        fault.starts_with("Mallory played an illegal move"),
+        "{}",
+        fault
+    );
+}
@@ -0,0 +1,152 @@
+use std::{sync::mpsc, time::Duration};
+
+use azulrs::{
+    model::Game,
+    net::{
+        client::{Connection, ConnectionStatus, NetworkEvent, RemoteGame},
+        protocol::GameEvent,
+    },
+    visor::{backend::TestBackend, Engine, UserInput},
+};
+
+use crate::net_helpers::{start_server, start_server_with_grace_period, ScriptedClient};
+
+struct Players {
+    addr: std::net::SocketAddr,
+    alice: ScriptedClient,
+    alice_token: String,
+    bob: ScriptedClient,
+    game: Game<2>,
+}
+
+fn start_game_with_grace_period(grace_period: Duration) -> Players {
+    let (addr, _) = start_server_with_grace_period::<2>(grace_period);
+    let mut alice = ScriptedClient::join(addr, "Alice");
+    let (_, alice_token) = alice.receive_welcome();
+    let mut bob = ScriptedClient::join(addr, "Bob");
+    let game = alice.receive_state();
+    let _: Game<2> = bob.receive_state();
+    Players {
+        addr,
+        alice,
+        alice_token,
+        bob,
+        game,
+    }
+}
+
+fn start_game() -> Players {
+    start_game_with_grace_period(Duration::from_secs(60))
+}
+
+#[test]
+fn test_players_get_different_tokens() {
+    let (addr, _) = start_server::<2>();
+    let mut alice = ScriptedClient::join(addr, "Alice");
+    let mut bob = ScriptedClient::join(addr, "Bob");
+    let (_, alice_token) = alice.receive_welcome();
+    let (_, bob_token) = bob.receive_welcome();
+    assert_ne!(alice_token, bob_token);
+}
+
+#[test]
+fn test_resuming_resyncs_the_game() {
+    let Players {
+        addr,
+        mut alice,
+        alice_token,
+        mut bob,
+        game,
+    } = start_game();
+    alice.play(game.legal_moves()[0]);
+    let game: Game<2> = bob.receive_state();
+
+    drop(alice);
+    bob.expect_event(GameEvent::PlayerDisconnected { seat: 0 });
+
+    let mut alice = ScriptedClient::resume(addr, &alice_token);
+    assert_eq!(alice.receive_welcome(), (0, alice_token));
+    let resynced: Game<2> = alice.receive_state();
+    assert_eq!(resynced.zobrist_hash(), game.zobrist_hash());
+    bob.expect_event(GameEvent::PlayerReconnected { seat: 0 });
+}
+
+#[test]
+fn test_game_is_paused_while_a_player_is_away() {
+    let Players {
+        addr,
+        mut alice,
+        alice_token,
+        mut bob,
+        game,
+    } = start_game();
+    alice.play(game.legal_moves()[0]);
+    let game: Game<2> = bob.receive_state();
+
+    drop(alice);
+    bob.expect_event(GameEvent::PlayerDisconnected { seat: 0 });
+    let mv = game.legal_moves()[0];
+    bob.play(mv);
+    assert_eq!(
+        bob.receive_refusal(mv),
+        "The game is paused until Alice reconnects"
+    );
+
+    let _alice = ScriptedClient::resume(addr, &alice_token);
+    bob.expect_event(GameEvent::PlayerReconnected { seat: 0 });
+    bob.play(game.legal_moves()[0]);
+    let game: Game<2> = bob.receive_state();
+    assert_eq!(game.get_current_player_id(), 0);
+}
+
+#[test]
+fn test_seat_is_given_up_after_the_grace_period() {
+    let Players { alice, mut bob, .. } = start_game_with_grace_period(Duration::from_millis(100));
+
+    drop(alice);
+    bob.expect_event(GameEvent::PlayerDisconnected { seat: 0 });
+    bob.expect_event(GameEvent::PlayerLeft { seat: 0 });
+    assert!(bob.is_closed());
+}
+
+#[test]
+fn test_unknown_tokens_are_rejected() {
+    let Players { addr, .. } = start_game();
+    let mut mallory = ScriptedClient::resume(addr, "0123456789abcdef");
+    assert_eq!(mallory.receive_error(), "Unknown session");
+    assert!(mallory.is_closed());
+}
+
+#[test]
+fn test_client_reconnects_and_takes_back_its_seat() {
+    let (addr, _) = start_server::<2>();
+    let connection = Connection::open(addr, "Alice", None).unwrap();
+    let token = connection.token().unwrap().to_owned();
+    let (sender, events) = mpsc::channel::<NetworkEvent>();
+    let mut alice = RemoteGame::<2>::new(connection, sender.clone()).unwrap();
+    alice.handle_input(UserInput::Confirm);
+    let _bob = ScriptedClient::join(addr, "Bob");
+    let wait_for = |alice: &mut RemoteGame<2>, condition: &dyn Fn(&RemoteGame<2>) -> bool| {
+        while !condition(alice) {
+            let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+            alice.handle_network_event(event);
+        }
+    };
+    wait_for(&mut alice, &|r| r.is_my_turn());
+
+    // Somebody else resuming the session kicks out the old connection, as if it had died
+    let mut intruder = ScriptedClient::resume(addr, &token);
+    intruder.receive_welcome();
+    wait_for(&mut alice, &|r| {
+        r.status.borrow().connection == ConnectionStatus::Disconnected
+    });
+    assert!(!alice.is_my_turn());
+
+    alice.reconnect(sender).unwrap();
+    assert!(intruder.is_closed());
+    wait_for(&mut alice, &|r| r.is_my_turn());
+
+    let mut engine = Engine::new(TestBackend::default(), Box::new(alice.view()) as Box<_>);
+    engine.render();
+    assert!(engine.get_contents().contains("Your turn"));
+}
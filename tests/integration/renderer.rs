@@ -1,9 +1,9 @@
 use azulrs::visor::{
-    backend::TestBackend,
+    backend::{DebuggableTerminalBackend, TestBackend},
     layout::Layout,
     renderer::RootedRenderer,
     view::{PanelBuilder, TextView},
-    Component,
+    Component, Coords,
 };
 
 #[test]
@@ -100,3 +100,19 @@ fn test_drawn_area4() {
 
     pretty_assertions::assert_eq!(writer.get_drawn_area_for_active_layer(), (16, 5));
 }
+
+#[test]
+fn test_text_takes_the_place_of_what_was_there() {
+    let mut backend = TestBackend::default();
+    let mut writer = RootedRenderer::default_with_writer(&mut backend);
+    writer.write("Hello World");
+    writer.set_cursor_to(Coords::new(7, 1));
+    writer.write("there");
+    writer.set_cursor_to(Coords::new(3, 2));
+    writer.write("└┘");
+    writer.set_cursor_to(Coords::new(1, 2));
+    writer.write("┌");
+    drop(writer);
+
+    pretty_assertions::assert_eq!(backend.get_contents(), "Hello there\n┌ └┘");
+}
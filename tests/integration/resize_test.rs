@@ -0,0 +1,109 @@
+use azulrs::{
+    model::{bag::Bag, local::local_players, view::GameView, Game},
+    visor::{
+        backend::TestBackend,
+        layout::Layout,
+        renderer::RootedRenderer,
+        view::{PanelBuilder, TextView},
+        Component, Engine,
+    },
+};
+use pretty_assertions::assert_eq;
+use std::{cell::RefCell, rc::Rc};
+
+/// Writes how much room it has
+struct RoomView;
+
+impl Component for RoomView {
+    fn render(&self, writer: &mut RootedRenderer) {
+        let (width, height) = writer.available_size();
+        writer.write(format!("{width}x{height}"));
+    }
+}
+
+fn render<'a, T: Into<Box<dyn Component + 'a>>>(size: (u16, u16), component: T) -> String {
+    let mut engine = Engine::new(TestBackend::with_size(size.0, size.1), component);
+    engine.render();
+    engine.get_contents()
+}
+
+fn hello_panels() -> Layout<'static> {
+    let panels = ["Hello", "World"]
+        .into_iter()
+        .map(|text| {
+            let panel = PanelBuilder::default()
+                .component(Box::new(TextView::from(text)))
+                .build()
+                .unwrap();
+            Box::new(panel) as Box<dyn Component>
+        })
+        .collect();
+    Layout::fitting(panels)
+}
+
+fn game_view() -> Box<dyn Component> {
+    let game = Game::for_players_with_bag(local_players(), Bag::seeded(3));
+    Box::new(GameView {
+        game: Rc::new(RefCell::new(game)),
+        clocks: None,
+    })
+}
+
+fn line_with<'a>(screen: &'a str, text: &str) -> &'a str {
+    screen.lines().find(|line| line.contains(text)).unwrap()
+}
+
+#[test]
+fn test_components_know_how_much_room_they_have() {
+    let panel = PanelBuilder::default()
+        .padding(1)
+        .component(Box::new(RoomView))
+        .build()
+        .unwrap();
+    assert_eq!(line_with(&render((20, 10), panel), "x"), "│ 16x6 │");
+
+    let layout = Layout::vertical(vec![Box::new(TextView::from("Hi")), Box::new(RoomView)]);
+    assert_eq!(render((20, 10), layout), "Hi\n20x9");
+}
+
+#[test]
+fn test_fitting_layouts_stack_when_there_is_no_room() {
+    let side_by_side = "┌─────┐┌─────┐\n│Hello││World│\n└─────┘└─────┘";
+    assert_eq!(render((14, 6), hello_panels()), side_by_side);
+    let stacked = "┌─────┐\n│Hello│\n└─────┘\n┌─────┐\n│World│\n└─────┘";
+    assert_eq!(render((13, 6), hello_panels()), stacked);
+}
+
+#[test]
+fn test_too_small_terminals_get_a_notice() {
+    let screen = render((10, 5), hello_panels());
+    assert_eq!(screen, "Terminal too small: 10x5\nMake it at least 7x6");
+
+    let screen = render((40, 10), game_view());
+    assert!(screen.starts_with("Terminal too small: 40x10\n"));
+}
+
+#[test]
+fn test_narrow_screens_stack_the_board() {
+    let wide = render((40, 100), game_view());
+    assert!(line_with(&wide, "Alice").contains("Bob"));
+    assert!(line_with(&wide, "Factories").contains("Common"));
+
+    let narrow = render((30, 100), game_view());
+    assert!(!line_with(&narrow, "Alice").contains("Bob"));
+    assert!(!line_with(&narrow, "Factories").contains("Common"));
+    assert!(narrow.lines().all(|line| line.chars().count() <= 30));
+}
+
+#[test]
+fn test_board_panels_line_up() {
+    let wide = render((80, 100), game_view());
+    assert!(wide.contains("│┌| Factories |─┐┌──| Common |──┐│"));
+    assert!(wide.contains("│└──────────────┘└──────────────┘│"));
+    assert!(line_with(&wide, "Alice:").contains("│Alice: 0"));
+    assert!(line_with(&wide, "Bob:").contains("│Bob:   0"));
+
+    let narrow = render((30, 100), game_view());
+    assert!(narrow.contains("│┌─| Factories |─┐│"));
+    assert!(narrow.contains("│┌───| Score |───┐│"));
+}
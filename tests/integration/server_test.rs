@@ -0,0 +1,178 @@
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use azulrs::{
+    model::{buildingarea::Destination, rules::Rules, Game, TileSource},
+    net::{
+        client,
+        protocol::{
+            write_message, ClientMessage, GameEvent, ServerMessage, MAX_MESSAGE_LENGTH,
+            MAX_NAME_LENGTH, PROTOCOL_VERSION,
+        },
+    },
+};
+
+use crate::net_helpers::{start_server, ScriptedClient};
+
+fn start_game() -> [ScriptedClient; 2] {
+    let (addr, _) = start_server::<2>();
+    let mut alice = ScriptedClient::join(addr, "Alice");
+    assert_eq!(alice.receive_welcome().0, 0);
+    let mut bob = ScriptedClient::join(addr, "Bob");
+    assert_eq!(bob.receive_welcome().0, 1);
+    [alice, bob]
+}
+
+#[test]
+fn test_players_receive_the_state_when_the_game_starts() {
+    let [mut alice, mut bob] = start_game();
+    let game: Game<2> = alice.receive_state();
+    let names: Vec<_> = game.get_players().iter().map(|p| p.get_name()).collect();
+    assert_eq!(names, ["Alice", "Bob"]);
+    assert_eq!(game.get_current_player_id(), 0);
+
+    let other: Game<2> = bob.receive_state();
+    assert_eq!(game.zobrist_hash(), other.zobrist_hash());
+}
+
+#[test]
+fn test_moves_are_broadcast() {
+    let [mut alice, mut bob] = start_game();
+    let game: Game<2> = alice.receive_state();
+    let mv = game.legal_moves()[0];
+    alice.play(mv);
+
+    let played = bob.receive_until(|message| match message {
+        ServerMessage::Event {
+            event: GameEvent::MovePlayed { seat, mv },
+        } => Some((seat, mv)),
+        _ => None,
+    });
+    assert_eq!(played, (0, mv));
+
+    let game: Game<2> = bob.receive_state();
+    assert_eq!(game.get_current_player_id(), 1);
+}
+
+#[test]
+fn test_moves_are_checked() {
+    let [mut alice, mut bob] = start_game();
+    let game: Game<2> = alice.receive_state();
+    let mv = game.legal_moves()[0];
+
+    bob.play(mv);
+    assert!(bob.receive_refusal(mv).contains("not the turn"));
+
+    let mut illegal = mv;
+    illegal.source = TileSource::Factory(42.into());
+    alice.play(illegal);
+    assert!(alice.receive_refusal(illegal).contains("cannot pick"));
+
+    let mut illegal = mv;
+    illegal.destination = Destination::PatternLine(7);
+    alice.play(illegal);
+    assert!(alice.receive_refusal(illegal).contains("cannot put"));
+
+    alice.play(mv);
+    let game: Game<2> = alice.receive_state();
+    assert_eq!(game.get_current_player_id(), 1);
+}
+
+#[test]
+fn test_wrong_protocol_version_is_rejected() {
+    let (addr, _) = start_server::<2>();
+    let mut client = ScriptedClient::connect(addr);
+    client.send(&ClientMessage::Join {
+        version: PROTOCOL_VERSION + 1,
+        name: "Mallory".into(),
+        table: None,
+    });
+    assert!(client
+        .receive_error()
+        .contains("Unsupported protocol version"));
+    assert!(client.is_closed());
+}
+
+#[test]
+fn test_garbage_is_rejected() {
+    let (addr, _) = start_server::<2>();
+    let mut client = ScriptedClient::connect(addr);
+    client.send_raw("hello\n");
+    assert!(client.receive_error().contains("Could not parse"));
+}
+
+#[test]
+fn test_names_are_cleaned_up() {
+    let (addr, _) = start_server::<2>();
+    let mut alice = ScriptedClient::join(addr, " \u{1b}[2JAlice\u{7} ");
+    alice.expect_event(GameEvent::PlayerJoined {
+        seat: 0,
+        name: "[2JAlice".into(),
+    });
+}
+
+#[test]
+fn test_bad_names_are_rejected() {
+    let (addr, _) = start_server::<2>();
+    let mut client = ScriptedClient::join_table(addr, &"a".repeat(MAX_NAME_LENGTH + 1), None);
+    assert!(client.receive_error().contains("at most 20 characters"));
+    let mut client = ScriptedClient::spectate(addr, "\u{1b}");
+    assert!(client.receive_error().contains("cannot be empty"));
+
+    // Neither of them took a seat
+    let mut alice = ScriptedClient::sit_at(addr, "Alice", None);
+    assert_eq!(alice.receive_welcome().0, 0);
+}
+
+#[test]
+fn test_overlong_lines_close_the_connection() {
+    let (addr, _) = start_server::<2>();
+    let mut client = ScriptedClient::connect(addr);
+    client.send_raw(&"a".repeat(MAX_MESSAGE_LENGTH + 1));
+    assert!(client.is_closed());
+}
+
+#[test]
+fn test_clients_that_do_not_read_are_cut_off() {
+    let (addr, _) = start_server::<2>();
+    for _ in 0..50 {
+        client::create_table(addr, 2, Rules::default(), None).unwrap();
+    }
+
+    // Asks for the tables over and over, and never reads the answers
+    let mut request = Vec::new();
+    write_message(&mut request, &ClientMessage::ListTables).unwrap();
+    let mut sloth = TcpStream::connect(addr).unwrap();
+    for _ in 0..20_000 {
+        if sloth.write_all(&request).is_err() {
+            break;
+        }
+    }
+
+    // The others are still served
+    let mut alice = ScriptedClient::connect(addr);
+    alice.send(&ClientMessage::ListTables);
+    alice.receive_until(|message| match message {
+        ServerMessage::Tables { tables } => Some(tables),
+        _ => None,
+    });
+
+    // And the connection of the one that did not read was closed
+    sloth
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut buf = [0; 1 << 16];
+    loop {
+        match sloth.read(&mut buf) {
+            Ok(0) => break,
+            Ok(_) => continue,
+            Err(e) => {
+                assert_eq!(e.kind(), io::ErrorKind::ConnectionReset);
+                break;
+            }
+        }
+    }
+}
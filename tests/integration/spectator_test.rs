@@ -0,0 +1,110 @@
+use std::{sync::mpsc, time::Duration};
+
+use azulrs::{
+    model::Game,
+    net::{
+        client::{Connection, RemoteGame},
+        protocol::{GameEvent, ServerMessage},
+    },
+    visor::{backend::TestBackend, Engine, UserInput},
+};
+
+use crate::net_helpers::{start_server, ScriptedClient};
+
+fn expect_spectator_count(client: &mut ScriptedClient) -> usize {
+    client.receive_until(|message| match message {
+        ServerMessage::Event {
+            event: GameEvent::SpectatorsChanged { count },
+        } => Some(count),
+        _ => None,
+    })
+}
+
+#[test]
+fn test_spectators_catch_up_on_a_running_game() {
+    let (addr, _) = start_server::<2>();
+    let mut alice = ScriptedClient::join(addr, "Alice");
+    let _bob = ScriptedClient::join(addr, "Bob");
+    let game: Game<2> = alice.receive_state();
+    alice.play(game.legal_moves()[0]);
+    let game: Game<2> = alice.receive_state();
+
+    let mut carol = ScriptedClient::spectate(addr, "Carol");
+    match carol.receive() {
+        ServerMessage::Spectating { players, .. } => assert_eq!(players, 2),
+        _ => panic!("Expected a spectating message"),
+    }
+    let watched: Game<2> = carol.receive_state();
+    assert_eq!(watched.zobrist_hash(), game.zobrist_hash());
+    assert_eq!(watched.get_current_player_id(), 1);
+}
+
+#[test]
+fn test_spectators_cannot_make_moves() {
+    let (addr, _) = start_server::<2>();
+    let mut alice = ScriptedClient::join(addr, "Alice");
+    let mut bob = ScriptedClient::join(addr, "Bob");
+    let mut carol = ScriptedClient::spectate(addr, "Carol");
+    let game: Game<2> = carol.receive_state();
+
+    let mv = game.legal_moves()[0];
+    carol.play(mv);
+    assert_eq!(carol.receive_refusal(mv), "Spectators cannot make moves");
+
+    // The game goes on, and the spectator sees the moves
+    alice.play(game.legal_moves()[0]);
+    let played = carol.receive_until(|message| match message {
+        ServerMessage::Event {
+            event: GameEvent::MovePlayed { seat, .. },
+        } => Some(seat),
+        _ => None,
+    });
+    assert_eq!(played, 0);
+    let game: Game<2> = bob.receive_state();
+    assert_eq!(game.get_current_player_id(), 0);
+    let game: Game<2> = bob.receive_state();
+    assert_eq!(game.get_current_player_id(), 1);
+}
+
+#[test]
+fn test_players_see_how_many_spectators_are_watching() {
+    let (addr, _) = start_server::<2>();
+    let mut alice = ScriptedClient::join(addr, "Alice");
+    let carol = ScriptedClient::spectate(addr, "Carol");
+    assert_eq!(expect_spectator_count(&mut alice), 1);
+    let _dave = ScriptedClient::spectate(addr, "Dave");
+    assert_eq!(expect_spectator_count(&mut alice), 2);
+
+    drop(carol);
+    assert_eq!(expect_spectator_count(&mut alice), 1);
+}
+
+#[test]
+fn test_watching_with_the_client() {
+    let (addr, _) = start_server::<2>();
+    let _alice = ScriptedClient::join(addr, "Alice");
+    let _bob = ScriptedClient::join(addr, "Bob");
+
+    let connection = Connection::spectate(addr, "Carol", None).unwrap();
+    assert_eq!(connection.seat(), None);
+    let (sender, events) = mpsc::channel();
+    let mut remote = RemoteGame::<2>::new(connection, sender).unwrap();
+    while !remote.status.borrow().game_started || remote.status.borrow().spectators == 0 {
+        let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+        remote.handle_network_event(event);
+    }
+
+    assert!(!remote.is_my_turn());
+    let hash = remote.game.borrow().zobrist_hash();
+    for _ in 0..3 {
+        remote.handle_input(UserInput::Confirm);
+    }
+    assert!(remote.status.borrow().pending_move.is_none());
+    assert_eq!(remote.game.borrow().zobrist_hash(), hash);
+
+    let mut engine = Engine::new(TestBackend::default(), Box::new(remote.view()) as Box<_>);
+    engine.render();
+    let contents = engine.get_contents();
+    assert!(contents.contains("Spectating, Alice to move"));
+    assert!(contents.contains("1 spectator is watching"));
+}
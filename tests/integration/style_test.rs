@@ -0,0 +1,128 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use azulrs::{
+    model::{
+        buildingarea::patternline::{PatternLine, PatternLineView},
+        view::{FactoryView, TileView},
+        Factory, Tile,
+    },
+    visor::{
+        backend::{DebuggableTerminalBackend, TermionBackend, TestBackend},
+        renderer::RootedRenderer,
+        style::{Color, Span, Style},
+        Component, Coords, Engine,
+    },
+};
+use pretty_assertions::assert_eq;
+
+/// Plain text around a bold red word
+struct Warning;
+
+impl Component for Warning {
+    fn render(&self, writer: &mut RootedRenderer) {
+        writer.write("a ");
+        writer.write(Span::styled("big", Style::default().fg(Color::Red).bold()));
+        writer.write(" deal");
+    }
+}
+
+/// What the terminal was sent
+#[derive(Clone, Default)]
+struct Sink(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn render<'a, T: Into<Box<dyn Component + 'a>>>(component: T) -> Engine<'a, TestBackend> {
+    let mut engine = Engine::new(TestBackend::default(), component);
+    engine.render();
+    engine
+}
+
+/// The first letter of the color of every character on the first line, `.` without one
+fn colors(engine: &Engine<TestBackend>) -> String {
+    let width = engine
+        .get_contents()
+        .lines()
+        .next()
+        .unwrap()
+        .chars()
+        .count() as u16;
+    (1..=width)
+        .map(|x| match engine.style_at(Coords::new(x, 1)).fg {
+            Some(color) => format!("{:?}", color).chars().next().unwrap(),
+            None => '.',
+        })
+        .collect()
+}
+
+#[test]
+fn test_the_text_stays_plain_and_the_styles_are_kept_aside() {
+    let engine = render(Box::new(Warning) as Box<_>);
+    assert_eq!(engine.get_contents(), "a big deal");
+    assert_eq!(colors(&engine), "..RRR.....");
+    assert!(engine.style_at(Coords::new(3, 1)).bold);
+    assert!(engine.style_at(Coords::new(1, 1)).is_plain());
+    // Past the end of the screen, nothing is styled
+    assert!(engine.style_at(Coords::new(40, 9)).is_plain());
+}
+
+#[test]
+fn test_the_terminal_gets_ansi_codes() {
+    let sink = Sink::default();
+    let mut engine = Engine::new(
+        TermionBackend::new(Box::new(sink.clone())),
+        Box::new(Warning) as Box<_>,
+    );
+    engine.render();
+    let sent = String::from_utf8(sink.0.borrow().clone()).unwrap();
+    let big = format!(
+        "{}{}big{}",
+        termion::color::Fg(termion::color::Red),
+        termion::style::Bold,
+        termion::style::Reset
+    );
+    assert!(sent.contains(&format!("a {} deal", big)), "{:?}", sent);
+}
+
+#[test]
+fn test_tiles_have_their_colors() {
+    let factory = Factory::new([Tile::Yellow, Tile::Green, Tile::Yellow, Tile::White]);
+    let engine = render(FactoryView::new(&factory, Some(Tile::Yellow), false));
+    assert_eq!(engine.get_contents(), "GW|YY|");
+    assert_eq!(colors(&engine), "GW.YY.");
+
+    let engine = render(TileView::new(Tile::FirstPlayer, false));
+    assert_eq!(engine.style_at(Coords::new(1, 1)), Style::default().bold());
+
+    let line = PatternLine::new_taken(Tile::Blue, 4, 2);
+    let engine = render(PatternLineView::new(&line, false));
+    assert_eq!(engine.get_contents(), "    ☐☐BB");
+    assert_eq!(colors(&engine), "......BB");
+}
+
+#[test]
+fn test_styles_are_overwritten_with_the_text_they_belong_to() {
+    let mut backend = TestBackend::default();
+    let red = Style::default().fg(Color::Red);
+    let mut writer = RootedRenderer::default_with_writer(&mut backend);
+    writer.write(Span::styled("RRRR", red));
+    writer.set_cursor_to(Coords::new(2, 1));
+    writer.write("pp");
+    writer.set_cursor_to(Coords::new(1, 2));
+    writer.write("plain");
+    drop(writer);
+    assert_eq!(backend.get_contents(), "RppR\nplain");
+    assert_eq!(
+        backend.styles()[0],
+        [red, Style::default(), Style::default(), red]
+    );
+    assert_eq!(backend.styles()[1], [Style::default(); 5]);
+}
@@ -0,0 +1,160 @@
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use azulrs::net::telnet::TelnetGateway;
+
+use crate::net_helpers::start_server;
+
+fn start_gateway() -> SocketAddr {
+    let (server, _) = start_server::<2>();
+    let gateway = TelnetGateway::bind("127.0.0.1:0", server).unwrap();
+    let addr = gateway.local_addr().unwrap();
+    thread::spawn(move || gateway.run().unwrap());
+    addr
+}
+
+/// Somebody at a terminal, as far as the gateway can tell
+struct TelnetClient {
+    stream: TcpStream,
+    /// What was received and not looked at yet
+    unread: Vec<u8>,
+}
+
+impl TelnetClient {
+    fn connect(addr: SocketAddr) -> Self {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        Self {
+            stream,
+            unread: vec![],
+        }
+    }
+
+    fn type_keys(&mut self, keys: &str) {
+        self.stream.write_all(keys.as_bytes()).unwrap();
+    }
+
+    /// Returns everything received up to and including `expected`
+    fn receive_until(&mut self, expected: &str) -> Vec<u8> {
+        let expected = expected.as_bytes();
+        loop {
+            if let Some(at) = self
+                .unread
+                .windows(expected.len())
+                .position(|w| w == expected)
+            {
+                return self.unread.drain(..at + expected.len()).collect();
+            }
+            let mut buf = [0; 4096];
+            let read = self.stream.read(&mut buf).unwrap();
+            assert!(
+                read > 0,
+                "Closed before {:?} arrived, got {}",
+                String::from_utf8_lossy(expected),
+                String::from_utf8_lossy(&self.unread)
+            );
+            self.unread.extend_from_slice(&buf[..read]);
+        }
+    }
+
+    fn is_closed(&mut self) -> bool {
+        let mut buf = [0; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return true,
+                Ok(_) => continue,
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+fn sign_in(addr: SocketAddr, name: &str) -> TelnetClient {
+    let mut client = TelnetClient::connect(addr);
+    client.receive_until("What is your name? ");
+    client.type_keys(&format!("{name}\r\n"));
+    client.receive_until("Press enter when you are ready");
+    client
+}
+
+#[test]
+fn test_gateway_negotiates_and_asks_for_a_name() {
+    let addr = start_gateway();
+    let mut client = TelnetClient::connect(addr);
+    let received = client.receive_until("What is your name? ");
+    // WILL ECHO, WILL SUPPRESS-GO-AHEAD
+    assert_eq!(received[..6], [255, 251, 1, 255, 251, 3]);
+
+    // The client answers the negotiation, which must not end up in the name
+    client
+        .stream
+        .write_all(&[255, 253, 1, 255, 253, 3])
+        .unwrap();
+    client.type_keys("Bobb\x7f\r\n");
+    // Typed characters are echoed, and erased again
+    let echoed = client.receive_until("Looking for a table");
+    assert_eq!(
+        String::from_utf8_lossy(&echoed),
+        "Bobb\x08 \x08\r\nLooking for a table"
+    );
+    let board = client.receive_until("Press enter when you are ready");
+    let board = String::from_utf8_lossy(&board);
+    assert!(board.contains("\x1b[2J"));
+    assert!(board.contains("Connected to"));
+}
+
+#[test]
+fn test_the_board_fits_the_window() {
+    let addr = start_gateway();
+    let mut client = TelnetClient::connect(addr);
+    let received = client.receive_until("What is your name? ");
+    // DO NAWS
+    assert_eq!(received[6..9], [255, 253, 31]);
+
+    // WILL NAWS, and a window of 30x10
+    client
+        .stream
+        .write_all(&[255, 251, 31, 255, 250, 31, 0, 30, 0, 10, 255, 240])
+        .unwrap();
+    client.type_keys("Alice\r\n");
+    client.receive_until("Terminal too small: 30x10");
+
+    // Drawn again once the window is big enough
+    client
+        .stream
+        .write_all(&[255, 250, 31, 0, 200, 0, 60, 255, 240])
+        .unwrap();
+    client.receive_until("Press enter when you are ready");
+}
+
+#[test]
+fn test_two_telnet_players_play_a_move() {
+    let addr = start_gateway();
+    let mut alice = sign_in(addr, "Alice");
+    let mut bob = sign_in(addr, "Bob");
+
+    alice.type_keys("\r");
+    bob.type_keys("\r");
+    alice.receive_until("Your turn");
+    bob.receive_until("Waiting for Alice");
+
+    // Pick the first factory, the first color, and the first row
+    alice.type_keys("\r\r\r");
+    alice.receive_until("Waiting for Bob");
+    bob.receive_until("Your turn");
+}
+
+#[test]
+fn test_quitting_hangs_up() {
+    let addr = start_gateway();
+    let mut alice = sign_in(addr, "Alice");
+    alice.type_keys("q");
+    alice.receive_until("Bye!");
+    assert!(alice.is_closed());
+}
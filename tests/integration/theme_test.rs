@@ -0,0 +1,157 @@
+use azulrs::{
+    model::{
+        buildingarea::patternline::{PatternLine, PatternLineView},
+        view::FactoryView,
+        Factory, Tile,
+    },
+    visor::{
+        backend::TestBackend,
+        style::{Color, Style},
+        theme::{utf8_locale, Glyph, Theme},
+        view::{PanelBuilder, TextView},
+        Component, Coords, Engine,
+    },
+};
+use pretty_assertions::assert_eq;
+
+fn render<'a, T: Into<Box<dyn Component + 'a>>>(
+    theme: Theme,
+    component: T,
+) -> Engine<'a, TestBackend> {
+    let mut engine = Engine::new(TestBackend::default(), component).with_theme(theme);
+    engine.render();
+    engine
+}
+
+fn factory() -> Factory {
+    Factory::new([Tile::Yellow, Tile::Green, Tile::Yellow, Tile::Blue])
+}
+
+#[test]
+fn test_high_contrast_changes_markers_and_borders() {
+    let factory = factory();
+    let engine = render(
+        Theme::high_contrast(),
+        FactoryView::new(&factory, Some(Tile::Yellow), true),
+    );
+    assert_eq!(engine.get_contents(), "==> BG[YY]");
+    let yellow = engine.style_at(Coords::new(8, 1));
+    assert_eq!(yellow.bg, Some(Color::Yellow));
+    assert!(yellow.bold);
+
+    let panel = PanelBuilder::default()
+        .name("Hi")
+        .component(Box::new(TextView::from("Hello")))
+        .build()
+        .unwrap();
+    let expected = "┏┫ Hi ┣━┓\n┃Hello  ┃\n┗━━━━━━━┛";
+    assert_eq!(
+        render(Theme::high_contrast(), panel).get_contents(),
+        expected
+    );
+}
+
+#[test]
+fn test_colorblind_tiles_have_shapes() {
+    let factory = factory();
+    let engine = render(Theme::colorblind(), FactoryView::new(&factory, None, false));
+    assert_eq!(engine.get_contents(), "●▲◆◆");
+    assert_eq!(
+        engine.style_at(Coords::new(1, 1)).fg,
+        Some(Color::Rgb(0, 114, 178))
+    );
+
+    let line = PatternLine::new_taken(Tile::Red, 3, 1);
+    let engine = render(Theme::colorblind(), PatternLineView::new(&line, true));
+    assert_eq!(engine.get_contents(), "  -> ☐☐■");
+}
+
+#[test]
+fn test_a_theme_file_changes_only_what_it_says() {
+    let theme = Theme::from_json(
+        r#"{
+            "tiles": {"blue": {"glyph": "b", "style": {"fg": "cyan", "bold": true}}},
+            "empty_slot": {"glyph": "_"},
+            "markers": {"row": "> "}
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        theme.tile(Tile::Blue),
+        Glyph::new('b', Style::default().fg(Color::Cyan).bold())
+    );
+    assert_eq!(theme.tile(Tile::Red), Theme::default().tile(Tile::Red));
+    assert_eq!(theme.markers.source, "--> ");
+
+    let line = PatternLine::new_taken(Tile::Blue, 4, 1);
+    let engine = render(theme.clone(), PatternLineView::new(&line, true));
+    assert_eq!(engine.get_contents(), "  > ___b");
+
+    let factory = factory();
+    let engine = render(theme, FactoryView::new(&factory, None, false));
+    assert_eq!(engine.get_contents(), "bGYY");
+}
+
+#[test]
+fn test_presets_and_invalid_themes() {
+    for name in Theme::PRESETS {
+        assert!(Theme::preset(name).is_ok());
+    }
+    assert_eq!(Theme::preset("default"), Ok(Theme::default()));
+    assert_eq!(
+        Theme::preset("neon"),
+        Err(String::from(
+            "Unknown theme neon, expected default, high-contrast, colorblind, ascii"
+        ))
+    );
+    assert!(Theme::from_json(r#"{"empty_slot": {"glyph": "too long"}}"#)
+        .unwrap_err()
+        .starts_with("Invalid theme"));
+    assert!(Theme::load("/nonexistent/theme.json")
+        .unwrap_err()
+        .starts_with("Cannot read the theme"));
+}
+
+#[test]
+fn test_ascii_keeps_the_styles_of_a_theme() {
+    let theme = Theme::colorblind().to_ascii();
+    assert_eq!(
+        theme.tile(Tile::Blue),
+        Glyph::new('B', Style::default().fg(Color::Rgb(0, 114, 178)))
+    );
+    let line = PatternLine::new_taken(Tile::Red, 3, 1);
+    assert_eq!(
+        render(theme, PatternLineView::new(&line, true)).get_contents(),
+        "  -> ..R"
+    );
+
+    let theme = Theme::high_contrast().to_ascii();
+    assert_eq!(theme.markers.open, '[');
+    assert_eq!(theme.borders.title_open, '|');
+    let panel = PanelBuilder::default()
+        .name("Hi")
+        .component(Box::new(TextView::from("Hello")))
+        .build()
+        .unwrap();
+    assert_eq!(
+        render(theme, panel).get_contents(),
+        "+| Hi |-+\n|Hello  |\n+-------+"
+    );
+}
+
+#[test]
+fn test_utf8_locale() {
+    let locale = |vars: &'static [(&'static str, &'static str)]| {
+        utf8_locale(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| String::from(*value))
+        })
+    };
+    assert!(locale(&[("LANG", "en_US.UTF-8")]));
+    assert!(locale(&[("LC_CTYPE", "de_DE.utf8"), ("LANG", "C")]));
+    assert!(!locale(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")]));
+    assert!(locale(&[("LC_ALL", ""), ("LANG", "en_US.UTF-8")]));
+    assert!(!locale(&[("LANG", "POSIX")]));
+    assert!(!locale(&[]));
+}
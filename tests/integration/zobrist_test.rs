@@ -0,0 +1,59 @@
+use azulrs::model::{bag::Bag, player::Player, Game, Tile};
+use azulrs::visor::UserInput;
+
+fn new_game() -> Game<2> {
+    let tiles = [
+        Tile::Yellow,
+        Tile::Red,
+        Tile::Blue,
+        Tile::Green,
+        Tile::White,
+    ]
+    .repeat(20);
+    let players = [
+        Player::default_with_name("Alice".into()),
+        Player::default_with_name("Bob".into()),
+    ];
+    Game::for_players_with_bag(players, Bag::new(tiles, vec![], 0))
+}
+
+// Picks the first source, the first tile in it and the first row that can take it
+fn play_first_move(game: &mut Game<2>) {
+    for _ in 0..3 {
+        game.handle(UserInput::Confirm);
+    }
+}
+
+#[test]
+fn test_incremental_hash_matches_recomputed_hash() {
+    let mut game = new_game();
+    assert_eq!(game.zobrist_hash(), game.compute_zobrist_hash());
+
+    for _ in 0..20 {
+        let before = game.zobrist_hash();
+        play_first_move(&mut game);
+        assert_ne!(before, game.zobrist_hash());
+        pretty_assertions::assert_eq!(game.zobrist_hash(), game.compute_zobrist_hash());
+    }
+}
+
+#[test]
+fn test_same_position_hashes_the_same() {
+    let mut first = new_game();
+    let mut second = new_game();
+    assert_eq!(first.zobrist_hash(), second.zobrist_hash());
+
+    play_first_move(&mut first);
+    assert_ne!(first.zobrist_hash(), second.zobrist_hash());
+    play_first_move(&mut second);
+    assert_eq!(first.zobrist_hash(), second.zobrist_hash());
+}
+
+#[test]
+fn test_selection_does_not_change_hash() {
+    let mut game = new_game();
+    let before = game.zobrist_hash();
+    game.handle(UserInput::Confirm);
+    game.handle(UserInput::Direction(azulrs::model::Direction::Next));
+    assert_eq!(before, game.zobrist_hash());
+}